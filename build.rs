@@ -1,5 +1,3 @@
-use embed_resource;
-
 fn main() {
     embed_resource::compile("resources.rc", embed_resource::NONE).manifest_optional().unwrap();
 }
\ No newline at end of file
@@ -0,0 +1,685 @@
+use crate::settings::{MessageSizeUnit, Settings};
+use crate::setup_utils;
+use crate::sharing_session::SharingSession;
+
+use clap::{Args, Parser, Subcommand};
+use folder_to_ai::{core_utils, git_changes, reply_edits, ExportFormat, FolderInfo, FolderScanner, FolderSnapshot, GitChangeSet, GitChanges, MessageTemplates, OutputFormat, Reassembly, RenderedMessages, ScanExport, TreeOverviewPlacement};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+use std::process::ExitCode;
+
+#[derive(Parser)]
+#[command(name = "FolderToAI", version, about = "Generates messages describing a folder's contents, to be sent to AI chats")]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<CliCommand>,
+
+    /// Folder to open in the GUI (this is how the Windows Explorer context menu invokes FolderToAI)
+    pub folder: Option<String>
+}
+
+#[derive(Subcommand)]
+pub enum CliCommand {
+    /// Opens the GUI for the given folder
+    Gui {
+        /// Folder to be scanned
+        folder: String
+    },
+    /// Scans a folder without opening the GUI and writes the generated messages to stdout or to numbered files
+    Messages(Box<MessagesArgs>),
+    /// Checks a transcript of a chat against the messages last generated for the folder: reports messages that are missing,
+    /// duplicated, out of order, altered or from another session
+    Verify {
+        /// Folder the messages were generated for
+        folder: String,
+        /// File with the transcript; if omitted, it is read from stdin
+        transcript: Option<String>
+    },
+    /// Rebuilds the files of a folder from a transcript of its messages (in plain text, with the default templates), and reports
+    /// missing messages or parts and checksum mismatches
+    Reassemble {
+        /// File with the transcript; if omitted, it is read from stdin
+        transcript: Option<String>,
+        /// Directory in which the files are rebuilt
+        #[arg(short, long)]
+        output_dir: String
+    },
+    /// Applies the file edits in an assistant's reply (whole files, as in the messages, or unified diffs) to a folder
+    Apply(ApplyArgs)
+}
+
+#[derive(Args)]
+pub struct ApplyArgs {
+    /// Folder to apply the edits to
+    pub folder: String,
+
+    /// File with the reply; if omitted, it is read from stdin
+    pub reply: Option<String>,
+
+    /// Only show what would change in each file, without writing anything
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Directory in which the files are backed up before being changed; defaults to the backups directory of FolderToAI
+    #[arg(long, value_name = "DIR")]
+    pub backup_dir: Option<String>,
+
+    /// Do not back the files up before changing them
+    #[arg(long, conflicts_with = "backup_dir")]
+    pub no_backup: bool
+}
+
+#[derive(Args)]
+pub struct MessagesArgs {
+    /// Folder to be scanned
+    pub folder: String,
+
+    /// Directory in which the messages are written as numbered files; if omitted, they are printed to stdout
+    #[arg(short, long)]
+    pub output_dir: Option<String>,
+
+    /// Instead of the messages, write a machine-readable export of the scan and the messages (to stdout, or to the output directory
+    /// as export.json or export.jsonl)
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    pub export: Option<ExportFormatArg>,
+
+    /// Task to carry out with the files (e.g. "Review for bugs"), sent in a closing message that the first one asks to wait for
+    #[arg(long, value_name = "PROMPT", conflicts_with = "task_preset")]
+    pub task: Option<String>,
+
+    /// Name of a task prompt preset (from the settings) to send in the closing message
+    #[arg(long, value_name = "NAME")]
+    pub task_preset: Option<String>,
+
+    /// Put everything in one message, whose maximum size (--chunk-size) then acts as a safety limit
+    #[arg(long)]
+    pub single_message: bool,
+
+    /// Cut messages wherever they fill up, instead of keeping files whole when possible and splitting them only at line boundaries
+    #[arg(long)]
+    pub split_anywhere: bool,
+
+    /// Leave the session ID and the checksum out of message headers
+    #[arg(long)]
+    pub no_integrity_markers: bool,
+
+    /// Ask in the first message for changes to the files as whole files or unified diffs, so that the reply can be applied with "apply"
+    #[arg(long)]
+    pub request_reply_format: bool,
+
+    /// Only send the files added, modified or deleted since the folder was last shared, as an update to earlier context
+    #[arg(long)]
+    pub changes_only: bool,
+
+    /// With --changes-only, send modified files in full instead of as unified diffs
+    #[arg(long)]
+    pub changed_files_in_full: bool,
+
+    /// Only send the changes of the folder's git repository (with their diff and the full content of the touched files): unstaged
+    /// changes, staged changes, or the commits from --git-base to HEAD
+    #[arg(long, value_enum, value_name = "CHANGES")]
+    pub git: Option<GitChangesArg>,
+
+    /// Base revision of the commits to share with --git range (e.g. "main" or a commit hash)
+    #[arg(long, value_name = "REVISION", required_if_eq("git", "range"), value_parser = parse_git_base)]
+    pub git_base: Option<String>,
+
+    /// Where to include a tree-style listing of the folder; defaults to the one in the settings
+    #[arg(long, value_enum, value_name = "PLACEMENT")]
+    pub tree: Option<TreePlacementArg>,
+
+    /// Format of the messages; defaults to the one of the profile
+    #[arg(long, value_enum)]
+    pub format: Option<FormatArg>,
+
+    /// JSON file with the templates that word the messages (as saved from the GUI); defaults to the ones in the settings
+    #[arg(long, value_name = "FILE")]
+    pub templates: Option<String>,
+
+    /// Name of the profile (from the settings) whose message size, context budget and output format are used; defaults to the selected one
+    #[arg(long)]
+    pub profile: Option<String>,
+
+    /// Maximum size of each message, in the unit given by --unit; defaults to the one of the profile
+    #[arg(long, value_parser = parse_chunk_size)]
+    pub chunk_size: Option<usize>,
+
+    /// Generate the messages even if together they exceed the context budget of the profile
+    #[arg(long)]
+    pub no_context_budget: bool,
+
+    /// Unit in which the size of messages is measured; defaults to the one of the profile
+    #[arg(long, value_enum)]
+    pub unit: Option<MessageSizeUnit>,
+
+    /// Vocabulary file (tiktoken format, e.g. cl100k_base.tiktoken) used to count tokens; defaults to the one in the settings
+    #[arg(long, value_name = "FILE")]
+    pub vocabulary: Option<String>,
+
+    /// Maximum size (in bytes) of the files whose contents are loaded; defaults to the one in the settings
+    #[arg(long)]
+    pub file_size_limit: Option<usize>,
+
+    /// Name of a subfolder to ignore, in addition to the ones in the settings (may be repeated)
+    #[arg(long = "ignore", value_name = "SUBFOLDER")]
+    pub ignored_subfolders: Vec<String>,
+
+    /// Ignore only the subfolders passed with --ignore, instead of adding them to the ones in the settings
+    #[arg(long)]
+    pub replace_ignores: bool,
+
+    /// Glob a file must match to be loaded, relative to the folder, e.g. "src/**/*.rs"; a leading "!" excludes instead (may be repeated)
+    #[arg(long = "include", value_name = "GLOB")]
+    pub included_file_globs: Vec<String>,
+
+    /// Glob whose matching files are ignored, relative to the folder, e.g. "**/*_test.go" (may be repeated)
+    #[arg(long = "exclude", value_name = "GLOB")]
+    pub excluded_file_globs: Vec<String>,
+
+    /// Do not honor .gitignore, .ignore and .foldertoaiignore files
+    #[arg(long)]
+    pub no_ignore_files: bool,
+
+    /// Send the contents as they are, without redacting the secrets found in them (keys, tokens, passwords, etc.)
+    #[arg(long)]
+    pub no_redaction: bool,
+
+    /// Path (relative to the folder) of a sensitive file, such as ".env" or a private key, to send instead of withholding it; the choice
+    /// is remembered for the folder once the messages are generated (may be repeated)
+    #[arg(long = "allow-sensitive", value_name = "PATH")]
+    pub allowed_sensitive_file_paths: Vec<String>,
+
+    /// Path (relative to the folder) of a sensitive file allowed earlier, to withhold again from now on (may be repeated)
+    #[arg(long = "withhold-sensitive", value_name = "PATH")]
+    pub withheld_sensitive_file_paths: Vec<String>,
+
+    /// Number of threads used to read the files (0 = one per CPU); defaults to the one in the settings
+    #[arg(long = "threads", value_name = "N")]
+    pub n_threads: Option<usize>
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum TreePlacementArg {
+    None,
+    Prologue,
+    Separate
+}
+
+impl From<TreePlacementArg> for TreeOverviewPlacement {
+    fn from(tree_placement_arg: TreePlacementArg) -> Self {
+        match tree_placement_arg {
+            TreePlacementArg::None => TreeOverviewPlacement::None,
+            TreePlacementArg::Prologue => TreeOverviewPlacement::Prologue,
+            TreePlacementArg::Separate => TreeOverviewPlacement::SeparateMessage
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum GitChangesArg {
+    Unstaged,
+    Staged,
+    Range
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum FormatArg {
+    Plain,
+    Markdown,
+    Xml
+}
+
+impl From<FormatArg> for OutputFormat {
+    fn from(format_arg: FormatArg) -> Self {
+        match format_arg {
+            FormatArg::Plain => OutputFormat::PlainText,
+            FormatArg::Markdown => OutputFormat::Markdown,
+            FormatArg::Xml => OutputFormat::Xml
+        }
+    }
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum ExportFormatArg {
+    Json,
+    Jsonl
+}
+
+impl From<ExportFormatArg> for ExportFormat {
+    fn from(export_format_arg: ExportFormatArg) -> Self {
+        match export_format_arg {
+            ExportFormatArg::Json => ExportFormat::Json,
+            ExportFormatArg::Jsonl => ExportFormat::Jsonl
+        }
+    }
+}
+
+fn parse_chunk_size(value: &str) -> Result<usize, String>
+{
+    let chunk_size: usize = value.parse().map_err(|_| format!("\"{}\" is not a valid number", value))?;
+    if chunk_size < core_utils::MINIMUM_MESSAGE_SIZE {
+        return Err(format!("messages must allow at least {} characters, bytes or tokens", core_utils::MINIMUM_MESSAGE_SIZE));
+    }
+    Ok(chunk_size)
+}
+
+fn parse_git_base(value: &str) -> Result<String, String>
+{
+    git_changes::validate_base_revision(value)?;
+    Ok(value.to_string())
+}
+
+fn has_sensitive_file_choices(args: &MessagesArgs) -> bool
+{
+    !args.allowed_sensitive_file_paths.is_empty() || !args.withheld_sensitive_file_paths.is_empty()
+}
+
+fn apply_sensitive_file_choices(args: &MessagesArgs, settings: &mut Settings)
+{
+    for allowed_sensitive_file_path in &args.allowed_sensitive_file_paths {
+        settings.allow_sensitive_file(&args.folder, &allowed_sensitive_file_path.replace('\\', "/"));
+    }
+    for withheld_sensitive_file_path in &args.withheld_sensitive_file_paths {
+        settings.withhold_sensitive_file(&args.folder, &withheld_sensitive_file_path.replace('\\', "/"));
+    }
+}
+
+/// Saves the choices about sensitive files into the stored settings, leaving out the other options, which only apply to this run.
+fn remember_sensitive_file_choices(args: &MessagesArgs) -> Result<(), String>
+{
+    if !has_sensitive_file_choices(args) {
+        return Ok(());
+    }
+    let settings_file_path = setup_utils::settings_file_path()?;
+    let mut settings = Settings::new();
+    settings.load_from_file(&settings_file_path);
+    apply_sensitive_file_choices(args, &mut settings);
+    if !settings.save_to_file(&settings_file_path) {
+        return Err(format!("the choices about sensitive files could not be saved to \"{}\"", settings_file_path));
+    }
+    Ok(())
+}
+
+fn settings_for_messages_command(args: &MessagesArgs) -> Result<Settings, String>
+{
+    let mut settings = Settings::new();
+    if let Ok(settings_file_path) = setup_utils::settings_file_path() {
+        settings.load_from_file(&settings_file_path);
+    }
+
+    // unlike the other options, choices about sensitive files are remembered (once the run succeeds), so they must be able to be saved
+    if has_sensitive_file_choices(args) {
+        setup_utils::settings_file_path().map_err(|error| format!("the choices about sensitive files cannot be remembered: {}", error))?;
+    }
+    apply_sensitive_file_choices(args, &mut settings);
+
+    if let Some(profile_name) = &args.profile {
+        settings.select_profile(profile_name)?;
+    }
+
+    if args.replace_ignores {
+        settings.ignored_subfolders.clear();
+    }
+    for subfolder in &args.ignored_subfolders {
+        settings.ignored_subfolders.insert(subfolder.clone());
+    }
+    settings.included_file_globs.extend(args.included_file_globs.iter().cloned());
+    settings.excluded_file_globs.extend(args.excluded_file_globs.iter().cloned());
+    if args.no_ignore_files {
+        settings.respect_ignore_files = false;
+    }
+    if args.no_redaction {
+        settings.redact_secrets = false;
+    }
+    if let Some(file_size_limit) = args.file_size_limit {
+        settings.file_size_limit = file_size_limit;
+    }
+    if let Some(n_threads) = args.n_threads {
+        settings.n_threads = n_threads;
+    }
+    if args.split_anywhere {
+        settings.split_messages_on_line_boundaries = false;
+    }
+    if args.no_integrity_markers {
+        settings.integrity_markers = false;
+    }
+    if args.request_reply_format {
+        settings.request_reply_format = true;
+    }
+    if args.changes_only {
+        settings.share_changes_only = true;
+    }
+    if args.changed_files_in_full {
+        settings.modified_files_as_diffs = false;
+    }
+    if let Some(git) = args.git {
+        settings.git_change_set = Some(match git {
+            GitChangesArg::Unstaged => GitChangeSet::Unstaged,
+            GitChangesArg::Staged => GitChangeSet::Staged,
+            GitChangesArg::Range => GitChangeSet::CommitRange { base: args.git_base.clone().unwrap_or_default() }
+        });
+    }
+    if let Some(tree) = args.tree {
+        settings.tree_overview_placement = tree.into();
+    }
+    if let Some(templates_file_path) = &args.templates {
+        settings.custom_message_templates = Some(MessageTemplates::load_from_file(templates_file_path)?);
+    }
+    if let Some(chunk_size) = args.chunk_size {
+        settings.selected_profile_mut().maximum_message_size = chunk_size;
+    }
+    if args.no_context_budget {
+        settings.selected_profile_mut().context_budget = None;
+    }
+    if let Some(unit) = args.unit {
+        settings.selected_profile_mut().message_size_unit = unit;
+    }
+    if let Some(task) = &args.task {
+        settings.task_prompt = task.clone();
+        settings.include_task_prompt = true;
+    }
+    if let Some(task_preset_name) = &args.task_preset {
+        settings.apply_task_prompt_preset(task_preset_name)?;
+    }
+    if args.single_message {
+        settings.selected_profile_mut().single_message = true;
+    }
+    if let Some(format) = args.format {
+        settings.selected_profile_mut().output_format = format.into();
+    }
+    if let Some(vocabulary) = &args.vocabulary {
+        settings.tokenizer_vocabulary_path = vocabulary.clone();
+    }
+
+    Ok(settings)
+}
+
+struct ScanResult {
+    folder_info: FolderInfo,
+    rendered_messages: RenderedMessages,
+    message_sizes: Vec<usize>,
+    message_size_unit_name: String
+}
+
+fn obtain_messages(args: &MessagesArgs, settings: &Settings) -> Result<ScanResult, String>
+{
+    let mut message_renderer = settings.message_renderer()?;
+    if let Some(tokenizer_warning) = settings.tokenizer_warning() {
+        eprintln!("warning: {}", tokenizer_warning);
+    }
+    if settings.share_changes_only {
+        match setup_utils::snapshots_directory_path().ok().and_then(|snapshots_directory_path| FolderSnapshot::load_for_folder(&snapshots_directory_path, &args.folder)) {
+            Some(folder_snapshot) => message_renderer = message_renderer.changes_since(folder_snapshot),
+            None => eprintln!("note: the folder was not shared before, so all of it is shared")
+        }
+    }
+    let folder_info = FolderScanner::new(&args.folder).options(settings.scan_options(&args.folder)).scan()?;
+    if let Some(git_change_set) = &settings.git_change_set {
+        let git_changes = GitChanges::obtain(&folder_info, git_change_set)?;
+        message_renderer = message_renderer.git_changes(if settings.redact_secrets { git_changes.with_secrets_redacted() } else { git_changes });
+    }
+
+    let n_redactions = folder_info.number_of_redactions();
+    if n_redactions > 0 {
+        eprintln!("note: {} secret(s) were redacted from the contents", n_redactions);
+    }
+    let n_withheld_sensitive_files = folder_info.number_of_withheld_sensitive_files();
+    if n_withheld_sensitive_files > 0 {
+        eprintln!("note: {} sensitive file(s) were withheld (send them with --allow-sensitive)", n_withheld_sensitive_files);
+    }
+    for file_info in folder_info.file_infos.iter().filter(|file_info| file_info.is_sensitive && !file_info.should_be_ignored) {
+        eprintln!("note: the sensitive file \"{}\" is sent, as allowed for the folder (withhold it with --withhold-sensitive)", file_info.filepath);
+    }
+
+    let n_files_that_could_not_be_loaded = folder_info.number_of_files_that_could_not_be_loaded();
+    if n_files_that_could_not_be_loaded > 0 {
+        eprintln!("warning: {} file(s) could not be loaded", n_files_that_could_not_be_loaded);
+    }
+
+    let rendered_messages = message_renderer.render_with_file_message_indices(&folder_info)?;
+    let message_sizes: Vec<usize> = rendered_messages.messages.iter().map(|message| message_renderer.message_size(message)).collect();
+
+    Ok(ScanResult{ folder_info, rendered_messages, message_sizes, message_size_unit_name: message_renderer.message_size_unit_name().to_string() })
+}
+
+fn write_export(scan_result: &ScanResult, export_format: ExportFormat, output_dir: Option<&str>) -> Result<(), String>
+{
+    let scan_export = ScanExport{ folder_info: &scan_result.folder_info, messages: &scan_result.rendered_messages.messages, message_sizes: &scan_result.message_sizes, message_size_unit: &scan_result.message_size_unit_name, file_message_indices: &scan_result.rendered_messages.file_message_indices };
+    match output_dir {
+        Some(output_dir) => {
+            std::fs::create_dir_all(output_dir).map_err(|error| format!("could not create directory \"{}\": {}", output_dir, error))?;
+            let file_path = Path::new(output_dir).join(format!("export.{}", export_format.file_extension()));
+            scan_export.save_to_file(export_format, &file_path.to_string_lossy())
+        },
+        None => {
+            let exported_scan = scan_export.to_format(export_format)?;
+            match std::io::stdout().lock().write_all(exported_scan.as_bytes()) {
+                Err(error) if error.kind() != std::io::ErrorKind::BrokenPipe => Err(error.to_string()),
+                _ => Ok(())
+            }
+        }
+    }
+}
+
+fn write_messages_to_directory(messages: &[String], output_dir: &str) -> Result<(), String>
+{
+    std::fs::create_dir_all(output_dir).map_err(|error| format!("could not create directory \"{}\": {}", output_dir, error))?;
+
+    let n_digits = messages.len().to_string().len();
+    for (message_index, message) in messages.iter().enumerate() {
+        let file_path = Path::new(output_dir).join(format!("message_{:0width$}.txt", message_index + 1, width = n_digits));
+        std::fs::write(&file_path, message).map_err(|error| format!("could not write \"{}\": {}", file_path.display(), error))?;
+    }
+
+    Ok(())
+}
+
+fn write_messages_to_stdout(messages: &[String]) -> Result<(), String>
+{
+    let mut stdout = std::io::stdout().lock();
+    for (message_index, message) in messages.iter().enumerate() {
+        let result = if message_index > 0 { writeln!(stdout, "\n{}", message) } else { writeln!(stdout, "{}", message) };
+        match result {
+            Ok(()) => {},
+            Err(error) if error.kind() == std::io::ErrorKind::BrokenPipe => return Ok(()), // e.g. piped into "head"
+            Err(error) => return Err(error.to_string())
+        }
+    }
+    Ok(())
+}
+
+pub fn run_messages_command(args: &MessagesArgs) -> ExitCode
+{
+    let result = settings_for_messages_command(args).and_then(|settings| obtain_messages(args, &settings).map(|scan_result| (settings, scan_result))).and_then(|(settings, scan_result)| {
+        if let Some(export_format) = args.export {
+            return write_export(&scan_result, export_format.into(), args.output_dir.as_deref());
+        }
+        // so that transcripts can be verified afterwards; not being able to is no reason to fail
+        let messages_are_redacted = settings.redact_secrets && !scan_result.folder_info.contains_shared_sensitive_files();
+        let sharing_session = SharingSession::new(&args.folder, scan_result.rendered_messages.messages.clone(), scan_result.rendered_messages.session_id.clone(), messages_are_redacted);
+        if let Err(error) = setup_utils::sessions_directory_path().and_then(|sessions_directory_path| sharing_session.save(&sessions_directory_path)) {
+            eprintln!("warning: the session could not be saved: {}", error);
+        }
+        // sharing git changes leaves most of the folder out, so it does not count as sharing the folder
+        if settings.git_change_set.is_none() {
+            // so that the changes since this share can be shared next
+            if let Err(error) = setup_utils::snapshots_directory_path().and_then(|snapshots_directory_path| FolderSnapshot::of_folder(&scan_result.folder_info, settings.redact_secrets).save(&snapshots_directory_path)) {
+                eprintln!("warning: the snapshot of the folder could not be saved: {}", error);
+            }
+        }
+        match &args.output_dir {
+            Some(output_dir) => write_messages_to_directory(&scan_result.rendered_messages.messages, output_dir),
+            None => write_messages_to_stdout(&scan_result.rendered_messages.messages)
+        }
+    }).and_then(|()| remember_sensitive_file_choices(args));
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn read_transcript(transcript_file_path: Option<&str>) -> Result<String, String>
+{
+    match transcript_file_path {
+        Some(transcript_file_path) => std::fs::read_to_string(transcript_file_path).map_err(|error| format!("could not read \"{}\": {}", transcript_file_path, error)),
+        None => std::io::read_to_string(std::io::stdin()).map_err(|error| format!("could not read the transcript from stdin: {}", error))
+    }
+}
+
+fn verify_transcript(folder: &str, transcript_file_path: Option<&str>) -> Result<Vec<String>, String>
+{
+    let sessions_directory_path = setup_utils::sessions_directory_path()?;
+    let sharing_session = SharingSession::load_for_folder(&sessions_directory_path, folder).ok_or_else(|| format!("no session was found for \"{}\"; generate its messages first", folder))?;
+    let transcript = read_transcript(transcript_file_path)?;
+    if !sharing_session.keeps_messages() {
+        eprintln!("note: the messages were not redacted, so they were not kept and cannot be checked for alterations");
+    }
+    Ok(sharing_session.verify_transcript(&transcript))
+}
+
+pub fn run_verify_command(folder: &str, transcript_file_path: Option<&str>) -> ExitCode
+{
+    match verify_transcript(folder, transcript_file_path) {
+        Ok(problems) if problems.is_empty() => {
+            println!("the transcript contains all the messages, in order and unaltered");
+            ExitCode::SUCCESS
+        },
+        Ok(problems) => {
+            for problem in &problems {
+                println!("{}", problem);
+            }
+            ExitCode::FAILURE
+        },
+        Err(error) => {
+            eprintln!("error: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+pub fn run_reassemble_command(transcript_file_path: Option<&str>, output_dir: &str) -> ExitCode
+{
+    let result = read_transcript(transcript_file_path).and_then(|transcript| {
+        let reassembly = Reassembly::from_transcript(&transcript);
+        reassembly.write_to_directory(output_dir)?;
+        Ok(reassembly)
+    });
+
+    match result {
+        Ok(reassembly) => {
+            println!("{} file(s) rebuilt in \"{}\"", reassembly.files.len(), output_dir);
+            for path in &reassembly.files_without_content {
+                println!("not rebuilt, as its content was not in the messages: \"{}\"", path);
+            }
+            for problem in &reassembly.problems {
+                println!("{}", problem);
+            }
+            if reassembly.problems.is_empty() { ExitCode::SUCCESS } else { ExitCode::FAILURE }
+        },
+        Err(error) => {
+            eprintln!("error: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// The folder as it is now, except that the files shared earlier have the content they were last shared with (as kept in the folder's
+/// snapshot or, failing that, reassembled from its stored session), so that files changed since the reply's author saw them are reported
+/// as conflicts.
+fn folder_info_as_shared(folder: &str) -> Result<FolderInfo, String>
+{
+    let mut settings = Settings::new();
+    if let Ok(settings_file_path) = setup_utils::settings_file_path() {
+        settings.load_from_file(&settings_file_path);
+    }
+    let mut folder_info = FolderScanner::new(folder).options(settings.scan_options(folder)).scan()?;
+
+    // snapshots only keep the contents that were redacted
+    let folder_snapshot = setup_utils::snapshots_directory_path().ok().and_then(|snapshots_directory_path| FolderSnapshot::load_for_folder(&snapshots_directory_path, folder))
+        .filter(|folder_snapshot| folder_snapshot.entries.iter().any(|snapshot_entry| snapshot_entry.content.is_some()));
+    if let Some(folder_snapshot) = folder_snapshot {
+        // updates only carry the changes, so the snapshot, which has every file as last shared, is preferred to the session
+        let shared_file_contents: HashMap<&str, &str> = folder_snapshot.entries.iter().filter_map(|snapshot_entry| Some((snapshot_entry.path.as_str(), snapshot_entry.content.as_deref()?))).collect();
+        for file_info in &mut folder_info.file_infos {
+            if let Some(shared_file_content) = shared_file_contents.get(file_info.filepath.as_str()) {
+                file_info.file_content = Some(shared_file_content.to_string());
+            }
+        }
+        if !shared_file_contents.is_empty() {
+            println!("comparing the files with their content as last shared");
+        }
+    }
+    else if let Some(sharing_session) = setup_utils::sessions_directory_path().ok().and_then(|sessions_directory_path| SharingSession::load_for_folder(&sessions_directory_path, folder)) {
+        let reassembly = Reassembly::from_transcript(&sharing_session.messages.join("\n\n"));
+        let shared_file_contents: HashMap<&str, &str> = reassembly.files.iter().map(|reassembled_file| (reassembled_file.path.as_str(), reassembled_file.content.as_str())).collect();
+        for file_info in &mut folder_info.file_infos {
+            // the line breaks of the messages are normalized when reassembling them, so files with "\r\n" line breaks are compared without them
+            let Some(shared_file_content) = shared_file_contents.get(file_info.filepath.as_str()) else {
+                continue;
+            };
+            if file_info.file_content.as_ref().is_none_or(|file_content| file_content.replace("\r\n", "\n") != *shared_file_content) {
+                file_info.file_content = Some(shared_file_content.to_string());
+            }
+        }
+        if !shared_file_contents.is_empty() {
+            println!("comparing the files with their content as shared in session {}", sharing_session.session_id);
+        }
+    }
+
+    Ok(folder_info)
+}
+
+fn apply_reply(args: &ApplyArgs) -> Result<bool, String>
+{
+    let reply = read_transcript(args.reply.as_deref())?;
+    let file_edits = reply_edits::parse_reply(&reply);
+    if file_edits.is_empty() {
+        return Err(String::from("no file edit (whole file or unified diff) was found in the reply"));
+    }
+
+    let folder_info = folder_info_as_shared(&args.folder)?;
+    let planned_file_changes = reply_edits::plan_file_changes(&folder_info, &file_edits);
+    for planned_file_change in &planned_file_changes {
+        println!("{}: {}", planned_file_change.path, planned_file_change.summary());
+    }
+    let there_are_conflicts = planned_file_changes.iter().any(|planned_file_change| planned_file_change.conflict.is_some());
+    if args.dry_run {
+        return Ok(!there_are_conflicts);
+    }
+
+    let backups_directory_path = match (&args.backup_dir, args.no_backup) {
+        (_, true) => None,
+        (Some(backup_dir), false) => Some(backup_dir.clone()),
+        (None, false) => Some(setup_utils::backups_directory_path().map_err(|error| format!("{}; pass --backup-dir or --no-backup", error))?)
+    };
+    let reply_application = reply_edits::apply_file_changes(&args.folder, &planned_file_changes, backups_directory_path.as_deref());
+    for applied_file_change in &reply_application.applied_file_changes {
+        if let Err(error) = &applied_file_change.result {
+            println!("{}: not applied: {}", applied_file_change.path, error);
+        }
+    }
+    println!("{} file(s) changed", reply_application.applied_file_changes.len() - reply_application.n_failed_file_changes());
+    if let Some(backup_directory_path) = &reply_application.backup_directory_path {
+        println!("the previous versions were backed up to \"{}\"", backup_directory_path);
+    }
+
+    Ok(!there_are_conflicts && reply_application.n_failed_file_changes() == 0)
+}
+
+pub fn run_apply_command(args: &ApplyArgs) -> ExitCode
+{
+    match apply_reply(args) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -1,11 +1,10 @@
-use clipboard::{ClipboardContext, ClipboardProvider};
+use arboard::Clipboard;
 
 pub fn set_clipboard_content(content: &str) -> bool
 {
-    if let Ok(mut ctx) = <ClipboardContext>::new() {
-        if let Ok(_) = ctx.set_contents(content.to_string()) {
-            return true;
-        }
+    if let Ok(mut clipboard) = Clipboard::new()
+        && clipboard.set_text(content.to_string()).is_ok() {
+        return true;
     }
     false
-}
\ No newline at end of file
+}
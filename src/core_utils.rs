@@ -1,79 +1,991 @@
+use crate::file_info::FileInfo;
+use crate::file_utils;
 use crate::folder_info::FolderInfo;
+use crate::git_changes::{GitChangeSet, GitChanges};
+use crate::integrity;
+use crate::message_renderer::{MessageRenderer, RenderedMessages};
+use crate::message_templates::{code_fence, code_span, fill_template, MessageTemplates};
+use crate::output_format::OutputFormat;
+use crate::snapshot::FolderChanges;
+use crate::tokenizer::Tokenizer;
+use crate::tree_overview::{self, TreeOverviewPlacement};
 
 use size::Size;
 use unicode_segmentation::UnicodeSegmentation;
 
-pub fn obtain_folder_representation_messages(folder_info: &FolderInfo) -> Result<Vec<String>,()> {
-    // this function can be made faster by building the parts directly from the file contents instead of concatenating all of them first
+// message sizes are measured in the unit of the tokenizer in use (characters, bytes or tokens)
+pub const DEFAULT_MAXIMUM_MESSAGE_SIZE: usize = 4096;
+pub const MINIMUM_MESSAGE_SIZE: usize = 64;
 
-    const MAXIMUM_AMOUNT_OF_CHARACTERS_ALLOWED_IN_LLM_MODEL: usize = 4096;
-    const APPROXIMATE_MAXIMUM_AMOUNT_OF_CHARACTERS_ALLOWED_IN_LLM_MODEL: usize = MAXIMUM_AMOUNT_OF_CHARACTERS_ALLOWED_IN_LLM_MODEL - 16; // assuming the LLM works with 4096 characters; leave 16 characters for starting message
+// the largest index a message is expected to have; the size of its header is reserved in every message
+const LARGEST_MESSAGE_INDEX: &str = "99999";
+// stand-ins of the same length as the actual session IDs and checksums, to measure headers before they are known
+const SAMPLE_SESSION_ID: &str = "000000000000";
+const SAMPLE_CHECKSUM: &str = "00000000";
 
-    let mut concatenated_file_contents = String::new();
+/// Splits the files into messages, worded according to the renderer's templates; when splitting on line boundaries, files are kept whole
+/// when they fit in a message and are otherwise split at line boundaries into self-contained parts, instead of cutting the concatenation
+/// of all files wherever a message fills up.
+pub fn obtain_folder_representation_messages(folder_info: &FolderInfo, message_renderer: &MessageRenderer, templates: &MessageTemplates) -> Result<RenderedMessages, String> {
+    obtain_messages(folder_info, None, message_renderer, templates)
+}
 
-    let folder_contains_no_relevant_files = !folder_info.contains_at_least_one_file_that_should_not_be_ignored();
+/// Like [`obtain_folder_representation_messages`], but with only the files added or modified since a snapshot, in messages presented
+/// as an update to the files shared earlier, without tree overview; the indices of the files refer to those of the whole folder.
+pub fn obtain_folder_update_messages(folder_changes: &FolderChanges, message_renderer: &MessageRenderer, templates: &MessageTemplates) -> Result<RenderedMessages, String> {
+    let changes_presentation = ChangesPresentation{ note: update_note(folder_changes), preceding_text: None };
+    obtain_messages(&folder_changes.changed_folder_info, Some(&changes_presentation), message_renderer, templates)
+}
+
+/// Like [`obtain_folder_representation_messages`], but with only the files touched by changes in a git repository (see [`GitChanges`]),
+/// preceded by the diff of the changes and presented with the branch and its recent commits, without tree overview.
+pub fn obtain_git_changes_messages(git_changes: &GitChanges, folder_info: &FolderInfo, message_renderer: &MessageRenderer, templates: &MessageTemplates) -> Result<RenderedMessages, String> {
+    let preceding_text = (!git_changes.diff.is_empty()).then(|| format!("Diff of the changes (from `{}`):\n{}", git_changes.change_set.diff_command(), git_changes.diff));
+    let changes_presentation = ChangesPresentation{ note: git_changes_note(git_changes), preceding_text };
+    obtain_messages(&git_changes.changed_folder_info(folder_info), Some(&changes_presentation), message_renderer, templates)
+}
+
+/// How messages that only carry some changes present them: a note in the first message explaining what changed, and text sent
+/// before the files (e.g. the diff of all the changes).
+struct ChangesPresentation {
+    note: String,
+    preceding_text: Option<String>
+}
+
+fn obtain_messages(folder_info: &FolderInfo, changes_presentation: Option<&ChangesPresentation>, message_renderer: &MessageRenderer, templates: &MessageTemplates) -> Result<RenderedMessages, String> {
+    let tokenizer = message_renderer.tokenizer.as_ref();
+    let maximum_message_size = message_renderer.maximum_message_size;
+    templates.validate()?;
+
+    if maximum_message_size < MINIMUM_MESSAGE_SIZE {
+        return Err(format!("messages must allow at least {} {}", MINIMUM_MESSAGE_SIZE, tokenizer.unit_name()));
+    }
+    let sample_session_id = message_renderer.integrity_markers.then_some(SAMPLE_SESSION_ID);
+    let message_index_header_size = tokenizer.count(&message_header(templates, LARGEST_MESSAGE_INDEX, LARGEST_MESSAGE_INDEX, sample_session_id, SAMPLE_CHECKSUM));
+    if message_index_header_size >= maximum_message_size {
+        return Err(format!("messages must allow more than {} {}", message_index_header_size, tokenizer.unit_name()));
+    }
+    let maximum_part_size = maximum_message_size - message_index_header_size;
+
+    if message_renderer.single_message {
+        return obtain_single_message(folder_info, changes_presentation, message_renderer, templates);
+    }
+
+    // an update is sent even if no file was added or modified, since it states which ones were deleted, if any
+    let folder_contains_no_relevant_files = changes_presentation.is_none() && !folder_info.contains_at_least_one_file_that_should_not_be_ignored();
     if folder_contains_no_relevant_files {
-        let empty_folder_message = format!("[FolderToAI]\n\nMessage 1/1\n\nThis message will provide you relevant information about the files within the folder {}.\n\nThe folder contains no relevant files.", folder_info.folder_path);
+        return obtain_empty_folder_messages(folder_info, message_renderer, templates);
+    }
+
+    // indices of the chunks each file lands in
+    let (chunks, file_chunk_indices) = if message_renderer.splits_on_line_boundaries() {
+        pack_files_on_line_boundaries(folder_info, maximum_part_size, message_renderer, templates)
+    }
+    else {
+        let mut concatenated_file_contents = String::new();
+        let mut file_block_ranges: Vec<Option<(usize, usize)>> = vec!();
+        for file_info in &folder_info.file_infos {
+            if file_info.should_be_ignored {
+                file_block_ranges.push(None);
+                continue;
+            }
+            let file_block_start = concatenated_file_contents.len();
+            concatenated_file_contents += &file_block(message_renderer, templates, file_info);
+            file_block_ranges.push(Some((file_block_start, concatenated_file_contents.len())));
+        }
+        let chunks = split_into_chunks(&concatenated_file_contents, maximum_part_size, tokenizer, false);
+        let file_chunk_indices = chunk_indices_of_ranges(&chunks, &file_block_ranges);
+        (chunks, file_chunk_indices)
+    };
 
-        let mut folder_representation_messages: Vec<String> = vec!();
-        folder_representation_messages.push(empty_folder_message);
-        return Ok(folder_representation_messages);
+    let n_closing_messages = usize::from(message_renderer.task_prompt.is_some());
+
+    let mut tree_overview_in_prologue: Option<String> = None;
+    // the chunks of the tree overview, or of the text preceding the files when only some changes are shared
+    let mut tree_overview_chunks: Vec<String> = vec!();
+    if let Some(preceding_text) = changes_presentation.and_then(|changes_presentation| changes_presentation.preceding_text.as_deref()) {
+        tree_overview_chunks = split_into_chunks(preceding_text, maximum_part_size, tokenizer, true);
+    }
+    else if message_renderer.tree_overview_placement != TreeOverviewPlacement::None && changes_presentation.is_none() {
+        let tree_overview = obtain_tree_overview_with_heading(folder_info);
+
+        let prologue_with_tree_overview = obtain_prologue(folder_info, None, chunks.len() + 1 + n_closing_messages, message_renderer, templates, sample_session_id, Some(&tree_overview)) ;
+        if message_renderer.tree_overview_placement == TreeOverviewPlacement::Prologue && tokenizer.count(&prologue_with_tree_overview) <= maximum_message_size {
+            tree_overview_in_prologue = Some(tree_overview);
+        }
+        else {
+            tree_overview_chunks = split_into_chunks(&tree_overview, maximum_part_size, tokenizer, true);
+        }
+    }
+
+    let total_n_messages = 1 + tree_overview_chunks.len() + chunks.len() + n_closing_messages;
+    let first_chunk_message_index = 2 + tree_overview_chunks.len();
+    let file_message_indices = file_chunk_indices.into_iter().map(|chunk_indices| chunk_indices.into_iter().map(|chunk_index| first_chunk_message_index + chunk_index).collect()).collect();
+
+    let message_bodies: Vec<String> = tree_overview_chunks.into_iter().chain(chunks).collect();
+    let session_id = if message_renderer.integrity_markers {
+        let mut hashed_message_bodies = message_bodies.clone();
+        hashed_message_bodies.extend(tree_overview_in_prologue.clone());
+        hashed_message_bodies.extend(message_renderer.task_prompt.clone());
+        Some(integrity::session_id_of(&folder_info.folder_path, &hashed_message_bodies))
+    }
+    else {
+        None
+    };
+
+    // the tree overview is only placed in the prologue if it fits, but the prologue may not fit even without it
+    let prologue = obtain_prologue(folder_info, changes_presentation, total_n_messages, message_renderer, templates, session_id.as_deref(), tree_overview_in_prologue.as_deref());
+    let prologue_size = tokenizer.count(&prologue);
+    if prologue_size > maximum_message_size {
+        return Err(format!("the first message, which presents the folder, takes {} {}, more than the maximum message size", prologue_size, tokenizer.unit_name()));
+    }
+    let mut folder_representation_messages: Vec<String> = vec!();
+    folder_representation_messages.push(prologue);
+    for message_body in message_bodies {
+        let checksum = integrity::checksum_of_message_body(&message_body);
+        let starting_string = message_header(templates, &(folder_representation_messages.len() + 1).to_string(), &total_n_messages.to_string(), session_id.as_deref(), &checksum);
+        folder_representation_messages.push(starting_string + &message_body);
     }
 
+    if let Some(task_prompt) = &message_renderer.task_prompt {
+        folder_representation_messages.push(obtain_checked_closing_message(templates, total_n_messages, task_prompt, session_id.as_deref(), message_renderer)?);
+    }
+
+    Ok(RenderedMessages{ messages: folder_representation_messages, session_id, file_message_indices })
+}
+
+/// A message stating that the folder contains no relevant files, followed, as usual, by the closing message with the task prompt, if any.
+fn obtain_empty_folder_messages(folder_info: &FolderInfo, message_renderer: &MessageRenderer, templates: &MessageTemplates) -> Result<RenderedMessages, String> {
+    let total_n_messages = 1 + usize::from(message_renderer.task_prompt.is_some());
+    let session_id = message_renderer.integrity_markers.then(|| {
+        let hashed_message_bodies: Vec<String> = message_renderer.task_prompt.iter().cloned().collect();
+        integrity::session_id_of(&folder_info.folder_path, &hashed_message_bodies)
+    });
+
+    let integrity_marker = session_id.as_deref().map(|session_id| integrity::integrity_marker(session_id, None)).unwrap_or_default();
+    let empty_folder_message = fill_template(&templates.empty_folder, &[
+        ("folder", &folder_info.folder_path),
+        ("index", "1"),
+        ("total", &total_n_messages.to_string()),
+        ("task_note", task_note(message_renderer)),
+        ("integrity_marker", &integrity_marker),
+        ("session", session_id.as_deref().unwrap_or_default())
+    ]);
+
+    let mut empty_folder_messages: Vec<String> = vec![empty_folder_message];
+    if let Some(task_prompt) = &message_renderer.task_prompt {
+        empty_folder_messages.push(obtain_checked_closing_message(templates, total_n_messages, task_prompt, session_id.as_deref(), message_renderer)?);
+    }
+
+    Ok(RenderedMessages{ messages: empty_folder_messages, session_id, file_message_indices: vec![vec!(); folder_info.file_infos.len()] })
+}
+
+/// Indices of the chunks that overlap each of the byte ranges (if any) of the text the chunks were split from.
+fn chunk_indices_of_ranges(chunks: &[String], ranges: &[Option<(usize, usize)>]) -> Vec<Vec<usize>> {
+    let mut chunk_ranges: Vec<(usize, usize)> = vec!();
+    let mut chunk_start = 0;
+    for chunk in chunks {
+        chunk_ranges.push((chunk_start, chunk_start + chunk.len()));
+        chunk_start += chunk.len();
+    }
+
+    ranges.iter().map(|range| match range {
+        Some((range_start, range_end)) => (0..chunk_ranges.len()).filter(|&chunk_index| chunk_ranges[chunk_index].0 < *range_end && *range_start < chunk_ranges[chunk_index].1).collect(),
+        None => vec!()
+    }).collect()
+}
+
+fn obtain_tree_overview_with_heading(folder_info: &FolderInfo) -> String {
+    format!("Directory tree of the folder, with the size and status of each file:\n{}", tree_overview::obtain_tree_overview(folder_info))
+}
+
+/// All the files in one self-contained message, without message headers nor requests for acknowledgement;
+/// the maximum message size and the context budget, if any, act as safety limits that the message must not exceed.
+fn obtain_single_message(folder_info: &FolderInfo, changes_presentation: Option<&ChangesPresentation>, message_renderer: &MessageRenderer, templates: &MessageTemplates) -> Result<RenderedMessages, String> {
+    let mut single_message = fill_template(&templates.single_message_preamble, &[
+        ("folder", &folder_info.folder_path),
+        ("folder_size", &Size::from_bytes(folder_info.size_in_bytes).to_string()),
+        ("update_note", changes_presentation.map_or("", |changes_presentation| &changes_presentation.note)),
+        ("reply_note", reply_note(message_renderer))
+    ]);
+    single_message += "\n\n";
+    if let Some(preceding_text) = changes_presentation.and_then(|changes_presentation| changes_presentation.preceding_text.as_deref()) {
+        single_message += preceding_text;
+        single_message += newline_if_missing(preceding_text);
+        single_message += "\n";
+    }
+    else if message_renderer.tree_overview_placement != TreeOverviewPlacement::None && changes_presentation.is_none() {
+        single_message += &obtain_tree_overview_with_heading(folder_info);
+        single_message += "\n";
+    }
+
+    let mut file_message_indices: Vec<Vec<usize>> = vec!();
     for file_info in &folder_info.file_infos {
         if file_info.should_be_ignored {
+            file_message_indices.push(vec!());
             continue;
         }
+        single_message += &file_block(message_renderer, templates, file_info);
+        file_message_indices.push(vec![1]);
+    }
+
+    if let Some(task_prompt) = &message_renderer.task_prompt {
+        single_message += "\n";
+        single_message += &fill_template(&templates.single_message_task, &[("task", task_prompt)]);
+    }
+
+    let single_message_size = message_renderer.tokenizer.count(&single_message);
+    if single_message_size > message_renderer.maximum_message_size {
+        let unit_name = message_renderer.tokenizer.unit_name();
+        return Err(format!("the single message would take {} {}, more than its limit of {} {}", single_message_size, unit_name, message_renderer.maximum_message_size, unit_name));
+    }
+    if let Some(context_budget) = message_renderer.context_budget
+        && single_message_size > context_budget {
+        let unit_name = message_renderer.tokenizer.unit_name();
+        return Err(format!("the single message would take {} {}, more than the context budget of {} {}", single_message_size, unit_name, context_budget, unit_name));
+    }
+
+    Ok(RenderedMessages{ messages: vec![single_message], session_id: None, file_message_indices })
+}
+
+/// Header of a message whose body has the given checksum; without a session ID, there are no integrity markers.
+fn message_header(templates: &MessageTemplates, message_index: &str, total_n_messages: &str, session_id: Option<&str>, checksum: &str) -> String {
+    let integrity_marker = session_id.map(|session_id| integrity::integrity_marker(session_id, Some(checksum))).unwrap_or_default();
+    fill_template(&templates.message_header, &[
+        ("index", message_index),
+        ("total", total_n_messages),
+        ("integrity_marker", &integrity_marker),
+        ("session", session_id.unwrap_or_default()),
+        ("checksum", if session_id.is_some() { checksum } else { "" })
+    ])
+}
+
+/// Like [`obtain_closing_message`], but failing if the closing message exceeds the maximum message size.
+fn obtain_checked_closing_message(templates: &MessageTemplates, total_n_messages: usize, task_prompt: &str, session_id: Option<&str>, message_renderer: &MessageRenderer) -> Result<String, String> {
+    let tokenizer = message_renderer.tokenizer.as_ref();
+    let closing_message = obtain_closing_message(templates, total_n_messages, task_prompt, session_id);
+    let closing_message_size = tokenizer.count(&closing_message);
+    if closing_message_size > message_renderer.maximum_message_size {
+        return Err(format!("the closing message with the task prompt takes {} {}, more than the maximum message size", closing_message_size, tokenizer.unit_name()));
+    }
+    Ok(closing_message)
+}
+
+/// The closing message, whose body (what follows its first line) is checksummed like those of the other messages.
+fn obtain_closing_message(templates: &MessageTemplates, total_n_messages: usize, task_prompt: &str, session_id: Option<&str>) -> String {
+    let total_n_messages_str = total_n_messages.to_string();
+    let mut values = vec![("index", total_n_messages_str.as_str()), ("total", total_n_messages_str.as_str()), ("task", task_prompt)];
+    let closing_message_without_integrity_marker = fill_template(&templates.closing_message, &values);
+    let Some(session_id) = session_id else {
+        return closing_message_without_integrity_marker;
+    };
+
+    let closing_message_body = closing_message_without_integrity_marker.split_once('\n').map_or("", |(_, closing_message_body)| closing_message_body);
+    let checksum = integrity::checksum_of_message_body(closing_message_body);
+    let integrity_marker = integrity::integrity_marker(session_id, Some(&checksum));
+    values.extend([("integrity_marker", integrity_marker.as_str()), ("session", session_id), ("checksum", checksum.as_str())]);
+    fill_template(&templates.closing_message, &values)
+}
 
-        concatenated_file_contents += &format!("File: {}\nSize: {}\n--- BEGINNING OF CONTENT ---\n", file_info.filepath, Size::from_bytes(file_info.size_in_bytes));
-        if let Some(file_content) = &file_info.file_content {
-            concatenated_file_contents += &file_content;
+/// Asks, if requested, to give changes to the files in a format they can be applied from: whole files, as they were given, or unified diffs.
+fn reply_note(message_renderer: &MessageRenderer) -> &'static str {
+    if !message_renderer.reply_format_note {
+        return "";
+    }
+    match message_renderer.output_format {
+        OutputFormat::PlainText => "\n\nIf you change or create files, give each of them in full as they are given here (a \"File: <path>\" line, then its whole content between the delimiter lines), or give the changes as unified diffs (with \"--- a/<path>\" and \"+++ b/<path>\" lines), so that they can be applied to the folder.",
+        OutputFormat::Markdown => "\n\nIf you change or create files, give each of them in full as they are given here (a heading with its path in backticks, then its whole content in a fenced code block), or give the changes as unified diffs (with \"--- a/<path>\" and \"+++ b/<path>\" lines), so that they can be applied to the folder.",
+        OutputFormat::Xml => "\n\nIf you change or create files, give each of them in full as they are given here (a <document path=\"...\"> element with its whole content in a <content> element), or give the changes as unified diffs (with \"--- a/<path>\" and \"+++ b/<path>\" lines), so that they can be applied to the folder."
+    }
+}
+
+// at most this many paths are listed for each kind of change in the note of an update
+const MAXIMUM_N_LISTED_CHANGED_FILE_PATHS: usize = 20;
+
+fn listed_file_paths(file_paths: &[String]) -> String {
+    listed_items(file_paths.iter().map(|file_path| format!("\"{}\"", file_path)).collect())
+}
+
+fn listed_items(items: Vec<String>) -> String {
+    let n_items = items.len();
+    let mut listed_items = items.into_iter().take(MAXIMUM_N_LISTED_CHANGED_FILE_PATHS).collect::<Vec<_>>().join(", ");
+    if n_items > MAXIMUM_N_LISTED_CHANGED_FILE_PATHS {
+        listed_items += &format!(" and {} more", n_items - MAXIMUM_N_LISTED_CHANGED_FILE_PATHS);
+    }
+    listed_items
+}
+
+/// Explains, when sharing changes, that the messages update the files shared earlier, and which files were added, modified and deleted.
+fn update_note(folder_changes: &FolderChanges) -> String {
+    let mut update_note = String::from("\n\nThis is an update to the files of this folder shared earlier in this conversation: only the files added or modified since then are given, and the files that are not mentioned are unchanged.");
+    if folder_changes.is_empty() {
+        update_note += " No file was added, modified or deleted since then.";
+        return update_note;
+    }
+
+    let (modified_file_infos_as_diffs, modified_file_infos_in_full): (Vec<&FileInfo>, Vec<&FileInfo>) = folder_changes.changed_folder_info.file_infos.iter()
+        .filter(|file_info| folder_changes.modified_file_paths.contains(&file_info.filepath))
+        .partition(|file_info| file_info.content_is_diff);
+    let file_paths_of = |file_infos: Vec<&FileInfo>| file_infos.into_iter().map(|file_info| file_info.filepath.clone()).collect::<Vec<String>>();
+    for (file_paths, description) in [
+        (folder_changes.added_file_paths.clone(), "Added files, given in full"),
+        (file_paths_of(modified_file_infos_as_diffs), "Modified files, given as unified diffs against their previous version (with \"--- a/<path>\" and \"+++ b/<path>\" lines)"),
+        (file_paths_of(modified_file_infos_in_full), "Modified files, given in full"),
+        (folder_changes.deleted_file_paths.clone(), "Deleted files")
+    ] {
+        if !file_paths.is_empty() {
+            update_note += &format!(" {}: {}.", description, listed_file_paths(&file_paths));
         }
-        else {
-            if file_info.is_binary {
-                concatenated_file_contents += "[Binary file]";
+    }
+    update_note
+}
+
+/// Presents changes in a git repository: what they are, on which branch, which files they touch and the recent commits.
+fn git_changes_note(git_changes: &GitChanges) -> String {
+    let change_set = &git_changes.change_set;
+    let mut git_changes_note = format!("\n\nWhat is shared are the changes {} in the git repository of this folder", change_set.description());
+    if let Some(branch_name) = &git_changes.branch_name {
+        git_changes_note += &format!(", on branch \"{}\"", branch_name);
+    }
+    if git_changes.touched_files.is_empty() {
+        git_changes_note += ". There are no such changes.";
+    }
+    else {
+        git_changes_note += &format!(". Only the files they touch are given: first the diff of the changes, then the full content of each touched file {}. Touched files: {}.", change_set.version_description(), listed_items(git_changes.touched_files.iter().map(|touched_file| format!("\"{}\" ({})", touched_file.path, touched_file.status)).collect()));
+        if git_changes.touched_files.iter().any(|touched_file| touched_file.status == "untracked") {
+            git_changes_note += " Untracked files are new files that are not in the diff.";
+        }
+        let unshared_file_paths: Vec<String> = git_changes.touched_files.iter().filter(|touched_file| !touched_file.is_shared && !touched_file.is_withheld_as_sensitive).map(|touched_file| touched_file.path.clone()).collect();
+        if !unshared_file_paths.is_empty() {
+            git_changes_note += &format!(" The changes to {} are not given, as these files are deleted or left out of the sharing.", listed_file_paths(&unshared_file_paths));
+        }
+        let withheld_file_paths: Vec<String> = git_changes.touched_files.iter().filter(|touched_file| touched_file.is_withheld_as_sensitive).map(|touched_file| touched_file.path.clone()).collect();
+        if !withheld_file_paths.is_empty() {
+            git_changes_note += &format!(" The changes to {} are not given either, as these files are withheld as sensitive.", listed_file_paths(&withheld_file_paths));
+        }
+    }
+    if !git_changes.commit_summaries.is_empty() {
+        let commits_heading = match change_set {
+            GitChangeSet::CommitRange { .. } => "Commits",
+            _ => "Recent commits"
+        };
+        git_changes_note += &format!("\n\n{}:\n{}", commits_heading, git_changes.commit_summaries.iter().map(|commit_summary| format!("- {}", commit_summary)).collect::<Vec<_>>().join("\n"));
+    }
+    git_changes_note
+}
+
+/// Asks to wait for the closing message before answering, when there is a task prompt.
+fn task_note(message_renderer: &MessageRenderer) -> &'static str {
+    if message_renderer.task_prompt.is_some() {
+        "\n\nThe last message states the task to carry out with these files. Do not answer, summarize or comment on the files before receiving it."
+    }
+    else {
+        ""
+    }
+}
+
+fn obtain_prologue(folder_info: &FolderInfo, changes_presentation: Option<&ChangesPresentation>, total_n_messages: usize, message_renderer: &MessageRenderer, templates: &MessageTemplates, session_id: Option<&str>, tree_overview: Option<&str>) -> String {
+    let splitting_note = if message_renderer.splits_on_line_boundaries() {
+        "Files that do not fit in a single message are split at line boundaries into parts, each with its own delimiters and a header stating the part's index and the lines it contains."
+    }
+    else {
+        "Note that these delimiters may be split in between messages but they will all eventually be there once all the parts get sent."
+    };
+
+    let integrity_marker = session_id.map(|session_id| integrity::integrity_marker(session_id, None)).unwrap_or_default();
+    let integrity_note = match session_id {
+        Some(session_id) => format!(" The header of every other message states the session ID ({}), shared by all the messages about this folder, and a checksum (CRC-32) of the rest of the message, so that duplicated, missing or foreign messages can be detected.", session_id),
+        None => String::new()
+    };
+
+    let mut prologue = fill_template(&templates.prologue, &[
+        ("folder", &folder_info.folder_path),
+        ("folder_size", &Size::from_bytes(folder_info.size_in_bytes).to_string()),
+        ("index", "1"),
+        ("total", &total_n_messages.to_string()),
+        ("limit", &message_renderer.maximum_message_size.to_string()),
+        ("unit", message_renderer.tokenizer.unit_name()),
+        ("splitting_note", splitting_note),
+        ("update_note", changes_presentation.map_or("", |changes_presentation| &changes_presentation.note)),
+        ("task_note", task_note(message_renderer)),
+        ("integrity_marker", &integrity_marker),
+        ("integrity_note", &integrity_note),
+        ("session", session_id.unwrap_or_default()),
+        ("reply_note", reply_note(message_renderer))
+    ]);
+
+    if let Some(tree_overview) = tree_overview {
+        prologue += "\n\n";
+        prologue += tree_overview;
+    }
+
+    prologue
+}
+
+const BINARY_FILE_PLACEHOLDER: &str = "[Binary file]";
+const FILE_TOO_LARGE_PLACEHOLDER: &str = "[This file is too large to be loaded]";
+const FILE_NOT_LOADED_PLACEHOLDER: &str = "[File content could not be loaded]";
+const ERROR_PLACEHOLDER: &str = "[Error]";
+/// What stands in the messages for the content of files that were not loaded.
+pub(crate) const FILE_CONTENT_PLACEHOLDERS: [&str; 4] = [BINARY_FILE_PLACEHOLDER, FILE_TOO_LARGE_PLACEHOLDER, FILE_NOT_LOADED_PLACEHOLDER, ERROR_PLACEHOLDER];
+
+fn file_content_placeholder(file_info: &FileInfo) -> &'static str {
+    if file_info.is_binary {
+        BINARY_FILE_PLACEHOLDER
+    }
+    else if file_info.file_too_large {
+        FILE_TOO_LARGE_PLACEHOLDER
+    }
+    else if file_info.content_should_be_loaded() {
+        FILE_NOT_LOADED_PLACEHOLDER
+    }
+    else {
+        ERROR_PLACEHOLDER
+    }
+}
+
+fn newline_if_missing(content: &str) -> &'static str {
+    if content.ends_with('\n') { "" } else { "\n" }
+}
+
+fn file_line_count(file_info: &FileInfo) -> String {
+    file_info.file_content.as_deref().map_or(0, |file_content| file_content.lines().count()).to_string()
+}
+
+fn language_of_block(file_info: &FileInfo) -> &'static str {
+    if file_info.content_is_diff { "diff" } else { file_utils::language_of_file(&file_info.filepath) }
+}
+
+fn file_block(message_renderer: &MessageRenderer, templates: &MessageTemplates, file_info: &FileInfo) -> String {
+    let output_format = message_renderer.output_format;
+    let file_content = file_info.file_content.as_deref().unwrap_or_else(|| file_content_placeholder(file_info));
+    fill_template(&templates.file, &[
+        ("path", &output_format.escape_attribute(&file_info.filepath)),
+        ("path_code", &code_span(&file_info.filepath)),
+        ("size", &Size::from_bytes(file_info.size_in_bytes).to_string()),
+        ("status", tree_overview::file_status(file_info)),
+        ("lines", &file_line_count(file_info)),
+        ("language", language_of_block(file_info)),
+        ("content", &output_format.encode_content(file_content)),
+        ("fence", &code_fence(file_content)),
+        ("newline_if_missing", newline_if_missing(file_content))
+    ])
+}
+
+fn file_part_block(message_renderer: &MessageRenderer, templates: &MessageTemplates, file_info: &FileInfo, part_index: usize, n_parts: usize, (first_line_number, last_line_number): (usize, usize), part_content: &str) -> String {
+    let output_format = message_renderer.output_format;
+    fill_template(&templates.file_part, &[
+        ("path", &output_format.escape_attribute(&file_info.filepath)),
+        ("path_code", &code_span(&file_info.filepath)),
+        ("size", &Size::from_bytes(file_info.size_in_bytes).to_string()),
+        ("status", tree_overview::file_status(file_info)),
+        ("lines", &file_line_count(file_info)),
+        ("language", language_of_block(file_info)),
+        ("content", &output_format.encode_content(part_content)),
+        ("fence", &code_fence(part_content)),
+        ("newline_if_missing", newline_if_missing(part_content)),
+        ("part", &part_index.to_string()),
+        ("parts", &n_parts.to_string()),
+        ("first_line", &first_line_number.to_string()),
+        ("last_line", &last_line_number.to_string())
+    ])
+}
+
+/// Measures file contents as they appear once encoded by the output format (e.g. with CDATA terminators escaped in XML),
+/// leaving out what the encoding adds around the whole content, which is part of the blocks' overhead.
+struct EncodedContentTokenizer<'a> {
+    tokenizer: &'a dyn Tokenizer,
+    output_format: OutputFormat,
+    encoding_overhead_size: usize
+}
+
+impl<'a> EncodedContentTokenizer<'a> {
+    fn new(tokenizer: &'a dyn Tokenizer, output_format: OutputFormat) -> Self {
+        let encoding_overhead_size = tokenizer.count(&output_format.encode_content(""));
+        EncodedContentTokenizer{ tokenizer, output_format, encoding_overhead_size }
+    }
+}
+
+impl Tokenizer for EncodedContentTokenizer<'_> {
+    fn unit_name(&self) -> &str {
+        self.tokenizer.unit_name()
+    }
+
+    fn count(&self, text: &str) -> usize {
+        self.tokenizer.count(&self.output_format.encode_content(text)).saturating_sub(self.encoding_overhead_size)
+    }
+}
+
+/// Chunks being filled in sequence, each of which becomes a message.
+struct ChunkPacker<'a> {
+    message_renderer: &'a MessageRenderer,
+    templates: &'a MessageTemplates,
+    tokenizer: &'a dyn Tokenizer,
+    content_tokenizer: EncodedContentTokenizer<'a>,
+    maximum_chunk_size: usize,
+    chunks: Vec<String>,
+    /// Indices of the chunks the file being appended has landed in so far.
+    current_file_chunk_indices: Vec<usize>,
+    current_chunk: String,
+    current_chunk_size: usize
+}
+
+impl<'a> ChunkPacker<'a> {
+    fn new(maximum_chunk_size: usize, message_renderer: &'a MessageRenderer, templates: &'a MessageTemplates) -> Self {
+        ChunkPacker{ message_renderer, templates, tokenizer: message_renderer.tokenizer.as_ref(), content_tokenizer: EncodedContentTokenizer::new(message_renderer.tokenizer.as_ref(), message_renderer.output_format), maximum_chunk_size, chunks: vec!(), current_file_chunk_indices: vec!(), current_chunk: String::new(), current_chunk_size: 0 }
+    }
+
+    fn remaining_size(&self) -> usize {
+        self.maximum_chunk_size.saturating_sub(self.current_chunk_size)
+    }
+
+    fn start_new_chunk(&mut self) {
+        if !self.current_chunk.is_empty() {
+            self.chunks.push(std::mem::take(&mut self.current_chunk));
+            self.current_chunk_size = 0;
+        }
+    }
+
+    fn append(&mut self, text: &str, text_size: usize) {
+        if self.current_file_chunk_indices.last() != Some(&self.chunks.len()) {
+            self.current_file_chunk_indices.push(self.chunks.len());
+        }
+        self.current_chunk += text;
+        self.current_chunk_size += text_size;
+    }
+
+    fn append_file(&mut self, file_info: &FileInfo) {
+        let block = file_block(self.message_renderer, self.templates, file_info);
+        let block_size = self.tokenizer.count(&block);
+        if block_size > self.remaining_size() && block_size <= self.maximum_chunk_size {
+            self.start_new_chunk();
+        }
+        if block_size <= self.remaining_size() {
+            self.append(&block, block_size);
+            return;
+        }
+
+        let file_content = file_info.file_content.as_deref().unwrap_or_else(|| file_content_placeholder(file_info));
+        self.append_file_in_parts(file_info, file_content);
+    }
+
+    fn append_file_in_parts(&mut self, file_info: &FileInfo, file_content: &str) {
+        // the size of the largest header and delimiters a part may have is reserved in each part
+        let largest_part_block_overhead_size = self.tokenizer.count(&file_part_block(self.message_renderer, self.templates, file_info, 99999, 99999, (9999999, 9999999), ""));
+        let maximum_part_content_size = self.maximum_chunk_size.saturating_sub(largest_part_block_overhead_size).max(1);
+
+        let first_line_size = file_content.split_inclusive('\n').next().map_or(0, |first_line| self.content_tokenizer.count(first_line));
+        if self.remaining_size() < largest_part_block_overhead_size + first_line_size {
+            self.start_new_chunk();
+        }
+
+        // (first line number, last line number, content) of each part
+        let mut parts: Vec<(usize, usize, String)> = vec!();
+        let mut part_content_size_limit = self.remaining_size().saturating_sub(largest_part_block_overhead_size).max(1);
+        let mut current_part_content = String::new();
+        let mut current_part_content_size = 0;
+        let mut current_part_first_line_number = 1;
+        let mut current_part_last_line_number = 1;
+
+        for (line_index, line) in file_content.split_inclusive('\n').enumerate() {
+            let line_number = line_index + 1;
+            let mut remaining_line = line;
+            while !remaining_line.is_empty() {
+                let (mut prefix_length, prefix_size) = longest_prefix_that_fits(remaining_line, part_content_size_limit - current_part_content_size, &self.content_tokenizer);
+                if prefix_length == remaining_line.len() {
+                    current_part_content += remaining_line;
+                    current_part_content_size += prefix_size;
+                    current_part_last_line_number = line_number;
+                    break;
+                }
+
+                if current_part_content.is_empty() {
+                    // a line larger than a whole part; it is split wherever the part fills up
+                    if prefix_length == 0 {
+                        prefix_length = remaining_line.graphemes(true).next().map_or(remaining_line.len(), str::len);
+                    }
+                    current_part_content += &remaining_line[..prefix_length];
+                    current_part_last_line_number = line_number;
+                    remaining_line = &remaining_line[prefix_length..];
+                }
+
+                parts.push((current_part_first_line_number, current_part_last_line_number, std::mem::take(&mut current_part_content)));
+                current_part_content_size = 0;
+                current_part_first_line_number = line_number;
+                part_content_size_limit = maximum_part_content_size;
+            }
+        }
+        // an empty file still gets a part, so that it is not left out of the messages
+        if !current_part_content.is_empty() || parts.is_empty() {
+            parts.push((current_part_first_line_number, current_part_last_line_number, current_part_content));
+        }
+
+        let n_parts = parts.len();
+        for (part_index, (first_line_number, last_line_number, part_content)) in parts.into_iter().enumerate() {
+            if part_index > 0 {
+                self.start_new_chunk();
+            }
+            let part_block = file_part_block(self.message_renderer, self.templates, file_info, part_index + 1, n_parts, (first_line_number, last_line_number), &part_content);
+            let part_block_size = self.tokenizer.count(&part_block);
+            self.append(&part_block, part_block_size);
+        }
+    }
+
+    fn finish(mut self) -> Vec<String> {
+        self.start_new_chunk();
+        self.chunks
+    }
+}
+
+/// Chunks, and the indices of the chunks each file lands in.
+fn pack_files_on_line_boundaries(folder_info: &FolderInfo, maximum_chunk_size: usize, message_renderer: &MessageRenderer, templates: &MessageTemplates) -> (Vec<String>, Vec<Vec<usize>>) {
+    let mut chunk_packer = ChunkPacker::new(maximum_chunk_size, message_renderer, templates);
+    let mut file_chunk_indices: Vec<Vec<usize>> = vec!();
+    for file_info in &folder_info.file_infos {
+        if !file_info.should_be_ignored {
+            chunk_packer.append_file(file_info);
+        }
+        file_chunk_indices.push(std::mem::take(&mut chunk_packer.current_file_chunk_indices));
+    }
+    (chunk_packer.finish(), file_chunk_indices)
+}
+
+/// Splits `text` into chunks whose sizes, as measured by `tokenizer`, are at most `maximum_chunk_size`, filling each chunk as much as possible;
+/// chunks are only split in between grapheme clusters and, with `keep_lines_whole`, lines that fit in a chunk are moved to the next one instead of being split.
+///
+/// The size of a chunk is taken as the sum of the sizes of its lines (or parts of lines), which is exact for characters and bytes
+/// and, for tokens, at least the size of the whole chunk in practice.
+fn split_into_chunks(text: &str, maximum_chunk_size: usize, tokenizer: &dyn Tokenizer, keep_lines_whole: bool) -> Vec<String> {
+    let mut chunks: Vec<String> = vec!();
+    let mut current_chunk = String::new();
+    let mut current_chunk_size = 0;
+
+    for line in text.split_inclusive('\n') {
+        let mut remaining_line = line;
+        while !remaining_line.is_empty() {
+            let (mut prefix_length, mut prefix_size) = longest_prefix_that_fits(remaining_line, maximum_chunk_size.saturating_sub(current_chunk_size), tokenizer);
+            if keep_lines_whole && prefix_length < remaining_line.len() && !current_chunk.is_empty() {
+                chunks.push(std::mem::take(&mut current_chunk));
+                current_chunk_size = 0;
+                continue;
             }
-            else if file_info.file_too_large {
-                concatenated_file_contents += "[This file is too large to be loaded]";
+            if prefix_length == 0 && current_chunk.is_empty() {
+                // a single grapheme cluster larger than the whole budget; it is sent anyway, as it cannot be split
+                let first_grapheme_length = remaining_line.graphemes(true).next().map_or(remaining_line.len(), str::len);
+                prefix_length = first_grapheme_length;
+                prefix_size = tokenizer.count(&remaining_line[..prefix_length]);
             }
-            else if file_info.content_should_be_loaded() {
-                concatenated_file_contents += "[File content could not be loaded]";
+
+            current_chunk += &remaining_line[..prefix_length];
+            current_chunk_size += prefix_size;
+            remaining_line = &remaining_line[prefix_length..];
+
+            if !remaining_line.is_empty() {
+                chunks.push(std::mem::take(&mut current_chunk));
+                current_chunk_size = 0;
+            }
+        }
+    }
+
+    if !current_chunk.is_empty() {
+        chunks.push(current_chunk);
+    }
+
+    chunks
+}
+
+/// Length (in bytes) and size of the longest prefix of `text`, ending at a grapheme cluster boundary, whose size is at most `maximum_size`.
+fn longest_prefix_that_fits(text: &str, maximum_size: usize, tokenizer: &dyn Tokenizer) -> (usize, usize) {
+    // the candidate prefixes are tried with exponentially increasing lengths and then with a binary search, so that
+    // only about as much of the text as ends up fitting is measured (lines of minified files may be huge)
+    let mut grapheme_ends = text.grapheme_indices(true).map(|(grapheme_start, grapheme)| grapheme_start + grapheme.len());
+    let mut known_grapheme_ends: Vec<usize> = vec!();
+
+    let mut n_graphemes_that_fit: usize = 0;
+    let mut size_of_prefix_that_fits: usize = 0;
+    let mut n_graphemes_that_do_not_fit: Option<usize> = None;
+
+    let mut n_graphemes_to_try: usize = 1;
+    loop {
+        while known_grapheme_ends.len() < n_graphemes_to_try {
+            match grapheme_ends.next() {
+                Some(grapheme_end) => known_grapheme_ends.push(grapheme_end),
+                None => break
+            }
+        }
+        let n_graphemes_to_try_clamped = std::cmp::min(n_graphemes_to_try, known_grapheme_ends.len());
+        if n_graphemes_to_try_clamped <= n_graphemes_that_fit {
+            break; // the whole text fits
+        }
+
+        let prefix_size = tokenizer.count(&text[..known_grapheme_ends[n_graphemes_to_try_clamped - 1]]);
+        if prefix_size > maximum_size {
+            n_graphemes_that_do_not_fit = Some(n_graphemes_to_try_clamped);
+            break;
+        }
+        n_graphemes_that_fit = n_graphemes_to_try_clamped;
+        size_of_prefix_that_fits = prefix_size;
+        n_graphemes_to_try *= 2;
+    }
+
+    if let Some(mut n_graphemes_that_do_not_fit) = n_graphemes_that_do_not_fit {
+        while n_graphemes_that_do_not_fit - n_graphemes_that_fit > 1 {
+            let n_graphemes_to_try = n_graphemes_that_fit + (n_graphemes_that_do_not_fit - n_graphemes_that_fit) / 2;
+            let prefix_size = tokenizer.count(&text[..known_grapheme_ends[n_graphemes_to_try - 1]]);
+            if prefix_size > maximum_size {
+                n_graphemes_that_do_not_fit = n_graphemes_to_try;
             }
             else {
-                concatenated_file_contents += "[Error]";
+                n_graphemes_that_fit = n_graphemes_to_try;
+                size_of_prefix_that_fits = prefix_size;
             }
         }
-        concatenated_file_contents += "\n--- END OF CONTENT ---\n";
     }
 
-    let mut file_contents_parts: Vec<String> = vec!();
-    let chars: Vec<&str> = concatenated_file_contents.as_str().graphemes(true).collect();
-    let n_parts = (chars.len() + APPROXIMATE_MAXIMUM_AMOUNT_OF_CHARACTERS_ALLOWED_IN_LLM_MODEL - 1) / APPROXIMATE_MAXIMUM_AMOUNT_OF_CHARACTERS_ALLOWED_IN_LLM_MODEL;
-    let total_n_messages = n_parts + 1;
+    let prefix_length = if n_graphemes_that_fit > 0 { known_grapheme_ends[n_graphemes_that_fit - 1] } else { 0 };
+    (prefix_length, size_of_prefix_that_fits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output_format::OutputFormat;
+    use crate::tokenizer::{ByteCounter, CharacterCounter};
+
+    const BEGINNING_OF_CONTENT_DELIMITER: &str = "--- BEGINNING OF CONTENT ---";
+    const END_OF_CONTENT_DELIMITER: &str = "--- END OF CONTENT ---";
 
-    let mut current_message_index: usize = 2;
+    fn message_renderer(maximum_message_size: usize) -> MessageRenderer {
+        MessageRenderer::new().maximum_message_size(maximum_message_size).integrity_markers(false)
+    }
 
-    let mut current_pos = 0;
-    while current_pos < chars.len() {
-        let end = std::cmp::min(current_pos + APPROXIMATE_MAXIMUM_AMOUNT_OF_CHARACTERS_ALLOWED_IN_LLM_MODEL, chars.len());
-        let chunk: String = chars[current_pos..end].concat();
-        
-        let starting_string = format!("Message {}/{}:\n", current_message_index, total_n_messages);
-        file_contents_parts.push(starting_string + &chunk);
-        current_pos = end;
-        current_message_index += 1;
+    fn folder_info_with_files(files: &[(&str, &str)]) -> FolderInfo {
+        let file_infos: Vec<FileInfo> = files.iter().map(|(path, content)| {
+            let mut file_info = FileInfo::new(path.to_string(), content.len(), false);
+            file_info.file_content = Some(content.to_string());
+            file_info
+        }).collect();
+        let size_in_bytes = file_infos.iter().map(|file_info| file_info.size_in_bytes).sum();
+        FolderInfo{ folder_path: String::from("folder"), file_infos, skipped_subtrees: vec!(), size_in_bytes }
     }
 
-    let mut prologue = String::from("[FolderToAI]");
-    prologue += &format!("\n\nMessage 1/{}:\n\nThis and the message(s) that follow will provide you relevant information about the files within the folder \"{}\", which occupies {}. There are {} messages in total.", total_n_messages, folder_info.folder_path, Size::from_bytes(folder_info.size_in_bytes).to_string(), total_n_messages);
-    prologue += "\n\nAt the beginning of each message, its index will be stated, along with the total number of messages. Each file's content will be be between lines that read \"--- BEGINNING OF CONTENT ---\" and \"--- END OF CONTENT ---\". Note that these delimiters may be split in between messages but they will all eventually be there once all the parts get sent.";
-    prologue += &format!("\n\nThe messages will contain at most {} characters, including line breaks. Please acknowledge that you get all the messages correctly and in sequence, given the indices provided at the beginning of each message. Warn me about any gaps (missing messages) and make sure you receive all {} of them in order.", MAXIMUM_AMOUNT_OF_CHARACTERS_ALLOWED_IN_LLM_MODEL, total_n_messages);
+    /// (message index, part index, number of parts, first line number, last line number, content) of each part of the file at `path`.
+    fn parts_of_file(messages: &[String], path: &str) -> Vec<(usize, usize, usize, usize, usize, String)> {
+        let part_header_start = format!("File: {} (part ", path);
+        let mut parts = vec!();
+        for (message_index, message) in messages.iter().enumerate() {
+            for (header_start, _) in message.match_indices(&part_header_start) {
+                let header = message[header_start + part_header_start.len()..].lines().next().unwrap();
+                let (part_numbers, line_numbers) = header.split_once("), lines ").unwrap();
+                let (part_index, n_parts) = part_numbers.split_once('/').unwrap();
+                let (first_line_number, last_line_number) = line_numbers.split_once('–').unwrap();
 
-    let mut folder_representation_messages: Vec<String> = vec!();
-    folder_representation_messages.push(prologue);
-    for file_contents_part in file_contents_parts {
-        folder_representation_messages.push(file_contents_part);
+                let content_start = header_start + message[header_start..].find(BEGINNING_OF_CONTENT_DELIMITER).unwrap() + BEGINNING_OF_CONTENT_DELIMITER.len() + 1;
+                let content_end = content_start + message[content_start..].find(END_OF_CONTENT_DELIMITER).unwrap();
+                // the template breaks the line after the content, whether or not the part ends with a line break
+                let part_content = message[content_start..content_end].strip_suffix('\n').unwrap();
+                parts.push((message_index, part_index.parse().unwrap(), n_parts.parse().unwrap(), first_line_number.parse().unwrap(), last_line_number.parse().unwrap(), part_content.to_string()));
+            }
+        }
+        parts
+    }
+
+    #[test]
+    fn files_that_fit_are_kept_whole_and_packed_together() {
+        let folder_info = folder_info_with_files(&[("a.txt", "first\n"), ("b.txt", "second\n"), ("c.txt", "third\n")]);
+        let messages = message_renderer(1000).render(&folder_info).unwrap();
+
+        assert_eq!(messages.len(), 2);
+        for path in ["a.txt", "b.txt", "c.txt"] {
+            assert!(messages[1].contains(&format!("File: {}\n", path)));
+        }
+        assert!(!messages[1].contains("(part "));
+    }
+
+    #[test]
+    fn files_that_do_not_fit_are_split_into_parts_at_line_boundaries() {
+        let content: String = (1..=200).map(|line_number| format!("line number {}\n", line_number)).collect();
+        let folder_info = folder_info_with_files(&[("small.txt", "small\n"), ("big.txt", &content)]);
+        let maximum_message_size = 1000;
+        let messages = message_renderer(maximum_message_size).render(&folder_info).unwrap();
+
+        for message in &messages {
+            assert!(CharacterCounter.count(message) <= maximum_message_size);
+        }
+
+        let parts = parts_of_file(&messages, "big.txt");
+        assert!(parts.len() > 1);
+        let mut next_line_number = 1;
+        for (part_index, (message_index, part_number, n_parts, first_line_number, last_line_number, part_content)) in parts.iter().enumerate() {
+            assert_eq!(*part_number, part_index + 1);
+            assert_eq!(*n_parts, parts.len());
+            // each part starts a message of its own, except possibly the first one
+            assert!(part_index == 0 || messages[*message_index].starts_with(&format!("Message {}/", message_index + 1)) && *message_index == parts[part_index - 1].0 + 1);
+            assert_eq!(*first_line_number, next_line_number);
+            assert_eq!(part_content.lines().count(), last_line_number - first_line_number + 1);
+            assert!(part_content.ends_with('\n'));
+            next_line_number = last_line_number + 1;
+        }
+        assert_eq!(next_line_number, 201);
+        assert_eq!(parts.iter().map(|part| part.5.as_str()).collect::<String>(), content);
+    }
+
+    #[test]
+    fn empty_files_too_large_for_a_message_get_an_empty_part() {
+        let long_path = format!("{}empty.txt", "directory/".repeat(100));
+        let folder_info = folder_info_with_files(&[("a.txt", &"x\n".repeat(60)), (&long_path, "")]);
+        let rendered_messages = message_renderer(1000).render_with_file_message_indices(&folder_info).unwrap();
+
+        assert_eq!(parts_of_file(&rendered_messages.messages, &long_path), vec![(2, 1, 1, 1, 1, String::new())]);
+        assert_eq!(rendered_messages.file_message_indices[1], vec![3]);
+    }
+
+    #[test]
+    fn lines_larger_than_a_part_are_split_within_the_line() {
+        let long_line = "x".repeat(5000);
+        let content = format!("short\n{}\nlast\n", long_line);
+        let folder_info = folder_info_with_files(&[("minified.js", &content)]);
+        let messages = message_renderer(1000).render(&folder_info).unwrap();
+
+        let parts = parts_of_file(&messages, "minified.js");
+        assert!(parts.len() > 3);
+        assert_eq!(parts.iter().map(|part| part.5.as_str()).collect::<String>(), content);
+        // the parts holding pieces of the long line all state it as their line range
+        assert!(parts[1..parts.len() - 1].iter().all(|part| part.3 == 2 && part.4 == 2));
+    }
+
+    #[test]
+    fn chunks_never_exceed_the_limit_and_keep_the_whole_text() {
+        let text = "alpha beta\ngamma\n\ndelta epsilon zeta eta\ntheta";
+        let chunks = split_into_chunks(text, 8, &CharacterCounter, false);
+
+        assert!(chunks.iter().all(|chunk| CharacterCounter.count(chunk) <= 8));
+        assert_eq!(chunks.concat(), text);
+    }
+
+    #[test]
+    fn a_grapheme_larger_than_the_limit_at_the_end_of_a_line_gets_a_chunk_of_its_own() {
+        let chunks = split_into_chunks("\r\na\n", 1, &ByteCounter, false);
+        assert_eq!(chunks, vec!["\r\n", "a", "\n"]);
+    }
+
+    #[test]
+    fn first_messages_larger_than_the_limit_are_rejected() {
+        let folder_info = folder_info_with_files(&[("a.txt", "a\n")]);
+        assert!(message_renderer(100).render(&folder_info).is_err_and(|error| error.contains("first message")));
+    }
+
+    #[test]
+    fn markdown_code_blocks_are_fenced_beyond_the_backticks_of_the_content() {
+        let readme = "# Usage\n\n```sh\ncargo run\n```";
+        let folder_info = folder_info_with_files(&[("README.md", readme), ("src/main.rs", "fn main() {}\n")]);
+        let messages = message_renderer(1000).output_format(OutputFormat::Markdown).render(&folder_info).unwrap();
+
+        assert!(messages[1].contains(&format!("````markdown\n{}\n````\n", readme)));
+        assert!(messages[1].contains("```rust\nfn main() {}\n```\n"));
+    }
+
+    #[test]
+    fn markdown_headings_keep_backticks_of_the_path_within_the_inline_code() {
+        let folder_info = folder_info_with_files(&[("notes `draft`.md", "draft\n")]);
+        let messages = message_renderer(1000).output_format(OutputFormat::Markdown).render(&folder_info).unwrap();
+
+        assert!(messages[1].contains("### ``notes `draft`.md`` ("));
+    }
+
+    #[test]
+    fn xml_messages_are_well_formed_on_their_own_and_keep_the_whole_content() {
+        let content: String = (1..=200).map(|line_number| format!("x[y[{}]]>z\n", line_number)).collect();
+        let folder_info = folder_info_with_files(&[("a<b>.rs", &content)]);
+        let maximum_message_size = 1500;
+        let messages = message_renderer(maximum_message_size).output_format(OutputFormat::Xml).render(&folder_info).unwrap();
+
+        assert!(messages.len() > 2);
+        let mut decoded_content = String::new();
+        for message in &messages[1..] {
+            assert!(CharacterCounter.count(message) <= maximum_message_size);
+            assert_eq!(message.matches("<document path=\"a&lt;b&gt;.rs\"").count(), message.matches("</document>").count());
+            for encoded_part in message.split("<content>").skip(1) {
+                let encoded_part = encoded_part.split_once("</content>").unwrap().0;
+                let cdata = encoded_part.strip_prefix("<![CDATA[").unwrap().strip_suffix("]]>").unwrap();
+                decoded_content += &cdata.replace("]]]]><![CDATA[>", "]]>");
+            }
+        }
+        assert_eq!(decoded_content, content);
+    }
+
+    #[test]
+    fn file_message_indices_point_to_the_messages_holding_each_file() {
+        let content: String = (1..=200).map(|line_number| format!("line number {}\n", line_number)).collect();
+        let mut folder_info = folder_info_with_files(&[("small.txt", "small\n"), ("big.txt", &content), ("ignored.txt", "")]);
+        folder_info.file_infos[2].should_be_ignored = true;
+
+        for split_on_line_boundaries in [true, false] {
+            let rendered_messages = message_renderer(1000).split_on_line_boundaries(split_on_line_boundaries).render_with_file_message_indices(&folder_info).unwrap();
+            let file_message_indices = &rendered_messages.file_message_indices;
+
+            assert_eq!(file_message_indices.len(), 3);
+            assert_eq!(file_message_indices[0], vec![2]);
+            assert!(file_message_indices[1].len() > 1);
+            assert!(file_message_indices[1].windows(2).all(|indices| indices[1] == indices[0] + 1));
+            assert_eq!(*file_message_indices[1].last().unwrap(), rendered_messages.messages.len());
+            assert!(rendered_messages.messages[file_message_indices[1][0] - 1].contains("big.txt"));
+            assert!(file_message_indices[2].is_empty());
+        }
     }
 
-    return Ok(folder_representation_messages);
-}
\ No newline at end of file
+    #[test]
+    fn messages_exceeding_the_context_budget_are_rejected() {
+        let content = "x\n".repeat(1000);
+        let folder_info = folder_info_with_files(&[("a.txt", &content)]);
+        let total_size: usize = message_renderer(1000).render(&folder_info).unwrap().iter().map(|message| message.chars().count()).sum();
+
+        assert!(message_renderer(1000).context_budget(Some(total_size)).render(&folder_info).is_ok());
+        assert!(message_renderer(1000).context_budget(Some(total_size - 1)).render(&folder_info).is_err_and(|error| error.contains("context budget")));
+    }
+
+    #[test]
+    fn folders_without_relevant_files_still_get_the_closing_message() {
+        let mut folder_info = folder_info_with_files(&[("ignored.txt", "")]);
+        folder_info.file_infos[0].should_be_ignored = true;
+        let message_renderer = MessageRenderer::new().maximum_message_size(1000).task_prompt(Some(String::from("Review for bugs")));
+        let rendered_messages = message_renderer.render_with_file_message_indices(&folder_info).unwrap();
+
+        let session_id = rendered_messages.session_id.unwrap();
+        assert_eq!(rendered_messages.messages.len(), 2);
+        assert!(rendered_messages.messages[0].contains(&format!("Message 1/2 [session {}]:", session_id)) && rendered_messages.messages[0].contains("no relevant files"));
+        assert!(rendered_messages.messages[1].starts_with(&format!("Message 2/2 [session {}, checksum ", session_id)) && rendered_messages.messages[1].ends_with("Review for bugs"));
+
+        let single_message = message_renderer.single_message(true).render(&folder_info).unwrap();
+        assert_eq!(single_message.len(), 1);
+        assert!(single_message[0].ends_with("Review for bugs"));
+    }
+
+    #[test]
+    fn single_message_mode_renders_every_file_in_one_message() {
+        let mut folder_info = folder_info_with_files(&[("a.txt", "first\n"), ("b.txt", "second\n"), ("ignored.txt", "")]);
+        folder_info.file_infos[2].should_be_ignored = true;
+        let rendered_messages = message_renderer(1000).single_message(true).render_with_file_message_indices(&folder_info).unwrap();
+
+        assert_eq!(rendered_messages.messages.len(), 1);
+        let single_message = &rendered_messages.messages[0];
+        assert!(single_message.contains("File: a.txt\n") && single_message.contains("File: b.txt\n"));
+        assert!(!single_message.contains("ignored.txt") && !single_message.contains("Message 1/"));
+        assert_eq!(rendered_messages.file_message_indices, vec![vec![1], vec![1], vec!()]);
+    }
+
+    #[test]
+    fn single_messages_larger_than_the_limit_are_rejected() {
+        let content = "x".repeat(2000);
+        let folder_info = folder_info_with_files(&[("a.txt", &content)]);
+        assert!(message_renderer(1000).single_message(true).render(&folder_info).is_err_and(|error| error.contains("single message")));
+    }
+
+    #[test]
+    fn single_messages_exceeding_the_context_budget_are_rejected() {
+        let content = "x".repeat(2000);
+        let folder_info = folder_info_with_files(&[("a.txt", &content)]);
+        assert!(message_renderer(10_000).single_message(true).render(&folder_info).is_ok());
+        assert!(message_renderer(10_000).single_message(true).context_budget(Some(2000)).render(&folder_info).is_err_and(|error| error.contains("single message") && error.contains("context budget")));
+    }
+}
@@ -0,0 +1,144 @@
+use globset::{Glob, GlobBuilder, GlobSet, GlobSetBuilder};
+use std::path::Path;
+
+/// Include/exclude glob filter, evaluated against paths relative to the scanned folder (with `/` as separator).
+///
+/// `*` does not cross folder boundaries while `**` does, so `docs/*.md` only matches files directly within `docs`
+/// and `src/**/*.rs` matches them at any depth. A file passes the filter if it matches at least one include glob
+/// (or there are none) and no exclude glob. Include globs starting with `!` are treated as exclude globs, so
+/// `["src/**", "!**/*_test.go"]` can be given as a single list.
+///
+/// Sensitive-file globs, set with [`with_sensitive_file_globs`](Self::with_sensitive_file_globs), do not exclude files; they flag
+/// the ones that are withheld unless explicitly allowed.
+#[derive(Debug, Clone)]
+pub struct FileFilter {
+    included_file_globs: Option<GlobSet>,
+    excluded_file_globs: GlobSet,
+    sensitive_file_globs: GlobSet
+}
+
+impl FileFilter {
+    pub fn new(included_file_globs: &[String], excluded_file_globs: &[String]) -> Result<Self, String> {
+        let mut include_builder = GlobSetBuilder::new();
+        let mut exclude_builder = GlobSetBuilder::new();
+        let mut n_included_file_globs: usize = 0;
+
+        for included_file_glob in included_file_globs {
+            if let Some(negated_glob) = included_file_glob.strip_prefix('!') {
+                exclude_builder.add(Self::build_glob(negated_glob)?);
+            }
+            else {
+                include_builder.add(Self::build_glob(included_file_glob)?);
+                n_included_file_globs += 1;
+            }
+        }
+        for excluded_file_glob in excluded_file_globs {
+            exclude_builder.add(Self::build_glob(excluded_file_glob.strip_prefix('!').unwrap_or(excluded_file_glob))?);
+        }
+
+        let included_file_globs = if n_included_file_globs > 0 { Some(include_builder.build().map_err(|error| error.to_string())?) } else { None };
+        let excluded_file_globs = exclude_builder.build().map_err(|error| error.to_string())?;
+
+        Ok(FileFilter{ included_file_globs, excluded_file_globs, sensitive_file_globs: GlobSet::empty() })
+    }
+
+    pub fn with_sensitive_file_globs(mut self, sensitive_file_globs: &[String]) -> Result<Self, String> {
+        let mut sensitive_builder = GlobSetBuilder::new();
+        for sensitive_file_glob in sensitive_file_globs {
+            sensitive_builder.add(Self::build_glob(sensitive_file_glob)?);
+        }
+        self.sensitive_file_globs = sensitive_builder.build().map_err(|error| error.to_string())?;
+        Ok(self)
+    }
+
+    fn build_glob(glob: &str) -> Result<Glob, String> {
+        GlobBuilder::new(glob).literal_separator(true).build().map_err(|error| format!("invalid glob \"{}\": {}", glob, error.kind()))
+    }
+
+    pub fn is_valid_glob(glob: &str) -> bool {
+        !glob.is_empty() && Self::build_glob(glob.strip_prefix('!').unwrap_or(glob)).is_ok()
+    }
+
+    /// Whether there are no globs at all, in which case every file passes.
+    pub fn is_empty(&self) -> bool {
+        self.included_file_globs.is_none() && self.excluded_file_globs.is_empty()
+    }
+
+    pub fn is_excluded(&self, relative_path: &Path) -> bool {
+        if let Some(included_file_globs) = &self.included_file_globs
+            && !included_file_globs.is_match(relative_path) {
+            return true;
+        }
+        self.excluded_file_globs.is_match(relative_path)
+    }
+
+    pub fn is_sensitive(&self, relative_path: &Path) -> bool {
+        self.sensitive_file_globs.is_match(relative_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn globs(globs: &[&str]) -> Vec<String> {
+        globs.iter().map(|glob| glob.to_string()).collect()
+    }
+
+    #[test]
+    fn single_stars_do_not_cross_folder_boundaries() {
+        let file_filter = FileFilter::new(&globs(&["docs/*.md", "src/**/*.rs"]), &[]).unwrap();
+
+        assert!(!file_filter.is_excluded(Path::new("docs/readme.md")));
+        assert!(file_filter.is_excluded(Path::new("docs/guide/intro.md")));
+        assert!(!file_filter.is_excluded(Path::new("src/main.rs")));
+        assert!(!file_filter.is_excluded(Path::new("src/core/parser.rs")));
+        assert!(file_filter.is_excluded(Path::new("build.rs")));
+    }
+
+    #[test]
+    fn exclude_globs_win_over_include_globs() {
+        let file_filter = FileFilter::new(&globs(&["src/**"]), &globs(&["**/*_test.go"])).unwrap();
+
+        assert!(!file_filter.is_excluded(Path::new("src/server.go")));
+        assert!(file_filter.is_excluded(Path::new("src/server_test.go")));
+        assert!(file_filter.is_excluded(Path::new("README.md")));
+    }
+
+    #[test]
+    fn negated_include_globs_are_exclude_globs() {
+        let file_filter = FileFilter::new(&globs(&["src/**", "!**/*_test.go"]), &[]).unwrap();
+
+        assert!(!file_filter.is_excluded(Path::new("src/server.go")));
+        assert!(file_filter.is_excluded(Path::new("src/server_test.go")));
+    }
+
+    #[test]
+    fn without_include_globs_every_file_not_excluded_passes() {
+        let file_filter = FileFilter::new(&[], &globs(&["*.lock"])).unwrap();
+
+        assert!(!file_filter.is_empty());
+        assert!(!file_filter.is_excluded(Path::new("src/main.rs")));
+        assert!(file_filter.is_excluded(Path::new("Cargo.lock")));
+        assert!(!file_filter.is_excluded(Path::new("vendor/Cargo.lock")));
+        assert!(FileFilter::new(&[], &[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn invalid_globs_are_rejected() {
+        assert!(FileFilter::new(&globs(&["src/[a"]), &[]).is_err());
+        assert!(!FileFilter::is_valid_glob("src/[a"));
+        assert!(!FileFilter::is_valid_glob(""));
+        assert!(FileFilter::is_valid_glob("!**/*.min.js"));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn paths_with_backslashes_are_matched_like_ones_with_slashes() {
+        let file_filter = FileFilter::new(&globs(&["src/*.rs"]), &globs(&["src/generated/**"])).unwrap();
+
+        assert!(!file_filter.is_excluded(Path::new("src\\main.rs")));
+        assert!(file_filter.is_excluded(Path::new("src\\core\\parser.rs")));
+        assert!(file_filter.is_excluded(Path::new("src\\generated\\bindings.rs")));
+    }
+}
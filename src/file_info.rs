@@ -1,6 +1,9 @@
+use crate::redaction::Redaction;
+
 use std::fmt;
 
-#[derive(Debug)]
+/// A file found while scanning a folder.
+#[derive(Debug, Clone)]
 pub struct FileInfo
 {
     pub filepath: String,
@@ -8,12 +11,21 @@ pub struct FileInfo
     pub is_binary: bool,
     pub file_too_large: bool,
     pub should_be_ignored: bool,
-    pub file_content: Option<String>
+    /// Whether the file, not ignored otherwise, matches a sensitive-file glob (see [`ScanOptions::sensitive_file_globs`](crate::ScanOptions::sensitive_file_globs));
+    /// it is then withheld (ignored) unless explicitly allowed.
+    pub is_sensitive: bool,
+    /// Time of the last modification, in seconds since the Unix epoch, if the file system provides it.
+    pub modified_time: Option<u64>,
+    pub file_content: Option<String>,
+    /// Whether the content is a unified diff against the file's previous version rather than the file's content, when sharing changes.
+    pub content_is_diff: bool,
+    /// Secrets that were redacted from the content.
+    pub redactions: Vec<Redaction>
 }
 
 impl FileInfo {
     pub fn new(filepath: String, size_in_bytes: usize, should_be_ignored: bool) -> Self {
-        FileInfo{ filepath, size_in_bytes, is_binary: false, file_too_large: false, should_be_ignored: should_be_ignored, file_content: None }
+        FileInfo{ filepath, size_in_bytes, is_binary: false, file_too_large: false, should_be_ignored, is_sensitive: false, modified_time: None, file_content: None, content_is_diff: false, redactions: Vec::new() }
     }
 
     pub fn content_should_be_loaded(&self) -> bool {
@@ -23,6 +35,10 @@ impl FileInfo {
     pub fn has_content_loaded(&self) -> bool {
         self.file_content.is_some()
     }
+
+    pub fn is_withheld_as_sensitive(&self) -> bool {
+        self.is_sensitive && self.should_be_ignored
+    }
 }
 
 impl fmt::Display for FileInfo{
@@ -112,6 +112,28 @@ lazy_static! {
         "rbmnu", "rbres", "rbtbar", "rbuistate", "rhtml", "raml", "qml", "qbs", "pro", "pri",
         "r", "rd", "rsx", "gcode", "gco", "gams", "gms", "mtml", "muf", "maxscript", "ms", "mcr",
     ];
+
+    // extensions (or whole file names) and the names of their languages, as used to tag code blocks
+    static ref LANGUAGES: Vec<(&'static str, &'static str)> = vec![
+        ("rs", "rust"), ("py", "python"), ("pyx", "cython"), ("js", "javascript"), ("mjs", "javascript"), ("cjs", "javascript"),
+        ("jsx", "jsx"), ("ts", "typescript"), ("tsx", "tsx"), ("java", "java"), ("kt", "kotlin"), ("kts", "kotlin"),
+        ("scala", "scala"), ("groovy", "groovy"), ("gradle", "groovy"), ("c", "c"), ("h", "c"), ("cpp", "cpp"), ("cc", "cpp"),
+        ("cxx", "cpp"), ("hpp", "cpp"), ("cu", "cuda"), ("cuh", "cuda"), ("cs", "csharp"), ("fs", "fsharp"), ("fsx", "fsharp"),
+        ("go", "go"), ("rb", "ruby"), ("php", "php"), ("pl", "perl"), ("perl", "perl"), ("swift", "swift"), ("lua", "lua"),
+        ("r", "r"), ("m", "objectivec"), ("erl", "erlang"), ("hrl", "erlang"), ("ex", "elixir"), ("exs", "elixir"),
+        ("hs", "haskell"), ("ml", "ocaml"), ("clj", "clojure"), ("lisp", "lisp"), ("el", "elisp"), ("scm", "scheme"),
+        ("dart", "dart"), ("zig", "zig"), ("f90", "fortran"), ("f", "fortran"), ("f95", "fortran"), ("glsl", "glsl"), ("hlsl", "hlsl"),
+        ("sh", "bash"), ("bash", "bash"), ("zsh", "zsh"), ("fish", "fish"), ("ps1", "powershell"), ("psm1", "powershell"),
+        ("bat", "batch"), ("cmd", "batch"), ("vbs", "vbscript"), ("sql", "sql"), ("html", "html"), ("htm", "html"),
+        ("css", "css"), ("scss", "scss"), ("sass", "sass"), ("less", "less"), ("vue", "vue"), ("svelte", "svelte"),
+        ("json", "json"), ("yaml", "yaml"), ("yml", "yaml"), ("toml", "toml"), ("xml", "xml"), ("ini", "ini"), ("cfg", "ini"),
+        ("md", "markdown"), ("rst", "rst"), ("tex", "latex"), ("csv", "csv"), ("tf", "hcl"), ("hcl", "hcl"), ("proto", "protobuf"),
+        ("graphql", "graphql"), ("gql", "graphql"), ("Makefile", "makefile"), ("Dockerfile", "dockerfile"), ("CMakeLists.txt", "cmake"), ("cmake", "cmake"),
+        ("tcl", "tcl"), ("awk", "awk"), ("sbt", "scala"), ("tsv", "tsv"), ("bib", "bibtex"), ("sty", "latex"), ("cls", "latex"),
+        ("properties", "properties"), ("conf", "ini"), ("env", "dotenv"), ("dotenv", "dotenv"), ("gitignore", "gitignore"),
+        ("f03", "fortran"), ("f08", "fortran"), ("f77", "fortran"), ("for", "fortran"), ("es", "erlang"), ("escript", "erlang"),
+        ("fsi", "fsharp"), ("frag", "glsl"), ("vert", "glsl"), ("build.xml", "xml"),
+    ];
 }
 
 pub fn file_is_binary(path: &str) -> bool {
@@ -160,7 +182,7 @@ pub fn file_is_binary(path: &str) -> bool {
     };
 
     // check if it's valid UTF8
-    let utf8_valid = std::str::from_utf8(&content_buffer[..content_bytes_read]).map_or(false, |s| {
+    let utf8_valid = std::str::from_utf8(&content_buffer[..content_bytes_read]).is_ok_and(|s| {
         // check if the string contains any invalid UTF-8 sequences
         s.chars().all(|c| c.is_ascii() || c.is_ascii_graphic() || c.is_ascii_control() || c.is_ascii_punctuation())
     });
@@ -171,15 +193,48 @@ pub fn file_is_binary(path: &str) -> bool {
 
     const PERCENTAGE_OF_NON_ASCII_CHARACTERS_ALLOWED: f64 = 0.20;
     // check for null bytes or non-UTF8 sequences, allowing some non-ASCII characters as defined in 'PERCENTAGE_OF_NON_ASCII_CHARACTERS_ALLOWED'
-    let non_ascii_count = content_buffer.iter().take(content_bytes_read).filter(|&&b| b == 0 || b < 0x20 || b > 0x7E).count();
+    let non_ascii_count = content_buffer.iter().take(content_bytes_read).filter(|&&b| b == 0 || !(0x20..=0x7E).contains(&b)).count();
     let threshold = (content_bytes_read as f64 * PERCENTAGE_OF_NON_ASCII_CHARACTERS_ALLOWED).ceil() as usize; // allow up to a certain percentage of non-ASCII characters
 
-    return non_ascii_count > threshold;
+    non_ascii_count > threshold
+}
+
+/// Name of the language of a file, judging by its name or extension (e.g. "rust" for "src/main.rs"); empty if unknown.
+pub fn language_of_file(path: &str) -> &'static str {
+    let file_name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+    if let Some((_, language)) = LANGUAGES.iter().find(|(name, _)| *name == file_name) {
+        return language;
+    }
+    if let Some((_, extension)) = file_name.rsplit_once('.') {
+        let extension = extension.to_lowercase();
+        if let Some((_, language)) = LANGUAGES.iter().find(|(name, _)| *name == extension) {
+            return language;
+        }
+    }
+    ""
 }
 
-pub fn get_file_size_in_bytes(path: &str) -> Result<usize, ()> {
+/// Whether `path` (with either kind of separator) stays within the folder it is relative to: it is neither empty nor absolute,
+/// and does not go up with "..".
+pub fn is_safe_relative_path(path: &str) -> bool {
+    let path = path.replace('\\', "/");
+    !path.is_empty() && std::path::Path::new(&path).components().all(|component| matches!(component, std::path::Component::Normal(_) | std::path::Component::CurDir))
+}
+
+pub fn get_file_size_in_bytes(path: &str) -> Result<usize, String> {
     if let Ok(metadata) = std::fs::metadata(path) {
         return Ok(metadata.len() as usize);
     }
-    Err(())
+    Err(format!("could not obtain the size of \"{}\"", path))
+}
+
+/// Absolute form of a folder path, so that what is kept about a folder is found however its path was given (e.g. relatively, from the CLI).
+pub fn normalized_folder_path(folder_path: &str) -> String {
+    std::path::absolute(folder_path).map_or_else(|_| folder_path.to_string(), |absolute_folder_path| absolute_folder_path.to_string_lossy().to_string())
+}
+
+/// Time of the last modification of a file, in seconds since the Unix epoch.
+pub fn get_file_modified_time(path: &str) -> Option<u64> {
+    let modified_time = std::fs::metadata(path).ok()?.modified().ok()?;
+    modified_time.duration_since(std::time::UNIX_EPOCH).ok().map(|duration| duration.as_secs())
 }
\ No newline at end of file
@@ -1,59 +1,83 @@
+use crate::file_filter::FileFilter;
 use crate::file_info::FileInfo;
-use crate::file_utils::{file_is_binary, get_file_size_in_bytes};
+use crate::file_utils::{file_is_binary, get_file_modified_time, get_file_size_in_bytes};
+use crate::ignore_rules::IgnoreRules;
+use crate::redaction;
 
-use crate::settings::Settings;
+use crate::scan_options::ScanOptions;
+use crate::scan_progress::ScanProgress;
+use crate::skipped_subtree::SkippedSubtree;
+use rayon::ThreadPool;
+use rayon::prelude::*;
 use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 
-#[derive(Debug)]
+/// Result of scanning a folder: its files (with paths relative to the folder) and what has been determined about them.
+///
+/// Besides [`FolderScanner`](crate::FolderScanner), it can be built with [`FolderInfo::new`] and then completed step by step,
+/// which allows reporting progress: the binarity of the files, then which files are too large, then the loading of their contents.
+/// The first and last phases can be run one file at a time or for all files at once, in parallel.
+#[derive(Debug, Clone)]
 pub struct FolderInfo {
     pub folder_path: String,
     pub file_infos: Vec<FileInfo>,
+    /// Ignored folders, which have not been walked into; their files are not part of `file_infos`.
+    pub skipped_subtrees: Vec<SkippedSubtree>,
+    /// Total size of the files that are not ignored.
     pub size_in_bytes: usize
 }
 
+/// What stays the same across the directories of a walk.
+struct WalkContext<'a> {
+    root_path: &'a Path,
+    options: &'a ScanOptions,
+    file_filter: &'a FileFilter,
+    cancellation_flag: &'a AtomicBool
+}
+
 impl FolderInfo {
-    pub fn new(folder_path: &String, settings: &Settings) -> Result<Self, ()> {
-        if let Ok(file_infos) = Self::obtain_file_infos(folder_path, settings, false) {
-            let mut size_in_bytes: usize = 0;
-            for file_info in &file_infos {
+    pub fn new(folder_path: &str, options: &ScanOptions) -> Result<Self, String> {
+        Self::new_with_cancellation(folder_path, options, &AtomicBool::new(false))
+    }
+
+    /// Like [`FolderInfo::new`], but stops walking the folder once `cancellation_flag` is set, keeping only the files listed until then.
+    pub fn new_with_cancellation(folder_path: &str, options: &ScanOptions, cancellation_flag: &AtomicBool) -> Result<Self, String> {
+        let (file_infos, skipped_subtrees) = Self::obtain_file_infos(folder_path, options, cancellation_flag)?;
+
+        let mut size_in_bytes: usize = 0;
+        for file_info in &file_infos {
+            if !file_info.should_be_ignored {
                 size_in_bytes += file_info.size_in_bytes;
             }
-
-            let folder_info: FolderInfo = FolderInfo{ folder_path: folder_path.to_string(), file_infos, size_in_bytes};
-            return Ok(folder_info);
         }
-        Err(())
+
+        let folder_info: FolderInfo = FolderInfo{ folder_path: folder_path.to_string(), file_infos, skipped_subtrees, size_in_bytes};
+        Ok(folder_info)
     }
 
-    pub fn get_number_of_files(&self) -> usize {
-        self.file_infos.len()
+    pub fn get_number_of_ignored_files(&self) -> usize {
+        self.file_infos.len() - self.get_number_of_files_not_ignored()
     }
 
-    pub fn get_number_of_files_whose_contents_should_be_loaded(&self) -> usize {
+    pub fn get_number_of_files_not_ignored(&self) -> usize {
         let mut n: usize = 0;
         for file_info in &self.file_infos {
-            if file_info.content_should_be_loaded() {
+            if !file_info.should_be_ignored {
                 n += 1;
             }
         }
         n
     }
 
-    fn should_ignore_file(path: &str, settings: &Settings) -> bool
-    {
-        let path = std::path::Path::new(&path);
-
-        for ancestor in path.ancestors() {
-            if let Some(dir_name) = ancestor.file_name() {
-                if let Some(dir_name_str) = dir_name.to_str() {
-                    if settings.ignored_subfolders.contains(dir_name_str) {
-                        return true;
-                    }
-                }
+    pub fn get_number_of_files_whose_contents_should_be_loaded(&self) -> usize {
+        let mut n: usize = 0;
+        for file_info in &self.file_infos {
+            if file_info.content_should_be_loaded() {
+                n += 1;
             }
         }
-
-        false
+        n
     }
 
     pub fn contains_at_least_one_file_that_should_not_be_ignored(&self) -> bool
@@ -66,67 +90,133 @@ impl FolderInfo {
         false
     }
 
-    pub fn obtain_file_infos(path: &str, settings: &Settings, recursive_call: bool) -> Result<Vec<FileInfo>, String>
+    fn obtain_file_infos(path: &str, options: &ScanOptions, cancellation_flag: &AtomicBool) -> Result<(Vec<FileInfo>, Vec<SkippedSubtree>), String>
     {
-        let mut result: Vec<FileInfo> = Vec::new();
-
-        if let Ok(entries) = std::fs::read_dir(path) {
-            for entry in entries {
-                if let Ok(dir_entry) = entry {
-                    if let Ok(file_type) = dir_entry.file_type() {
-                        let is_file = file_type.is_file();
-                        let is_directory = file_type.is_dir();
-                        if let Some(path) = dir_entry.path().to_str() {
-                            if is_file {
-                                if let Ok(file_size) = get_file_size_in_bytes(path) {
-                                    result.push(FileInfo::new(path.to_string(), file_size, Self::should_ignore_file(path, settings)));
-                                }
-                            }
-                            else if is_directory {
-                                if let Ok(file_infos_within_directory) = Self::obtain_file_infos(path, settings, true) {
-                                    for file_info in file_infos_within_directory {
-                                        result.push(file_info);
-                                    }
-                                }
+        let mut file_infos: Vec<FileInfo> = Vec::new();
+        let mut skipped_subtrees: Vec<SkippedSubtree> = Vec::new();
+
+        let file_filter = FileFilter::new(&options.included_file_globs, &options.excluded_file_globs)?.with_sensitive_file_globs(&options.sensitive_file_globs)?;
+        let mut ignore_rules = if options.respect_ignore_files { Some(IgnoreRules::new(path)) } else { None };
+        let context = WalkContext{ root_path: Path::new(path), options, file_filter: &file_filter, cancellation_flag };
+        Self::obtain_file_infos_within_directory(&context, Path::new(""), &mut ignore_rules, &mut file_infos, &mut skipped_subtrees)?;
+
+        Ok((file_infos, skipped_subtrees))
+    }
+
+    fn directory_should_be_skipped(directory_relative_path: &Path, options: &ScanOptions, ignore_rules: &Option<IgnoreRules>) -> bool
+    {
+        if let Some(directory_name) = directory_relative_path.file_name().and_then(|directory_name| directory_name.to_str())
+            && options.ignored_subfolders.contains(directory_name) {
+            return true;
+        }
+        ignore_rules.as_ref().is_some_and(|ignore_rules| ignore_rules.is_ignored(directory_relative_path, true))
+    }
+
+    fn obtain_file_infos_within_directory(context: &WalkContext, directory_relative_path: &Path, ignore_rules: &mut Option<IgnoreRules>, file_infos: &mut Vec<FileInfo>, skipped_subtrees: &mut Vec<SkippedSubtree>) -> Result<(), String>
+    {
+        let WalkContext{ root_path, options, file_filter, cancellation_flag } = *context;
+        let directory_path = root_path.join(directory_relative_path);
+        let Ok(entries) = std::fs::read_dir(&directory_path) else {
+            return Err(format!("could not read folder \"{}\"", directory_path.display()));
+        };
+
+        if let Some(ignore_rules) = ignore_rules.as_mut() {
+            ignore_rules.enter_directory(directory_relative_path);
+        }
+
+        // entries are sorted so that the order of the files does not depend on the file system
+        let mut dir_entries: Vec<std::fs::DirEntry> = entries.flatten().collect();
+        dir_entries.sort_by_key(|dir_entry| dir_entry.file_name());
+
+        for dir_entry in dir_entries {
+            if cancellation_flag.load(Ordering::Relaxed) {
+                break;
+            }
+            if let Ok(file_type) = dir_entry.file_type() {
+                let relative_path = directory_relative_path.join(dir_entry.file_name());
+                if let Some(relative_path_str) = relative_path.to_str() {
+                    if file_type.is_file() {
+                        if let Some(path) = root_path.join(&relative_path).to_str()
+                            && let Ok(file_size) = get_file_size_in_bytes(path) {
+                            let is_ignored = file_filter.is_excluded(&relative_path) || ignore_rules.as_ref().is_some_and(|ignore_rules| ignore_rules.is_ignored(&relative_path, false));
+                            // files that usually hold secrets are withheld unless explicitly allowed; ignored ones are not withheld, as
+                            // allowing them would not share them anyway
+                            let is_sensitive = !is_ignored && file_filter.is_sensitive(&relative_path);
+                            let is_withheld = is_sensitive && !options.allowed_sensitive_file_paths.contains(&relative_path_str.replace('\\', "/"));
+                            let mut file_info = FileInfo::new(relative_path_str.to_string(), file_size, is_ignored || is_withheld);
+                            file_info.is_sensitive = is_sensitive;
+                            file_info.modified_time = get_file_modified_time(path);
+                            file_infos.push(file_info);
+                        }
+                    }
+                    else if file_type.is_dir() {
+                        // ignored folders are not walked into at all, which matters for huge ones such as "node_modules"
+                        if Self::directory_should_be_skipped(&relative_path, options, ignore_rules) {
+                            let mut skipped_subtree = SkippedSubtree::new(relative_path_str.to_string());
+                            if options.summarize_skipped_subtrees {
+                                skipped_subtree.summary = Some(SkippedSubtree::summarize(&root_path.join(&relative_path)));
                             }
+                            skipped_subtrees.push(skipped_subtree);
+                        }
+                        else {
+                            // folders within the scanned one that cannot be read are simply left out
+                            let _ = Self::obtain_file_infos_within_directory(context, &relative_path, ignore_rules, file_infos, skipped_subtrees);
                         }
                     }
                 }
             }
         }
-        else {
-            return Err("could not obtain file infos".to_string())
-        }
 
-        if !recursive_call {
-            for file_info in &mut result {
-                file_info.filepath = file_info.filepath.replace(&format!("{}\\", path), "");
-            }
+        if let Some(ignore_rules) = ignore_rules.as_mut() {
+            ignore_rules.leave_directory();
         }
 
-        Ok(result)
+        Ok(())
     }
 
-    pub fn determine_binarity_of_next_file(&mut self, file_index: usize, settings: &Settings) {
-        let file_info = &mut self.file_infos[file_index];
-        if !Self::should_ignore_file(&file_info.filepath, settings) {
-            file_info.is_binary = file_is_binary(file_info.filepath.as_str());
+    fn determine_binarity_of_file(folder_path: &Path, file_info: &mut FileInfo) {
+        if !file_info.should_be_ignored
+            && let Some(absolute_path) = folder_path.join(&file_info.filepath).to_str() {
+            file_info.is_binary = file_is_binary(absolute_path);
         }
     }
 
-    pub fn determine_files_too_large(&mut self, max_file_size_in_bytes: usize, settings: &Settings) {
+    /// Determines whether the file at `file_index` is binary; meant to be called once for each index, in order.
+    pub fn determine_binarity_of_next_file(&mut self, file_index: usize) {
+        Self::determine_binarity_of_file(Path::new(&self.folder_path), &mut self.file_infos[file_index]);
+    }
+
+    /// Determines whether each file is binary, processing them in parallel on `thread_pool`; files are left unprocessed once `cancellation_flag` is set.
+    pub fn determine_binarity_of_all_files(&mut self, thread_pool: &ThreadPool, scan_progress: &ScanProgress, cancellation_flag: &AtomicBool) {
+        let folder_path = Path::new(&self.folder_path);
+        let file_infos = &mut self.file_infos;
+        thread_pool.install(|| {
+            file_infos.par_iter_mut().for_each(|file_info| {
+                if cancellation_flag.load(Ordering::Relaxed) {
+                    return;
+                }
+                // ignored files are not examined, so they do not count towards the progress either
+                if !file_info.should_be_ignored {
+                    Self::determine_binarity_of_file(folder_path, file_info);
+                    scan_progress.n_files_already_determined_whether_binary_or_not.fetch_add(1, Ordering::Relaxed);
+                }
+            });
+        });
+    }
+
+    /// Flags the files larger than `max_file_size_in_bytes`; meant to be called once the binarity of all files is determined.
+    pub fn determine_files_too_large(&mut self, max_file_size_in_bytes: usize) {
         for file_info in &mut self.file_infos {
-            if !Self::should_ignore_file(&file_info.filepath, settings) {
+            if !file_info.should_be_ignored {
                 file_info.file_too_large = file_info.size_in_bytes > max_file_size_in_bytes;
             }
         }
     }
 
-    pub fn load_next_file_content_if_required(&mut self, file_index: usize) -> bool
+    fn load_file_content_if_required(folder_path: &Path, file_info: &mut FileInfo) -> bool
     {
-        let file_info = &mut self.file_infos[file_index];
         if file_info.content_should_be_loaded() {
-            if let Ok(file_bytes) = fs::read(&file_info.filepath) {
+            if let Ok(file_bytes) = fs::read(folder_path.join(&file_info.filepath)) {
                 file_info.file_content = Some(String::from_utf8_lossy(&file_bytes).into_owned());
             }
             return true;
@@ -134,6 +224,61 @@ impl FolderInfo {
         false
     }
 
+    /// Loads the content of the file at `file_index` if it should be loaded, returning whether it should.
+    pub fn load_next_file_content_if_required(&mut self, file_index: usize) -> bool
+    {
+        Self::load_file_content_if_required(Path::new(&self.folder_path), &mut self.file_infos[file_index])
+    }
+
+    /// Loads the contents of the files that should be loaded, processing them in parallel on `thread_pool`; files are left unprocessed once `cancellation_flag` is set.
+    pub fn load_all_file_contents_if_required(&mut self, thread_pool: &ThreadPool, scan_progress: &ScanProgress, cancellation_flag: &AtomicBool)
+    {
+        let folder_path = Path::new(&self.folder_path);
+        let file_infos = &mut self.file_infos;
+        thread_pool.install(|| {
+            file_infos.par_iter_mut().for_each(|file_info| {
+                if cancellation_flag.load(Ordering::Relaxed) {
+                    return;
+                }
+                if Self::load_file_content_if_required(folder_path, file_info) {
+                    scan_progress.n_files_loaded.fetch_add(1, Ordering::Relaxed);
+                }
+                scan_progress.n_files_already_loaded_if_required_loading.fetch_add(1, Ordering::Relaxed);
+            });
+        });
+    }
+
+    /// Replaces the secrets in the loaded contents by `[REDACTED:<kind>]` (see [`crate::redaction`]), processing the files in parallel
+    /// on `thread_pool`; meant to be called once the contents are loaded.
+    pub fn redact_secrets_in_all_files(&mut self, thread_pool: &ThreadPool) {
+        let file_infos = &mut self.file_infos;
+        thread_pool.install(|| {
+            file_infos.par_iter_mut().for_each(|file_info| {
+                if let Some(file_content) = &file_info.file_content {
+                    let (redacted_content, redactions) = redaction::redact_secrets(file_content);
+                    if !redactions.is_empty() {
+                        file_info.file_content = Some(redacted_content);
+                        file_info.redactions = redactions;
+                    }
+                }
+            });
+        });
+    }
+
+    pub fn number_of_redactions(&self) -> usize {
+        self.file_infos.iter().map(|file_info| file_info.redactions.len()).sum()
+    }
+
+    /// Number of sensitive files that are withheld.
+    pub fn number_of_withheld_sensitive_files(&self) -> usize {
+        self.file_infos.iter().filter(|file_info| file_info.is_withheld_as_sensitive()).count()
+    }
+
+    /// Whether a sensitive file is shared, as allowed for the folder.
+    pub fn contains_shared_sensitive_files(&self) -> bool {
+        self.file_infos.iter().any(|file_info| file_info.is_sensitive && !file_info.should_be_ignored)
+    }
+
     pub fn number_of_files_that_could_not_be_loaded(&self) -> usize {
         let mut n: usize = 0;
         for file_info in &self.file_infos {
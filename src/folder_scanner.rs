@@ -0,0 +1,109 @@
+use crate::folder_info::FolderInfo;
+use crate::scan_options::ScanOptions;
+use crate::scan_progress::ScanProgress;
+use std::sync::atomic::AtomicBool;
+
+/// Builder that scans a folder and loads the contents of its relevant files.
+///
+/// This runs the whole pipeline at once; applications that need to report progress can instead drive
+/// [`FolderInfo`] phase by phase, reading a shared [`ScanProgress`] meanwhile, as the GUI does.
+pub struct FolderScanner {
+    folder_path: String,
+    options: ScanOptions
+}
+
+impl FolderScanner {
+    /// Creates a scanner for `folder_path` using the default [`ScanOptions`].
+    pub fn new(folder_path: &str) -> Self {
+        FolderScanner{ folder_path: folder_path.to_string(), options: ScanOptions::new() }
+    }
+
+    /// Replaces all the options at once.
+    pub fn options(mut self, options: ScanOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Adds a subfolder name to the ones being ignored.
+    pub fn ignore_subfolder(mut self, subfolder: &str) -> Self {
+        self.options.ignored_subfolders.insert(subfolder.to_string());
+        self
+    }
+
+    /// Replaces the subfolder names being ignored (including the default ones).
+    pub fn ignored_subfolders<I, S>(mut self, subfolders: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>
+    {
+        self.options.ignored_subfolders = subfolders.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Adds a glob that files must match to not be ignored (once there is one, files matching none of them are ignored).
+    pub fn include_files(mut self, glob: &str) -> Self {
+        self.options.included_file_globs.push(glob.to_string());
+        self
+    }
+
+    /// Adds a glob whose matching files are ignored.
+    pub fn exclude_files(mut self, glob: &str) -> Self {
+        self.options.excluded_file_globs.push(glob.to_string());
+        self
+    }
+
+    /// Sets whether `.gitignore`, `.ignore` and `.foldertoaiignore` files are honored (see [`IgnoreRules`](crate::ignore_rules::IgnoreRules)).
+    pub fn respect_ignore_files(mut self, respect_ignore_files: bool) -> Self {
+        self.options.respect_ignore_files = respect_ignore_files;
+        self
+    }
+
+    /// Sets whether the approximate amount of files and size of the skipped (ignored) folders are determined.
+    pub fn summarize_skipped_subtrees(mut self, summarize_skipped_subtrees: bool) -> Self {
+        self.options.summarize_skipped_subtrees = summarize_skipped_subtrees;
+        self
+    }
+
+    /// Sets the amount of threads used to determine binary files and load contents; 0 means one per CPU.
+    pub fn n_threads(mut self, n_threads: usize) -> Self {
+        self.options.n_threads = n_threads;
+        self
+    }
+
+    /// Sets the maximum size (in bytes) of the files whose contents are loaded.
+    pub fn file_size_limit(mut self, file_size_limit: usize) -> Self {
+        self.options.file_size_limit = file_size_limit;
+        self
+    }
+
+    /// Sets whether secrets are redacted from the loaded contents.
+    pub fn redact_secrets(mut self, redact_secrets: bool) -> Self {
+        self.options.redact_secrets = redact_secrets;
+        self
+    }
+
+    /// Shares the sensitive file at `relative_path` (relative to the folder, with `/` as separator) instead of withholding it.
+    pub fn allow_sensitive_file(mut self, relative_path: &str) -> Self {
+        self.options.allowed_sensitive_file_paths.insert(relative_path.to_string());
+        self
+    }
+
+    /// Scans the folder: lists its files, determines which are binary or too large, loads the contents of the remaining ones and
+    /// redacts the secrets from them.
+    pub fn scan(&self) -> Result<FolderInfo, String> {
+        let mut folder_info = FolderInfo::new(&self.folder_path, &self.options)?;
+
+        let thread_pool = self.options.build_thread_pool()?;
+        let scan_progress = ScanProgress::new();
+        let cancellation_flag = AtomicBool::new(false);
+
+        folder_info.determine_binarity_of_all_files(&thread_pool, &scan_progress, &cancellation_flag);
+        folder_info.determine_files_too_large(self.options.file_size_limit);
+        folder_info.load_all_file_contents_if_required(&thread_pool, &scan_progress, &cancellation_flag);
+        if self.options.redact_secrets {
+            folder_info.redact_secrets_in_all_files(&thread_pool);
+        }
+
+        Ok(folder_info)
+    }
+}
@@ -0,0 +1,367 @@
+use crate::folder_info::FolderInfo;
+use crate::redaction::{self, Redaction};
+
+use serde::{Serialize, Deserialize};
+use std::collections::{HashMap, HashSet};
+use std::process::Command;
+
+// number of recent commits stated, and at most that many of a commit range
+const N_RECENT_COMMITS: usize = 5;
+const MAXIMUM_N_RANGE_COMMITS: usize = 20;
+// paths given to a single `git diff`, which keeps its command line well below the limits of every platform
+const N_PATHSPECS_PER_DIFF: usize = 100;
+
+/// Which changes of a git repository are shared.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub enum GitChangeSet {
+    /// The changes in the working tree that are not staged, along with untracked files.
+    Unstaged,
+    /// The changes staged for the next commit.
+    Staged,
+    /// The changes made by the commits from `base` (excluded) to HEAD.
+    CommitRange { base: String }
+}
+
+impl GitChangeSet {
+    pub fn label(&self) -> &str {
+        match self {
+            GitChangeSet::Unstaged => "unstaged changes",
+            GitChangeSet::Staged => "staged changes",
+            GitChangeSet::CommitRange { .. } => "commits since a base revision"
+        }
+    }
+
+    /// Arguments of `git diff` (after "diff") selecting the changes.
+    fn diff_arguments(&self) -> Vec<String> {
+        match self {
+            GitChangeSet::Unstaged => vec!(),
+            GitChangeSet::Staged => vec![String::from("--cached")],
+            GitChangeSet::CommitRange { base } => vec![format!("{}..HEAD", base)]
+        }
+    }
+
+    /// The `git diff` command giving the changes, as a user would type it.
+    pub fn diff_command(&self) -> String {
+        std::iter::once(String::from("git diff")).chain(self.diff_arguments()).collect::<Vec<String>>().join(" ")
+    }
+
+    /// What the changes are, e.g. "staged for the next commit".
+    pub fn description(&self) -> String {
+        match self {
+            GitChangeSet::Unstaged => String::from("not staged yet (the working tree against the index)"),
+            GitChangeSet::Staged => String::from("staged for the next commit (the index against HEAD)"),
+            GitChangeSet::CommitRange { base } => format!("made by the commits from {} to HEAD", base)
+        }
+    }
+
+    /// Checks that the base of a commit range names a revision rather than being empty or looking like an option of git.
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            GitChangeSet::CommitRange { base } => validate_base_revision(base),
+            _ => Ok(())
+        }
+    }
+
+    /// The change set with the base of a commit range (validated) replaced by the hash of its commit, so that git cannot take it for
+    /// anything else than a revision.
+    fn resolved(&self, folder_path: &str) -> Result<Self, String> {
+        match self {
+            GitChangeSet::CommitRange { base } => {
+                validate_base_revision(base)?;
+                let base_commit_hash = run_git(folder_path, &["rev-parse", "--verify", "--quiet", "--end-of-options", &format!("{}^{{commit}}", base)])
+                    .map_err(|_| format!("the base revision \"{}\" is not a commit of the repository", base))?;
+                Ok(GitChangeSet::CommitRange { base: base_commit_hash.trim().to_string() })
+            },
+            _ => Ok(self.clone())
+        }
+    }
+
+    /// Which version of the touched files is given in full.
+    pub fn version_description(&self) -> &'static str {
+        match self {
+            GitChangeSet::Unstaged => "as it is in the working tree",
+            GitChangeSet::Staged => "as staged",
+            GitChangeSet::CommitRange { .. } => "as of HEAD"
+        }
+    }
+
+    /// Revision (as understood by `git show <revision>:<path>`) of the version of the touched files given in full;
+    /// None for the working tree, whose files are read as scanned.
+    fn content_revision(&self) -> Option<&'static str> {
+        match self {
+            GitChangeSet::Unstaged => None,
+            GitChangeSet::Staged => Some(""),
+            GitChangeSet::CommitRange { .. } => Some("HEAD")
+        }
+    }
+}
+
+/// A file touched by the changes.
+#[derive(Clone, Debug)]
+pub struct TouchedFile {
+    /// Path relative to the folder.
+    pub path: String,
+    /// "added", "modified", "deleted", "untracked", etc.
+    pub status: &'static str,
+    /// Whether the file is in the diff and given in full: files that are ignored when scanning the folder, or that are deleted (and so cannot
+    /// be checked against the scan's rules), are only listed by name.
+    pub is_shared: bool,
+    /// Whether the scan withheld the file as sensitive; it is then not shared either.
+    pub is_withheld_as_sensitive: bool
+}
+
+/// Changes of the git repository a folder is in, obtained with the git CLI from the local repository.
+#[derive(Clone, Debug)]
+pub struct GitChanges {
+    pub change_set: GitChangeSet,
+    /// None when HEAD is detached or there is no commit yet.
+    pub branch_name: Option<String>,
+    /// Abbreviated hash and subject of the recent commits, or of those of the commit range, most recent first.
+    pub commit_summaries: Vec<String>,
+    /// Files touched by the changes within the folder, in the order of `git diff`, followed by the untracked ones.
+    pub touched_files: Vec<TouchedFile>,
+    /// Unified diff of the changes to the shared touched files, as given by `git diff`.
+    pub diff: String,
+    /// Content of the touched files in the version given in full, when it is not that of the working tree, and the secrets redacted from it.
+    file_contents: HashMap<String, (String, Vec<Redaction>)>
+}
+
+/// Checks that a base revision, as typed by the user, is not empty and does not start with "-", which git would take for an option.
+pub fn validate_base_revision(base: &str) -> Result<(), String> {
+    if base.trim().is_empty() {
+        return Err(String::from("the base revision of the commits is empty"));
+    }
+    if base.starts_with('-') {
+        return Err(format!("the base revision \"{}\" must not start with \"-\"", base));
+    }
+    Ok(())
+}
+
+fn run_git(folder_path: &str, arguments: &[&str]) -> Result<String, String> {
+    let output = Command::new("git").arg("-C").arg(folder_path).args(["-c", "core.quotepath=off"]).args(arguments).output().map_err(|error| format!("could not run git: {}", error))?;
+    if !output.status.success() {
+        return Err(format!("\"git {}\" failed: {}", arguments.join(" "), String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn status_of_letter(status_letter: char) -> &'static str {
+    match status_letter {
+        'A' => "added",
+        'D' => "deleted",
+        'M' => "modified",
+        'T' => "type changed",
+        'U' => "unmerged",
+        _ => "changed"
+    }
+}
+
+impl GitChanges {
+    /// Obtains the changes within the scanned folder, which must be in a git repository; paths are relative to the folder, and changes outside of
+    /// it are left out. Only the touched files that the scan did not ignore are shared: the diff is restricted to them, so that no hunk of an
+    /// ignored file is given.
+    pub fn obtain(folder_info: &FolderInfo, change_set: &GitChangeSet) -> Result<Self, String> {
+        let folder_path = folder_info.folder_path.as_str();
+        run_git(folder_path, &["rev-parse", "--is-inside-work-tree"]).map_err(|_| format!("\"{}\" is not in a git repository", folder_path))?;
+        // the change set as given is kept to present the changes, and the resolved one is given to git
+        let resolved_change_set = change_set.resolved(folder_path)?;
+
+        let diff_arguments = resolved_change_set.diff_arguments();
+        let diff_arguments: Vec<&str> = diff_arguments.iter().map(String::as_str).collect();
+        // renames are shown as deletions and additions, so that every touched file has a single path
+        let common_diff_arguments = ["diff", "--relative", "--no-renames", "--no-color", "--no-ext-diff"];
+
+        // git separates paths with slashes on every platform
+        let shareable_file_paths: HashSet<String> = folder_info.file_infos.iter().filter(|file_info| !file_info.should_be_ignored && !file_info.is_withheld_as_sensitive()).map(|file_info| file_info.filepath.replace('\\', "/")).collect();
+        let withheld_file_paths: HashSet<String> = folder_info.file_infos.iter().filter(|file_info| file_info.is_withheld_as_sensitive()).map(|file_info| file_info.filepath.replace('\\', "/")).collect();
+        let touched_file = |path: &str, status: &'static str| TouchedFile{ path: path.to_string(), status, is_shared: status != "deleted" && shareable_file_paths.contains(path), is_withheld_as_sensitive: withheld_file_paths.contains(path) };
+
+        let name_status_output = run_git(folder_path, &[&common_diff_arguments[..], &["--name-status", "-z"], &diff_arguments[..]].concat())?;
+        let mut touched_files: Vec<TouchedFile> = vec!();
+        let mut name_status_fields = name_status_output.split('\0');
+        while let (Some(status_field), Some(path)) = (name_status_fields.next(), name_status_fields.next()) {
+            let Some(status_letter) = status_field.chars().next() else {
+                break;
+            };
+            touched_files.push(touched_file(path, status_of_letter(status_letter)));
+        }
+        if *change_set == GitChangeSet::Unstaged {
+            let untracked_files_output = run_git(folder_path, &["ls-files", "--others", "--exclude-standard", "-z"])?;
+            touched_files.extend(untracked_files_output.split('\0').filter(|path| !path.is_empty()).map(|path| touched_file(path, "untracked")));
+        }
+
+        // without any pathspec, git would diff every file, so there is no call at all when no touched file is shared
+        let shared_pathspecs: Vec<String> = touched_files.iter().filter(|touched_file| touched_file.is_shared).map(|touched_file| format!(":(literal){}", touched_file.path)).collect();
+        let mut diff = String::new();
+        for pathspecs in shared_pathspecs.chunks(N_PATHSPECS_PER_DIFF) {
+            let pathspecs: Vec<&str> = pathspecs.iter().map(String::as_str).collect();
+            diff += &run_git(folder_path, &[&common_diff_arguments[..], &diff_arguments[..], &["--"], &pathspecs[..]].concat())?;
+        }
+
+        // there is no branch (nor commit) in a repository without commits, and no branch when HEAD is detached
+        let branch_name = run_git(folder_path, &["rev-parse", "--abbrev-ref", "HEAD"]).ok().map(|branch_name| branch_name.trim().to_string()).filter(|branch_name| branch_name != "HEAD");
+        let commit_summaries_output = match &resolved_change_set {
+            GitChangeSet::CommitRange { base } => run_git(folder_path, &["log", &format!("-n{}", MAXIMUM_N_RANGE_COMMITS), "--format=%h %s", &format!("{}..HEAD", base)])?,
+            _ => run_git(folder_path, &["log", &format!("-n{}", N_RECENT_COMMITS), "--format=%h %s"]).unwrap_or_default()
+        };
+        let commit_summaries = commit_summaries_output.lines().map(str::to_string).collect();
+
+        let mut file_contents: HashMap<String, (String, Vec<Redaction>)> = HashMap::new();
+        if let Some(content_revision) = change_set.content_revision() {
+            for touched_file in touched_files.iter().filter(|touched_file| touched_file.is_shared) {
+                // "./" makes the path relative to the folder rather than to the root of the repository
+                if let Ok(file_content) = run_git(folder_path, &["show", &format!("{}:./{}", content_revision, touched_file.path)]) {
+                    file_contents.insert(touched_file.path.clone(), (file_content, vec!()));
+                }
+            }
+        }
+
+        Ok(GitChanges{ change_set: change_set.clone(), branch_name, commit_summaries, touched_files, diff, file_contents })
+    }
+
+    /// Redacts the secrets from the diff and from the contents obtained from git, which the scan did not load (see [`crate::redaction`]).
+    pub fn with_secrets_redacted(mut self) -> Self {
+        self.diff = redaction::redact_secrets(&self.diff).0;
+        for (file_content, redactions) in self.file_contents.values_mut() {
+            (*file_content, *redactions) = redaction::redact_secrets(file_content);
+        }
+        self
+    }
+
+    /// The folder with only the shared touched files, with their content in the version given in full.
+    pub fn changed_folder_info(&self, folder_info: &FolderInfo) -> FolderInfo {
+        let touched_file_paths: HashMap<String, &TouchedFile> = self.touched_files.iter().map(|touched_file| (touched_file.path.clone(), touched_file)).collect();
+        let mut changed_folder_info = folder_info.clone();
+        for file_info in &mut changed_folder_info.file_infos {
+            // git separates paths with slashes on every platform
+            // files deleted by the changes may still be in the working tree (e.g. when only removed from the index)
+            if touched_file_paths.get(&file_info.filepath.replace('\\', "/")).is_none_or(|touched_file| !touched_file.is_shared) {
+                file_info.should_be_ignored = true;
+                continue;
+            }
+            if let Some((file_content, redactions)) = self.file_contents.get(&file_info.filepath.replace('\\', "/"))
+                && file_info.content_should_be_loaded() {
+                file_info.size_in_bytes = file_content.len();
+                file_info.file_content = Some(file_content.clone());
+                file_info.redactions = redactions.clone();
+            }
+        }
+        changed_folder_info
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::folder_scanner::FolderScanner;
+    use crate::message_renderer::MessageRenderer;
+    use crate::scan_options::ScanOptions;
+    use crate::test_utils::TestDirectory;
+
+    /// A test directory holding a git repository, whose first commit holds the given files.
+    struct TestRepository {
+        test_directory: TestDirectory
+    }
+
+    impl TestRepository {
+        fn new(name: &str, files: &[(&str, &str)]) -> Self {
+            let test_repository = TestRepository{ test_directory: TestDirectory::new(&format!("git_changes_{}", name), files) };
+            test_repository.git(&["init", "-q"]);
+            test_repository.git(&["add", "-A"]);
+            test_repository.git(&["-c", "user.name=test", "-c", "user.email=test@example.com", "-c", "commit.gpgsign=false", "commit", "-q", "-m", "first commit"]);
+            test_repository
+        }
+
+        fn folder_path(&self) -> &str {
+            self.test_directory.path().to_str().unwrap()
+        }
+
+        fn write_files(&self, files: &[(&str, &str)]) {
+            self.test_directory.write_files(files);
+        }
+
+        fn git(&self, arguments: &[&str]) {
+            run_git(self.folder_path(), arguments).unwrap();
+        }
+
+        fn scan(&self, scan_options: &ScanOptions) -> FolderInfo {
+            FolderInfo::new(self.folder_path(), scan_options).unwrap()
+        }
+    }
+
+    #[test]
+    fn changes_to_files_left_out_of_the_scan_are_not_in_the_diff() {
+        let test_repository = TestRepository::new("left_out", &[("main.rs", "fn main() {}\n"), ("notes.log", "first note\n")]);
+        test_repository.write_files(&[("main.rs", "fn main() { run(); }\n"), ("notes.log", "private note\n"), ("new.log", "another private note\n")]);
+        let mut scan_options = ScanOptions::new();
+        scan_options.excluded_file_globs = vec![String::from("*.log")];
+
+        let git_changes = GitChanges::obtain(&test_repository.scan(&scan_options), &GitChangeSet::Unstaged).unwrap();
+
+        let touched_files: Vec<(&str, &str, bool)> = git_changes.touched_files.iter().map(|touched_file| (touched_file.path.as_str(), touched_file.status, touched_file.is_shared)).collect();
+        assert_eq!(touched_files, vec![("main.rs", "modified", true), ("notes.log", "modified", false), ("new.log", "untracked", false)]);
+        assert!(git_changes.diff.contains("+fn main() { run(); }"));
+        assert!(!git_changes.diff.contains("note"));
+    }
+
+    #[test]
+    fn there_is_no_diff_when_no_touched_file_is_shared() {
+        let test_repository = TestRepository::new("nothing_shared", &[("main.rs", "fn main() {}\n"), ("notes.log", "first note\n")]);
+        test_repository.write_files(&[("notes.log", "private note\n")]);
+        test_repository.git(&["add", "-A"]);
+        let mut scan_options = ScanOptions::new();
+        scan_options.excluded_file_globs = vec![String::from("*.log")];
+
+        let git_changes = GitChanges::obtain(&test_repository.scan(&scan_options), &GitChangeSet::Staged).unwrap();
+
+        assert_eq!(git_changes.touched_files.len(), 1);
+        assert!(git_changes.diff.is_empty());
+        assert!(git_changes.changed_folder_info(&test_repository.scan(&scan_options)).file_infos.iter().all(|file_info| file_info.should_be_ignored));
+    }
+
+    #[test]
+    fn touched_sensitive_files_are_only_named_even_without_redaction() {
+        let test_repository = TestRepository::new("sensitive", &[("main.rs", "fn main() {}\n"), (".env", "API_KEY=first-secret-value\n")]);
+        test_repository.write_files(&[("main.rs", "fn main() { run(); }\n"), (".env", "API_KEY=second-secret-value\n")]);
+        let mut scan_options = ScanOptions::new();
+        scan_options.redact_secrets = false;
+        let folder_info = FolderScanner::new(test_repository.folder_path()).options(scan_options).scan().unwrap();
+
+        let assert_only_named = |change_set: GitChangeSet| {
+            let git_changes = GitChanges::obtain(&folder_info, &change_set).unwrap();
+            let env_file = git_changes.touched_files.iter().find(|touched_file| touched_file.path == ".env").unwrap();
+            assert!(!env_file.is_shared && env_file.is_withheld_as_sensitive);
+
+            let messages = MessageRenderer::new().git_changes(git_changes).render(&folder_info).unwrap().join("\n");
+            assert!(messages.contains("+fn main() { run(); }"), "{:?}", change_set);
+            assert!(messages.contains("\".env\" are not given either, as these files are withheld as sensitive"), "{:?}", change_set);
+            assert!(!messages.contains("secret-value"), "{:?}", change_set);
+        };
+
+        assert_only_named(GitChangeSet::Unstaged);
+        test_repository.git(&["add", "-A"]);
+        assert_only_named(GitChangeSet::Staged);
+        test_repository.git(&["-c", "user.name=test", "-c", "user.email=test@example.com", "-c", "commit.gpgsign=false", "commit", "-q", "-m", "second commit"]);
+        assert_only_named(GitChangeSet::CommitRange { base: String::from("HEAD~1") });
+    }
+
+    #[test]
+    fn base_revisions_must_be_commits_and_cannot_be_options() {
+        let test_repository = TestRepository::new("base_revisions", &[("main.rs", "fn main() {}\n")]);
+        test_repository.write_files(&[("main.rs", "fn main() { run(); }\n")]);
+        test_repository.git(&["-c", "user.name=test", "-c", "user.email=test@example.com", "-c", "commit.gpgsign=false", "commit", "-q", "-a", "-m", "second commit"]);
+        let folder_info = test_repository.scan(&ScanOptions::new());
+        let obtain_since = |base: &str| GitChanges::obtain(&folder_info, &GitChangeSet::CommitRange { base: base.to_string() });
+
+        for base in ["", " ", "--output=diff.txt", "-p"] {
+            assert!(obtain_since(base).is_err(), "{:?}", base);
+        }
+        assert!(obtain_since("missing").is_err_and(|error| error.contains("not a commit")));
+        assert!(!test_repository.test_directory.join("diff.txt").exists());
+
+        let git_changes = obtain_since("HEAD~1").unwrap();
+        assert!(git_changes.diff.contains("+fn main() { run(); }"));
+        assert_eq!(git_changes.commit_summaries.len(), 1);
+        assert_eq!(git_changes.change_set.diff_command(), "git diff HEAD~1..HEAD");
+    }
+}
@@ -0,0 +1,195 @@
+use ignore::Match;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::path::{Path, PathBuf};
+
+pub const FOLDERTOAI_IGNORE_FILE_NAME: &str = ".foldertoaiignore";
+
+// sorted from the highest precedence to the lowest one; within each kind, files in deeper directories take precedence
+const PER_DIRECTORY_IGNORE_FILE_NAMES: [&str; 3] = [FOLDERTOAI_IGNORE_FILE_NAME, ".ignore", ".gitignore"];
+
+struct DirectoryIgnoreFiles {
+    directory_relative_to_base: PathBuf,
+    matchers: [Option<Gitignore>; PER_DIRECTORY_IGNORE_FILE_NAMES.len()]
+}
+
+/// Gitignore-syntax rules that apply while walking a folder.
+///
+/// Paths are matched against, in order of precedence: `.foldertoaiignore`, `.ignore` and `.gitignore` files
+/// (the ones in deeper directories first, including those between the enclosing git repository's root and the scanned folder),
+/// then the repository's `.git/info/exclude` and finally the global excludes file (git's `core.excludesFile`).
+/// The first rule that matches decides, so a negated pattern (`!pattern`) re-includes a path excluded by a rule of lower precedence.
+pub struct IgnoreRules {
+    base_path: PathBuf,
+    scanned_folder_relative_to_base: PathBuf,
+    directory_ignore_files_stack: Vec<DirectoryIgnoreFiles>,
+    repository_exclude: Option<Gitignore>,
+    global_exclude: Option<Gitignore>
+}
+
+impl IgnoreRules {
+    /// Collects the rules that apply to `folder_path` itself; the ones within it are collected as the walk enters each directory.
+    pub fn new(folder_path: &str) -> Self {
+        let scanned_folder_path = std::fs::canonicalize(folder_path).unwrap_or_else(|_| PathBuf::from(folder_path));
+
+        let repository_root_path = scanned_folder_path.ancestors().find(|ancestor| ancestor.join(".git").is_dir()).map(Path::to_path_buf);
+        let base_path = repository_root_path.clone().unwrap_or_else(|| scanned_folder_path.clone());
+        let scanned_folder_relative_to_base = scanned_folder_path.strip_prefix(&base_path).map(Path::to_path_buf).unwrap_or_default();
+
+        let mut ignore_rules = IgnoreRules{ base_path, scanned_folder_relative_to_base, directory_ignore_files_stack: Vec::new(), repository_exclude: None, global_exclude: None };
+
+        if let Some(repository_root_path) = repository_root_path {
+            ignore_rules.repository_exclude = Self::build_matcher(&repository_root_path, &repository_root_path.join(".git").join("info").join("exclude"));
+        }
+
+        let (global_exclude, _) = GitignoreBuilder::new(&ignore_rules.base_path).build_global();
+        if !global_exclude.is_empty() {
+            ignore_rules.global_exclude = Some(global_exclude);
+        }
+
+        // ignore files in the directories between the repository's root and the scanned folder (the latter excluded, as the walk enters it)
+        let ancestors_relative_to_base: Vec<PathBuf> = ignore_rules.scanned_folder_relative_to_base.ancestors().skip(1).map(Path::to_path_buf).collect();
+        for ancestor_relative_to_base in ancestors_relative_to_base.into_iter().rev() {
+            ignore_rules.push_directory(&ancestor_relative_to_base);
+        }
+
+        ignore_rules
+    }
+
+    fn build_matcher(directory_path: &Path, ignore_file_path: &Path) -> Option<Gitignore> {
+        if !ignore_file_path.is_file() {
+            return None;
+        }
+        let mut builder = GitignoreBuilder::new(directory_path);
+        builder.add(ignore_file_path);
+        match builder.build() {
+            Ok(matcher) if !matcher.is_empty() => Some(matcher),
+            _ => None
+        }
+    }
+
+    fn push_directory(&mut self, directory_relative_to_base: &Path) {
+        let directory_path = self.base_path.join(directory_relative_to_base);
+        let matchers = PER_DIRECTORY_IGNORE_FILE_NAMES.map(|ignore_file_name| Self::build_matcher(&directory_path, &directory_path.join(ignore_file_name)));
+        self.directory_ignore_files_stack.push(DirectoryIgnoreFiles{ directory_relative_to_base: directory_relative_to_base.to_path_buf(), matchers });
+    }
+
+    /// Must be called when the walk enters a directory (including the scanned folder itself, whose relative path is empty).
+    pub fn enter_directory(&mut self, directory_relative_path: &Path) {
+        let directory_relative_to_base = self.scanned_folder_relative_to_base.join(directory_relative_path);
+        self.push_directory(&directory_relative_to_base);
+    }
+
+    /// Must be called when the walk leaves the directory it entered last.
+    pub fn leave_directory(&mut self) {
+        self.directory_ignore_files_stack.pop();
+    }
+
+    /// Whether the file or directory at `relative_path` (relative to the scanned folder) is ignored, given the rules of the directories entered so far.
+    pub fn is_ignored(&self, relative_path: &Path, is_dir: bool) -> bool {
+        let path_relative_to_base = self.scanned_folder_relative_to_base.join(relative_path);
+
+        for ignore_file_kind_index in 0..PER_DIRECTORY_IGNORE_FILE_NAMES.len() {
+            for directory_ignore_files in self.directory_ignore_files_stack.iter().rev() {
+                if let Some(matcher) = &directory_ignore_files.matchers[ignore_file_kind_index]
+                    && let Ok(path_relative_to_directory) = path_relative_to_base.strip_prefix(&directory_ignore_files.directory_relative_to_base) {
+                    match matcher.matched(path_relative_to_directory, is_dir) {
+                        Match::Ignore(_) => return true,
+                        Match::Whitelist(_) => return false,
+                        Match::None => {}
+                    }
+                }
+            }
+        }
+
+        for matcher in [&self.repository_exclude, &self.global_exclude].into_iter().flatten() {
+            match matcher.matched(&path_relative_to_base, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+        }
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestDirectory;
+
+    /// The ignore rules of the test directory or one of its subfolders.
+    fn ignore_rules_of(test_directory: &TestDirectory, relative_path: &str) -> IgnoreRules {
+        IgnoreRules::new(test_directory.join(relative_path).to_str().unwrap())
+    }
+
+    #[test]
+    fn negated_patterns_re_include_files_excluded_by_earlier_ones() {
+        let test_directory = TestDirectory::new("ignore_rules_negation", &[(".gitignore", "*.log\n!keep.log\n")]);
+        let mut ignore_rules = ignore_rules_of(&test_directory, "");
+        ignore_rules.enter_directory(Path::new(""));
+
+        assert!(ignore_rules.is_ignored(Path::new("debug.log"), false));
+        assert!(!ignore_rules.is_ignored(Path::new("keep.log"), false));
+        assert!(!ignore_rules.is_ignored(Path::new("main.rs"), false));
+    }
+
+    #[test]
+    fn directory_patterns_only_match_directories() {
+        let test_directory = TestDirectory::new("ignore_rules_directory_patterns", &[(".gitignore", "build/\n")]);
+        let mut ignore_rules = ignore_rules_of(&test_directory, "");
+        ignore_rules.enter_directory(Path::new(""));
+
+        assert!(ignore_rules.is_ignored(Path::new("build"), true));
+        assert!(!ignore_rules.is_ignored(Path::new("build"), false));
+    }
+
+    #[test]
+    fn foldertoaiignore_and_ignore_files_take_precedence_over_gitignore() {
+        let test_directory = TestDirectory::new("ignore_rules_kinds", &[
+            (".gitignore", "generated.rs\n!notes.txt\n"),
+            (".ignore", "notes.txt\n"),
+            (FOLDERTOAI_IGNORE_FILE_NAME, "!generated.rs\n")
+        ]);
+        let mut ignore_rules = ignore_rules_of(&test_directory, "");
+        ignore_rules.enter_directory(Path::new(""));
+
+        assert!(!ignore_rules.is_ignored(Path::new("generated.rs"), false));
+        assert!(ignore_rules.is_ignored(Path::new("notes.txt"), false));
+    }
+
+    #[test]
+    fn ignore_files_in_deeper_directories_take_precedence() {
+        let test_directory = TestDirectory::new("ignore_rules_depth", &[
+            (".gitignore", "*.tmp\n"),
+            ("sub/.gitignore", "!keep.tmp\n")
+        ]);
+        let mut ignore_rules = ignore_rules_of(&test_directory, "");
+        ignore_rules.enter_directory(Path::new(""));
+        assert!(ignore_rules.is_ignored(Path::new("keep.tmp"), false));
+
+        ignore_rules.enter_directory(Path::new("sub"));
+        assert!(!ignore_rules.is_ignored(Path::new("sub/keep.tmp"), false));
+        assert!(ignore_rules.is_ignored(Path::new("sub/other.tmp"), false));
+
+        ignore_rules.leave_directory();
+        assert!(ignore_rules.is_ignored(Path::new("sub/keep.tmp"), false));
+    }
+
+    #[test]
+    fn rules_between_the_repository_root_and_the_scanned_folder_apply() {
+        let test_directory = TestDirectory::new("ignore_rules_repository", &[
+            (".git/info/exclude", "*.bak\n"),
+            (".gitignore", "*.secret\n"),
+            ("app/.gitignore", "!public.secret\n"),
+            ("app/main.rs", "")
+        ]);
+        let mut ignore_rules = ignore_rules_of(&test_directory, "app");
+        ignore_rules.enter_directory(Path::new(""));
+
+        assert!(ignore_rules.is_ignored(Path::new("api.secret"), false));
+        assert!(!ignore_rules.is_ignored(Path::new("public.secret"), false));
+        assert!(ignore_rules.is_ignored(Path::new("main.rs.bak"), false));
+        assert!(!ignore_rules.is_ignored(Path::new("main.rs"), false));
+    }
+}
@@ -24,15 +24,6 @@ impl InputManager {
         keys.contains(&Keycode::LControl) || keys.contains(&Keycode::RControl)
     }
 
-    fn is_shift_key_pressed(&self) -> bool {
-        let keys = self.device_state.get_keys();
-        keys.contains(&Keycode::LShift) || keys.contains(&Keycode::RShift)
-    }
-
-    fn is_d_key_pressed(&self) -> bool {
-        self.device_state.get_keys().contains(&Keycode::D)
-    }
-
     fn is_v_key_pressed(&self) -> bool {
         self.device_state.get_keys().contains(&Keycode::V)
     }
@@ -40,8 +31,4 @@ impl InputManager {
     pub fn is_control_v_pressed(&self) -> bool {
         self.is_control_key_pressed() && self.is_v_key_pressed()
     }
-
-    pub fn is_control_shift_d_pressed(&self) -> bool {
-        self.is_control_key_pressed() && self.is_shift_key_pressed()  && self.is_d_key_pressed()
-    }
 }
\ No newline at end of file
@@ -0,0 +1,111 @@
+use fancy_regex::Regex;
+
+/// FNV-1a hash, which (unlike the standard library's hasher) stays the same across versions and platforms.
+pub fn stable_hash(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/// CRC-32 (as used by zip and PNG) of `bytes`.
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc: u32 = 0xffffffff;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb88320 & mask);
+        }
+    }
+    !crc
+}
+
+/// Checksum of a message's body, as stated in its header: the CRC-32 of its UTF-8 encoding, in 8 hexadecimal digits.
+/// Line breaks are normalized to "\n" first, since pasting may turn them into "\r\n".
+pub fn checksum_of_message_body(message_body: &str) -> String {
+    format!("{:08x}", crc32(message_body.replace("\r\n", "\n").as_bytes()))
+}
+
+/// Session ID (12 hexadecimal digits) of the messages about a folder, derived from its path and the messages' bodies,
+/// so that it changes whenever the folder's content or the settings yield different messages.
+pub fn session_id_of(folder_path: &str, message_bodies: &[String]) -> String {
+    let mut hashed_text = String::from(folder_path);
+    for message_body in message_bodies {
+        hashed_text.push('\0');
+        hashed_text += message_body;
+    }
+    format!("{:012x}", stable_hash(hashed_text.as_bytes()) & 0xffff_ffff_ffff)
+}
+
+/// Text of the `{integrity_marker}` placeholder: the session ID and, except for the first message, the checksum of the message's body.
+pub fn integrity_marker(session_id: &str, checksum: Option<&str>) -> String {
+    match checksum {
+        Some(checksum) => format!(" [session {}, checksum {}]", session_id, checksum),
+        None => format!(" [session {}]", session_id)
+    }
+}
+
+/// A message header with an integrity marker, found in a transcript.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct FoundIntegrityMarker {
+    /// Index of the message, starting at 1.
+    pub message_index: usize,
+    pub n_messages: usize,
+    pub session_id: String,
+    pub checksum: Option<String>
+}
+
+/// Message headers with integrity markers (e.g. "Message 3/12 [session 0123456789ab, checksum 89abcdef]:") found in `transcript`, in order;
+/// headers take a whole line, so markers quoted within the shared files are not taken for headers.
+pub fn find_integrity_markers(transcript: &str) -> Vec<FoundIntegrityMarker> {
+    let pattern = Regex::new(r"(?m)^Message (\d+)/(\d+) \[session ([0-9a-f]{12})(?:, checksum ([0-9a-f]{8}))?\]:$").unwrap();
+    let mut found_integrity_markers = vec!();
+    for captures in pattern.captures_iter(transcript).flatten() {
+        let (Ok(message_index), Ok(n_messages)) = (captures[1].parse(), captures[2].parse()) else {
+            continue;
+        };
+        found_integrity_markers.push(FoundIntegrityMarker{ message_index, n_messages, session_id: captures[3].to_string(), checksum: captures.get(4).map(|checksum| checksum.as_str().to_string()) });
+    }
+    found_integrity_markers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_markers_of_message_headers() {
+        let transcript = "[FolderToAI]\n\nMessage 1/2 [session 0123456789ab]:\n\nFirst message\n\nMessage 2/2 [session 0123456789ab, checksum 89abcdef]:\nSecond message\n";
+        let found_integrity_markers = find_integrity_markers(transcript);
+        assert_eq!(found_integrity_markers, vec![
+            FoundIntegrityMarker{ message_index: 1, n_messages: 2, session_id: String::from("0123456789ab"), checksum: None },
+            FoundIntegrityMarker{ message_index: 2, n_messages: 2, session_id: String::from("0123456789ab"), checksum: Some(String::from("89abcdef")) }
+        ]);
+    }
+
+    #[test]
+    fn ignores_markers_within_file_contents() {
+        let transcript = concat!(
+            "Message 2/2 [session 0123456789ab, checksum 89abcdef]:\n",
+            "File: src/integrity.rs\n",
+            "--- BEGINNING OF CONTENT ---\n",
+            "/// e.g. \"Message 3/12 [session fedcba987654, checksum 01234567]:\"\n",
+            "    // Message 1/1 [session fedcba987654]:\n",
+            "let header = \"Message 1/1 [session fedcba987654]: \";\n",
+            "--- END OF CONTENT ---\n"
+        );
+        let found_integrity_markers = find_integrity_markers(transcript);
+        assert_eq!(found_integrity_markers.len(), 1);
+        assert_eq!(found_integrity_markers[0].session_id, "0123456789ab");
+    }
+
+    #[test]
+    fn checksums_do_not_depend_on_line_breaks() {
+        assert_eq!(checksum_of_message_body("a\r\nb\r\n"), checksum_of_message_body("a\nb\n"));
+        assert_eq!(checksum_of_message_body(""), "00000000");
+        assert_eq!(format!("{:08x}", crc32(b"123456789")), "cbf43926");
+    }
+}
@@ -0,0 +1,53 @@
+//! Scanning of folders and generation of messages describing their contents, to be sent to AI chats.
+//!
+//! This is the library behind the FolderToAI application, which is one of its consumers:
+//!
+//! ```no_run
+//! use folder_to_ai::{FolderScanner, MessageRenderer};
+//!
+//! let folder_info = FolderScanner::new("path/to/folder").ignore_subfolder("docs").scan()?;
+//! let messages = MessageRenderer::new().maximum_amount_of_characters_per_message(8000).render(&folder_info)?;
+//! # Ok::<(), String>(())
+//! ```
+
+pub mod core_utils;
+pub mod file_filter;
+pub mod file_info;
+pub mod file_utils;
+pub mod folder_info;
+pub mod folder_scanner;
+pub mod git_changes;
+pub mod ignore_rules;
+pub mod integrity;
+pub mod message_renderer;
+pub mod message_templates;
+pub mod output_format;
+pub mod reassembly;
+pub mod redaction;
+pub mod reply_edits;
+pub mod scan_export;
+pub mod scan_options;
+pub mod scan_progress;
+pub mod skipped_subtree;
+pub mod snapshot;
+#[cfg(test)]
+mod test_utils;
+pub mod tokenizer;
+pub mod tree_overview;
+pub mod unified_diff;
+
+pub use file_info::FileInfo;
+pub use folder_info::FolderInfo;
+pub use folder_scanner::FolderScanner;
+pub use git_changes::{GitChangeSet, GitChanges};
+pub use message_renderer::{MessageRenderer, RenderedMessages};
+pub use message_templates::MessageTemplates;
+pub use output_format::OutputFormat;
+pub use reassembly::Reassembly;
+pub use scan_export::{ExportFormat, ScanExport};
+pub use scan_options::ScanOptions;
+pub use scan_progress::ScanProgress;
+pub use skipped_subtree::SkippedSubtree;
+pub use snapshot::FolderSnapshot;
+pub use tokenizer::{BpeTokenizer, ByteCounter, CharacterCounter, EstimatedTokenCounter, Tokenizer};
+pub use tree_overview::TreeOverviewPlacement;
@@ -1,28 +1,34 @@
 #![windows_subsystem = "windows"]
 
+mod cli;
 mod clipboard_utils;
-mod core_utils;
-mod file_info;
-mod file_utils;
-mod folder_info;
 mod input_utils;
+mod scan_worker;
 mod settings;
 mod setup_utils;
+mod sharing_session;
+#[cfg(windows)]
 mod win_utils;
 
+use clap::{CommandFactory, Parser};
+use cli::{Cli, CliCommand};
 use egui::{RichText, Color32};
-use folder_info::FolderInfo;
+use folder_to_ai::{reply_edits, ExportFormat, FolderInfo, FolderSnapshot, ScanExport, SkippedSubtree};
+use folder_to_ai::reply_edits::PlannedFileChange;
 use input_utils::InputManager;
+use scan_worker::{ScanEvent, ScanWorker};
 use settings::Settings;
+use sharing_session::SharingSession;
 use size::Size;
-use std::sync::{atomic::{AtomicBool, Ordering}, Arc};
+use std::sync::Arc;
+use std::process::ExitCode;
 use std::thread;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 const APP_NAME: &str = "FolderToAI";
 const LINK_TO_GIT_REPO: &str = "https://github.com/DaviFN/FolderToAI";
 
-const GUI_UPDATE_DELAY_MS: u128 = 50;
+const GUI_UPDATE_DELAY_MS: u64 = 50;
 const GUI_SIZE_OF_SPACE_AFTER_SEPARATOR: f32 = 5.0;
 
 #[derive(PartialEq)]
@@ -33,6 +39,7 @@ enum FolderToAiState {
     LoadingContents,
     ProcessingContents,
     ReadyForUse,
+    Cancelled,
     Error
 }
 
@@ -48,6 +55,8 @@ struct FolderToAiApp {
     state: FolderToAiState,
     gui_has_ever_been_updated: bool,
     previous_window_size: egui::Vec2,
+    scan_worker: Option<ScanWorker>,
+    scan_error_message: String,
     folder_info: Option<FolderInfo>,
     total_n_files: usize,
     n_binary_files: usize,
@@ -59,6 +68,20 @@ struct FolderToAiApp {
     n_files_that_could_not_be_loaded: usize,
     progress_of_loading_contents: f64,
     folder_representation_messages: Vec<String>,
+    folder_representation_message_sizes: Vec<usize>,
+    folder_representation_file_message_indices: Vec<Vec<usize>>,
+    export_file_path_input: String,
+    export_status_message: String,
+    sharing_session: Option<SharingSession>,
+    /// Message re-orienting the model, in the clipboard before the one sharing resumes with.
+    pending_resume_message: Option<String>,
+    resume_from_message_number: usize,
+    reply_input: String,
+    /// Changes previewed from the reply, against the scanned folder.
+    planned_file_changes: Vec<PlannedFileChange>,
+    reply_status_message: String,
+    message_size_unit_name: String,
+    tokenizer_warning: Option<String>,
     current_selected_message_index: usize,
     input_manager: InputManager,
     current_user_input: FolderToAiUserInput,
@@ -70,10 +93,12 @@ impl FolderToAiApp {
     fn new(folder_path: String) -> Self
     {
         FolderToAiApp {
-            folder_path: folder_path,
+            folder_path,
             state: FolderToAiState::Initializing,
             gui_has_ever_been_updated: false,
             previous_window_size: egui::Vec2{x: 0.0, y: 0.0},
+            scan_worker: None,
+            scan_error_message: String::new(),
             folder_info: None,
             total_n_files: 0,
             n_binary_files: 0,
@@ -85,6 +110,18 @@ impl FolderToAiApp {
             n_files_that_could_not_be_loaded: 0,
             progress_of_loading_contents: 0.0,
             folder_representation_messages: Vec::new(),
+            folder_representation_message_sizes: Vec::new(),
+            folder_representation_file_message_indices: Vec::new(),
+            export_file_path_input: String::new(),
+            export_status_message: String::new(),
+            sharing_session: None,
+            pending_resume_message: None,
+            resume_from_message_number: 1,
+            reply_input: String::new(),
+            planned_file_changes: Vec::new(),
+            reply_status_message: String::new(),
+            message_size_unit_name: String::new(),
+            tokenizer_warning: None,
             current_selected_message_index: 0,
             input_manager: InputManager::new(),
             current_user_input: FolderToAiUserInput::None,
@@ -93,11 +130,20 @@ impl FolderToAiApp {
         }
     }
 
+    fn save_settings(&self)
+    {
+        if let Ok(settings_file_path) = setup_utils::settings_file_path() {
+            self.settings.save_to_file(&settings_file_path);
+        }
+    }
+
     fn on_first_gui_update(&mut self, ctx: &eframe::egui::Context)
     {
         ctx.set_theme(egui::Theme::Dark);
 
-        self.settings.load_from_file(&setup_utils::settings_file_path());
+        if let Ok(settings_file_path) = setup_utils::settings_file_path() {
+            self.settings.load_from_file(&settings_file_path);
+        }
 
         ctx.send_viewport_cmd(egui::ViewportCommand::InnerSize(self.settings.window_size()));
         ctx.send_viewport_cmd(egui::ViewportCommand::Visible(true));
@@ -110,24 +156,84 @@ impl FolderToAiApp {
         }
     }
 
+    fn send_current_message_to_clipboard(&mut self)
+    {
+        clipboard_utils::set_clipboard_content(&self.folder_representation_messages[self.current_selected_message_index]);
+        self.clipboard_content_information_message = format!("Clipboard has been set to message {} of {}", self.current_selected_message_index + 1, self.folder_representation_messages.len());
+        self.save_sharing_session_progress();
+    }
+
+    fn save_sharing_session_progress(&mut self)
+    {
+        if let Some(sharing_session) = &mut self.sharing_session {
+            sharing_session.current_message_index = self.current_selected_message_index;
+            if let Ok(sessions_directory_path) = setup_utils::sessions_directory_path() {
+                let _ = sharing_session.save(&sessions_directory_path);
+            }
+        }
+    }
+
     fn step_forward_current_message_and_send_to_clipboard_if_possible(&mut self)
     {
+        // once the re-orientation message is pasted, the message sharing resumes with is sent
+        if self.pending_resume_message.take().is_some() {
+            self.send_current_message_to_clipboard();
+            return;
+        }
         if self.current_selected_message_index + 1 < self.folder_representation_messages.len() {
             self.current_selected_message_index += 1;
-            clipboard_utils::set_clipboard_content(&self.folder_representation_messages[self.current_selected_message_index]);
-            self.clipboard_content_information_message = format!("Clipboard has been set to message {} of {}", self.current_selected_message_index + 1, self.folder_representation_messages.len());
+            self.send_current_message_to_clipboard();
         }
     }
 
     fn step_backwards_current_message_and_send_to_clipboard_if_possible(&mut self)
     {
+        if self.pending_resume_message.take().is_some() {
+            self.send_current_message_to_clipboard();
+            return;
+        }
         if self.current_selected_message_index != 0 {
             self.current_selected_message_index -= 1;
-            clipboard_utils::set_clipboard_content(&self.folder_representation_messages[self.current_selected_message_index]);
-            self.clipboard_content_information_message = format!("Clipboard has been set to message {} of {}", self.current_selected_message_index + 1, self.folder_representation_messages.len());
+            self.send_current_message_to_clipboard();
         }
     }
 
+    fn resume_sharing_session(&mut self, resume_message_index: usize)
+    {
+        let Some(sharing_session) = &self.sharing_session else {
+            return;
+        };
+        let resume_message = sharing_session.resume_message(&self.settings.message_templates(), resume_message_index);
+        self.current_selected_message_index = resume_message_index;
+        self.save_sharing_session_progress();
+
+        clipboard_utils::set_clipboard_content(&resume_message);
+        self.clipboard_content_information_message = format!("Clipboard has been set to the re-orientation message; pasting it advances to message {} of {}", resume_message_index + 1, self.folder_representation_messages.len());
+        self.pending_resume_message = Some(resume_message);
+    }
+
+    /// Continues the stored session of the folder if its messages are the same as the new ones (the folder and the settings did not change),
+    /// and otherwise starts a new one.
+    fn start_or_continue_sharing_session(&mut self, session_id: Option<String>)
+    {
+        let sessions_directory_path = setup_utils::sessions_directory_path();
+        match sessions_directory_path.as_ref().ok().and_then(|sessions_directory_path| SharingSession::load_for_folder(sessions_directory_path, &self.folder_path)) {
+            Some(sharing_session) if sharing_session.is_of_messages(&self.folder_representation_messages) => {
+                self.current_selected_message_index = std::cmp::min(sharing_session.current_message_index, self.folder_representation_messages.len() - 1);
+                self.sharing_session = Some(sharing_session);
+            },
+            _ => {
+                let messages_are_redacted = self.settings.redact_secrets && !self.folder_info.as_ref().is_some_and(FolderInfo::contains_shared_sensitive_files);
+                let sharing_session = SharingSession::new(&self.folder_path, self.folder_representation_messages.clone(), session_id, messages_are_redacted);
+                if let Ok(sessions_directory_path) = &sessions_directory_path {
+                    let _ = sharing_session.save(sessions_directory_path);
+                }
+                self.sharing_session = Some(sharing_session);
+            }
+        }
+        self.resume_from_message_number = self.current_selected_message_index + 1;
+    }
+
     fn key_combination_to_step_forward_in_messages_is_pressed(&mut self) -> bool
     {
         self.input_manager.is_right_key_pressed() || self.settings.pasting_with_ctrlv_advances_to_next_message && self.input_manager.is_control_v_pressed()
@@ -190,6 +296,12 @@ impl FolderToAiApp {
             FolderToAiState::LoadingContents => {
                 format!("Loading contents... ({}/{})", self.n_files_loaded, self.total_n_files_to_load)
             },
+            FolderToAiState::Cancelled => {
+                String::from("The loading process has been cancelled")
+            },
+            FolderToAiState::Error => {
+                format!("The loading process has failed: {}", self.scan_error_message)
+            },
             _ => {
                 if self.state != FolderToAiState::ReadyForUse {
                     String::from("Loading process in execution, please wait...")
@@ -217,6 +329,9 @@ impl FolderToAiApp {
             }
             return egui::Color32::RED;
         }
+        if self.state == FolderToAiState::Error {
+            return egui::Color32::RED;
+        }
         egui::Color32::LIGHT_GRAY
     }
 
@@ -245,6 +360,12 @@ impl FolderToAiApp {
             FolderToAiState::ReadyForUse => {
                 "Loading process (completed)"
             }
+            FolderToAiState::Cancelled => {
+                "Loading process (cancelled)"
+            }
+            FolderToAiState::Error => {
+                "Loading process (failed)"
+            }
             _ => {
                 "Loading process (in progress)"
             }
@@ -256,6 +377,15 @@ impl FolderToAiApp {
         ui.label(egui::RichText::new(self.current_state_info_string()).color(self.current_state_string_info_color()));
         
         self.show_loading_process_bar(ui);
+
+        if self.scan_is_in_progress() {
+            if ui.button("Cancel").clicked() {
+                self.cancel_scan();
+            }
+        }
+        else if ui.button("Scan again").on_hover_text("Scans the folder again, using the current settings").clicked() {
+            self.start_scan();
+        }
     }
 
     fn show_folder_information_gui(&mut self, ui: &mut egui::Ui) {
@@ -265,35 +395,134 @@ impl FolderToAiApp {
         ui.label(format!("Folder: \"{}\"", self.folder_path));
         
         let mut size_info_str = String::from("Size: ");
-        if self.state == FolderToAiState::Initializing || self.state == FolderToAiState::ObtainingInitialInformationAboutTheFiles {
-            size_info_str += "being determined...";
+        if let Some(folder_info) = &self.folder_info {
+            size_info_str += &format!("{}", Size::from_bytes(folder_info.size_in_bytes));
         }
         else {
-            let size_in_bytes = self.folder_info.as_mut().unwrap().size_in_bytes;
-            size_info_str += &format!("{}", Size::from_bytes(size_in_bytes));
+            size_info_str += self.unavailable_folder_information_string();
         }
         ui.label(size_info_str);
 
         let mut n_files_info_str = String::from("Total number of files: ");
-        if self.state == FolderToAiState::Initializing || self.state == FolderToAiState::ObtainingInitialInformationAboutTheFiles {
-            n_files_info_str += "being determined...";
+        if let Some(folder_info) = &self.folder_info {
+            let n_files_not_ignored = folder_info.get_number_of_files_not_ignored();
+            n_files_info_str += &format!("{}", n_files_not_ignored);
+            let n_withheld_sensitive_files = folder_info.number_of_withheld_sensitive_files();
+            let n_ignored_files = folder_info.get_number_of_ignored_files() - n_withheld_sensitive_files;
+            if n_ignored_files > 0 {
+                n_files_info_str += &format!(" (plus {} ignored)", n_ignored_files);
+            }
+            if n_withheld_sensitive_files > 0 {
+                n_files_info_str += &format!(" (plus {} withheld as sensitive)", n_withheld_sensitive_files);
+            }
         }
         else {
-            n_files_info_str += &format!("{}", self.folder_info.as_mut().unwrap().get_number_of_files());
+            n_files_info_str += self.unavailable_folder_information_string();
         }
         ui.label(n_files_info_str);
 
+        if let Some(folder_info) = &self.folder_info {
+            Self::show_skipped_subtrees_gui(ui, &folder_info.skipped_subtrees);
+        }
+        if self.state == FolderToAiState::ReadyForUse {
+            self.show_secrets_review_gui(ui);
+        }
+
         let mut size_info_str = String::from("Number of binary files among the scanned ones: ");
         if self.state == FolderToAiState::Initializing || self.state == FolderToAiState::ObtainingInitialInformationAboutTheFiles || self.state == FolderToAiState::DeterminingBinaryFiles {
             size_info_str += "being determined...";
         }
+        else if let Some(folder_info) = &self.folder_info {
+            let n_files_not_ignored = folder_info.get_number_of_files_not_ignored();
+            let percentage_of_binary_files: f64 = if n_files_not_ignored > 0 { self.n_binary_files as f64 / n_files_not_ignored as f64 } else { 0.0 };
+            size_info_str += &format!("{} ({:.2}% of total)", self.n_binary_files, percentage_of_binary_files * 100.0);
+        }
         else {
-            let percentage_of_binary_files: f64 = self.n_binary_files as f64 / self.total_n_files as f64;
-            size_info_str += &format!("{} ({}% of total)", self.n_binary_files, format!("{:.2}", percentage_of_binary_files * 100.0));
+            size_info_str += self.unavailable_folder_information_string();
         }
         ui.label(size_info_str);
     }
 
+    /// What to show in place of information that would come from the scan, when there is no scan result (yet).
+    fn unavailable_folder_information_string(&self) -> &'static str
+    {
+        match self.state {
+            FolderToAiState::Cancelled => "unknown (the loading process has been cancelled)",
+            FolderToAiState::Error => "unknown (the loading process has failed)",
+            _ => "being determined..."
+        }
+    }
+
+    fn show_skipped_subtrees_gui(ui: &mut egui::Ui, skipped_subtrees: &[SkippedSubtree])
+    {
+        if skipped_subtrees.is_empty() {
+            return;
+        }
+
+        egui::CollapsingHeader::new(format!("Skipped subfolders: {}", skipped_subtrees.len())).id_salt("skipped_subtrees").show(ui, |ui| {
+            for skipped_subtree in skipped_subtrees {
+                let mut skipped_subtree_str = skipped_subtree.path.clone();
+                if let Some(summary) = &skipped_subtree.summary {
+                    let approximation_str = if summary.is_partial { "over " } else { "~" };
+                    skipped_subtree_str += &format!(" ({}{} files, {}{})", approximation_str, summary.n_files, approximation_str, Size::from_bytes(summary.size_in_bytes));
+                }
+                ui.label(skipped_subtree_str);
+            }
+        });
+    }
+
+    fn show_secrets_review_gui(&mut self, ui: &mut egui::Ui)
+    {
+        let Some(folder_info) = &self.folder_info else {
+            return;
+        };
+        let n_redactions = folder_info.number_of_redactions();
+        let n_withheld_sensitive_files = folder_info.number_of_withheld_sensitive_files();
+        let allowed_sensitive_file_infos: Vec<_> = folder_info.file_infos.iter().filter(|file_info| file_info.is_sensitive && !file_info.should_be_ignored).collect();
+        if n_redactions == 0 && n_withheld_sensitive_files == 0 && allowed_sensitive_file_infos.is_empty() {
+            return;
+        }
+
+        // the choice is remembered for the folder, and the folder is scanned again to apply it
+        let mut sensitive_file_path_to_allow: Option<String> = None;
+        let mut sensitive_file_path_to_withhold: Option<String> = None;
+        let header_text = format!("Secrets: {} redacted, {} sensitive file(s) withheld, {} allowed", n_redactions, n_withheld_sensitive_files, allowed_sensitive_file_infos.len());
+        egui::CollapsingHeader::new(RichText::new(header_text).color(Color32::GOLD)).id_salt("secrets_review").show(ui, |ui| {
+            for file_info in &folder_info.file_infos {
+                for redaction in &file_info.redactions {
+                    ui.label(format!("{}, line {}: {}", file_info.filepath, redaction.line_number, redaction.kind));
+                }
+            }
+            for file_info in folder_info.file_infos.iter().filter(|file_info| file_info.is_withheld_as_sensitive()) {
+                ui.horizontal(|ui| {
+                    ui.label(format!("{}: withheld: sensitive", file_info.filepath));
+                    if ui.button("Share anyway").on_hover_text("Shares this file from now on whenever this folder is scanned (with secrets redacted from it if redaction is enabled), and scans it again").clicked() {
+                        sensitive_file_path_to_allow = Some(file_info.filepath.replace('\\', "/"));
+                    }
+                });
+            }
+            for file_info in &allowed_sensitive_file_infos {
+                ui.horizontal(|ui| {
+                    ui.label(egui::RichText::new(format!("{}: sensitive, shared as allowed for this folder", file_info.filepath)).color(Color32::RED));
+                    if ui.button("Withhold again").clicked() {
+                        sensitive_file_path_to_withhold = Some(file_info.filepath.replace('\\', "/"));
+                    }
+                });
+            }
+        });
+
+        if let Some(sensitive_file_path) = sensitive_file_path_to_allow {
+            self.settings.allow_sensitive_file(&self.folder_path, &sensitive_file_path);
+            self.save_settings();
+            self.start_scan();
+        }
+        else if let Some(sensitive_file_path) = sensitive_file_path_to_withhold {
+            self.settings.withhold_sensitive_file(&self.folder_path, &sensitive_file_path);
+            self.save_settings();
+            self.start_scan();
+        }
+    }
+
     fn show_messages_gui(&mut self, ui: &mut egui::Ui)
     {
         Self::show_ui_heading(ui, "Messages");
@@ -304,11 +533,128 @@ impl FolderToAiApp {
         ui.checkbox(&mut self.settings.pasting_with_ctrlv_advances_to_next_message, "Pasting with CTRL + V advances to the next message");
 
         ui.label(egui::RichText::new(&self.clipboard_content_information_message).color(egui::Color32::GOLD));
+
+        if let Some(current_message_size) = self.folder_representation_message_sizes.get(self.current_selected_message_index) {
+            let total_size: usize = self.folder_representation_message_sizes.iter().sum();
+            ui.label(format!("Size of the current message: {} {}; of all messages: {} {}", current_message_size, self.message_size_unit_name, total_size, self.message_size_unit_name));
+        }
+
+        if let Some(tokenizer_warning) = &self.tokenizer_warning {
+            ui.label(egui::RichText::new(format!("Note: {}", tokenizer_warning)).color(egui::Color32::GOLD));
+        }
+
+        if let Some(session_id) = self.sharing_session.as_ref().map(|sharing_session| sharing_session.session_id.clone()) {
+            let n_messages = self.folder_representation_messages.len();
+            ui.horizontal(|ui| {
+                ui.label(format!("Session {}", session_id)).on_hover_text("The session is remembered for this folder, along with the current message, as long as its messages stay the same");
+                ui.label("Resume from message");
+                ui.add(egui::DragValue::new(&mut self.resume_from_message_number).range(1..=n_messages));
+                if ui.button("Resume").on_hover_text("After a chat reset or error, sets the clipboard to a message stating which messages were already delivered; pasting it advances to the chosen message").clicked() {
+                    self.resume_sharing_session(self.resume_from_message_number - 1);
+                }
+            });
+        }
+
+        if self.state == FolderToAiState::ReadyForUse {
+            ui.horizontal(|ui| {
+                ui.label("Export the scan and the messages to:");
+                ui.add(egui::TextEdit::singleline(&mut self.export_file_path_input).hint_text("e.g. C:\\exports\\folder.json"));
+                for export_format in ExportFormat::ALL {
+                    if ui.button(format!("Export as {}", export_format.label())).clicked() {
+                        self.export_scan(export_format);
+                    }
+                }
+            });
+            if !self.export_status_message.is_empty() {
+                ui.label(&self.export_status_message);
+            }
+        }
+    }
+
+    fn export_scan(&mut self, export_format: ExportFormat)
+    {
+        let Some(folder_info) = &self.folder_info else {
+            return;
+        };
+        let scan_export = ScanExport{ folder_info, messages: &self.folder_representation_messages, message_sizes: &self.folder_representation_message_sizes, message_size_unit: &self.message_size_unit_name, file_message_indices: &self.folder_representation_file_message_indices };
+        self.export_status_message = match scan_export.save_to_file(export_format, &self.export_file_path_input) {
+            Ok(()) => format!("Exported as {} to \"{}\"", export_format.label(), self.export_file_path_input),
+            Err(error) => error
+        };
+    }
+
+    fn show_apply_reply_gui(&mut self, ui: &mut egui::Ui)
+    {
+        egui::CollapsingHeader::new("Apply a reply").enabled(self.state == FolderToAiState::ReadyForUse).show(ui, |ui| {
+            ui.label("Paste an assistant's reply with whole files (as in the messages) or unified diffs, to apply them to the folder");
+            ui.add(egui::TextEdit::multiline(&mut self.reply_input).desired_rows(6).desired_width(f32::INFINITY).hint_text("Reply"));
+            ui.checkbox(&mut self.settings.back_up_files_before_applying_replies, "Back the files up before changing them");
+
+            ui.horizontal(|ui| {
+                if ui.button("Preview").on_hover_text("Shows what would change in each file, without writing anything").clicked() {
+                    self.preview_reply();
+                }
+                let there_are_applicable_changes = self.planned_file_changes.iter().any(PlannedFileChange::is_applicable);
+                if ui.add_enabled(there_are_applicable_changes, egui::Button::new("Apply")).on_hover_text("Writes the previewed changes, except the conflicting ones").clicked() {
+                    self.apply_previewed_reply();
+                }
+            });
+
+            for planned_file_change in &self.planned_file_changes {
+                let color = if planned_file_change.conflict.is_some() { egui::Color32::RED } else { egui::Color32::LIGHT_GRAY };
+                ui.label(egui::RichText::new(format!("{}: {}", planned_file_change.path, planned_file_change.summary())).color(color));
+            }
+            if !self.reply_status_message.is_empty() {
+                ui.label(egui::RichText::new(&self.reply_status_message).color(egui::Color32::GOLD));
+            }
+        });
+    }
+
+    fn preview_reply(&mut self)
+    {
+        let Some(folder_info) = &self.folder_info else {
+            return;
+        };
+        let file_edits = reply_edits::parse_reply(&self.reply_input);
+        self.planned_file_changes = reply_edits::plan_file_changes(folder_info, &file_edits);
+        self.reply_status_message = if file_edits.is_empty() { String::from("No file edit (whole file or unified diff) was found in the reply") } else { String::new() };
+    }
+
+    fn apply_previewed_reply(&mut self)
+    {
+        let backups_directory_path = if self.settings.back_up_files_before_applying_replies {
+            match setup_utils::backups_directory_path() {
+                Ok(backups_directory_path) => Some(backups_directory_path),
+                Err(error) => {
+                    // nothing is changed without the backups that were asked for
+                    self.reply_status_message = format!("No file was changed: {}", error);
+                    return;
+                }
+            }
+        }
+        else {
+            None
+        };
+        let reply_application = reply_edits::apply_file_changes(&self.folder_path, &self.planned_file_changes, backups_directory_path.as_deref());
+
+        let n_changed_files = reply_application.applied_file_changes.len() - reply_application.n_failed_file_changes();
+        self.reply_status_message = format!("{} file(s) changed", n_changed_files);
+        for applied_file_change in &reply_application.applied_file_changes {
+            if let Err(error) = &applied_file_change.result {
+                self.reply_status_message += &format!("; \"{}\" not applied: {}", applied_file_change.path, error);
+            }
+        }
+        if let Some(backup_directory_path) = &reply_application.backup_directory_path {
+            self.reply_status_message += &format!("; the previous versions were backed up to \"{}\"", backup_directory_path);
+        }
+        // the scan no longer matches the folder, so further replies are previewed against it only after scanning again
+        self.reply_status_message += ". Scan again before applying another reply.";
+        self.planned_file_changes.clear();
     }
 
     fn should_allow_user_to_interact_with_settings(&self) -> bool
     {
-        self.state == FolderToAiState::ReadyForUse
+        !self.scan_is_in_progress()
     }
 
     fn show_settings_gui(&mut self, ui: &mut egui::Ui)
@@ -326,92 +672,147 @@ impl FolderToAiApp {
         ui.separator();
         self.show_messages_gui(ui);
         ui.separator();
+        self.show_apply_reply_gui(ui);
+        ui.separator();
         self.show_settings_gui(ui);
         ui.separator();
-        ui.label(RichText::new("Press CTRL + SHIFT + D at any time to forcibly terminate FolderToAI").color(Color32::LIGHT_BLUE));
         ui.add(egui::Hyperlink::from_label_and_url("Feel free to take a look at the source code and/or contribute", LINK_TO_GIT_REPO));
     }
 
-    fn update_state(&mut self)
+    fn scan_is_in_progress(&self) -> bool
     {
-        match self.state {
-            FolderToAiState::Initializing => {
-                self.state = FolderToAiState::ObtainingInitialInformationAboutTheFiles;
-            },
-            FolderToAiState::ObtainingInitialInformationAboutTheFiles => {
-                if let Ok(folder_info) = FolderInfo::new(&self.folder_path, &self.settings) {
-                    self.folder_info = Some(folder_info);
-                    self.state = FolderToAiState::DeterminingBinaryFiles;
-                    self.total_n_files = self.folder_info.as_mut().unwrap().get_number_of_files();
+        !matches!(self.state, FolderToAiState::ReadyForUse | FolderToAiState::Cancelled | FolderToAiState::Error)
+    }
+
+    fn start_scan(&mut self)
+    {
+        self.state = FolderToAiState::ObtainingInitialInformationAboutTheFiles;
+        self.scan_error_message.clear();
+        self.folder_info = None;
+        self.total_n_files = 0;
+        self.n_binary_files = 0;
+        self.total_n_files_to_load = 0;
+        self.n_files_already_determined_whether_binary_or_not = 0;
+        self.progress_of_determining_binary_files = 0.0;
+        self.n_files_already_loaded_if_required_loading = 0;
+        self.n_files_loaded = 0;
+        self.n_files_that_could_not_be_loaded = 0;
+        self.progress_of_loading_contents = 0.0;
+        self.folder_representation_messages.clear();
+        self.folder_representation_message_sizes.clear();
+        self.folder_representation_file_message_indices.clear();
+        self.export_status_message.clear();
+        self.sharing_session = None;
+        self.pending_resume_message = None;
+        self.planned_file_changes.clear();
+        self.reply_status_message.clear();
+        self.current_selected_message_index = 0;
+        self.clipboard_content_information_message = String::from("Messages being created...");
+
+        match self.settings.message_renderer() {
+            Ok(mut message_renderer) => {
+                // without a snapshot (the folder was never shared), the whole folder is shared
+                if self.settings.share_changes_only
+                    && let Ok(snapshots_directory_path) = setup_utils::snapshots_directory_path()
+                    && let Some(folder_snapshot) = FolderSnapshot::load_for_folder(&snapshots_directory_path, &self.folder_path) {
+                    message_renderer = message_renderer.changes_since(folder_snapshot);
                 }
+                self.message_size_unit_name = message_renderer.message_size_unit_name().to_string();
+                self.tokenizer_warning = self.settings.tokenizer_warning();
+                self.scan_worker = Some(ScanWorker::start(self.folder_path.clone(), self.settings.scan_options(&self.folder_path), message_renderer, self.settings.git_change_set.clone()));
             },
-            FolderToAiState::DeterminingBinaryFiles => {
-                if self.total_n_files > 0 {
-                    self.folder_info.as_mut().unwrap().determine_binarity_of_next_file(self.n_files_already_determined_whether_binary_or_not, &self.settings);
-                    self.n_files_already_determined_whether_binary_or_not += 1;
-    
-                    if self.total_n_files > 0 {
-                        self.progress_of_determining_binary_files = self.n_files_already_determined_whether_binary_or_not as f64 / self.total_n_files as f64;
-                    }
-                    else {
-                        self.progress_of_determining_binary_files = 1.0;
-                    }
-                }
-                else {
+            Err(error) => {
+                self.handle_scan_event(ScanEvent::Failed(error));
+            }
+        }
+    }
+
+    fn cancel_scan(&mut self)
+    {
+        if let Some(scan_worker) = self.scan_worker.take() {
+            scan_worker.cancel();
+        }
+        self.state = FolderToAiState::Cancelled;
+        self.clipboard_content_information_message = String::from("No messages have been created");
+    }
+
+    fn handle_scan_event(&mut self, scan_event: ScanEvent)
+    {
+        match scan_event {
+            ScanEvent::FilesListed(folder_info) => {
+                self.total_n_files = folder_info.get_number_of_files_not_ignored();
+                self.folder_info = Some(folder_info);
+                self.state = FolderToAiState::DeterminingBinaryFiles;
+                if self.total_n_files == 0 {
                     self.progress_of_determining_binary_files = 1.0;
                 }
-    
-                if self.total_n_files == self.n_files_already_determined_whether_binary_or_not {
-                    self.folder_info.as_mut().unwrap().determine_files_too_large(self.settings.file_size_limit, &self.settings);
-    
-                    self.total_n_files_to_load = self.folder_info.as_mut().unwrap().get_number_of_files_whose_contents_should_be_loaded();
-    
-                    self.n_binary_files = self.folder_info.as_mut().unwrap().number_of_binary_files();
-    
-                    self.state = FolderToAiState::LoadingContents;
-                }    
             },
-            FolderToAiState::LoadingContents => {
-                if self.total_n_files > 0 {
-                    let did_load_file = self.folder_info.as_mut().unwrap().load_next_file_content_if_required(self.n_files_already_loaded_if_required_loading);
-                    self.n_files_already_loaded_if_required_loading += 1;
-                    if did_load_file {
-                        self.n_files_loaded += 1;
-                    }
-    
-                    if self.total_n_files_to_load > 0 {
-                        self.progress_of_loading_contents = self.n_files_loaded as f64 / self.total_n_files_to_load as f64;
-                    }
-                    else {
-                        self.progress_of_loading_contents = 1.0;
-                    }
-                }
-                else {
+            ScanEvent::BinaryFilesDetermined { n_binary_files, n_files_to_load } => {
+                self.n_binary_files = n_binary_files;
+                self.total_n_files_to_load = n_files_to_load;
+                self.state = FolderToAiState::LoadingContents;
+                if self.total_n_files_to_load == 0 {
                     self.progress_of_loading_contents = 1.0;
                 }
-    
-                if self.total_n_files == self.n_files_already_loaded_if_required_loading {
-                    self.n_files_that_could_not_be_loaded = self.folder_info.as_mut().unwrap().number_of_files_that_could_not_be_loaded();
-                    
-                    self.state = FolderToAiState::ProcessingContents;
-                }
             },
-            FolderToAiState::ProcessingContents => {
-                if let Ok(folder_representation_messages) = core_utils::obtain_folder_representation_messages(&self.folder_info.as_mut().unwrap()) {
-                    self.folder_representation_messages = folder_representation_messages;
-    
-                    if !self.folder_representation_messages.is_empty() {
-                        clipboard_utils::set_clipboard_content(&self.folder_representation_messages[self.current_selected_message_index]);
-                        self.clipboard_content_information_message = format!("Clipboard has been set to message {} of {}", self.current_selected_message_index + 1, self.folder_representation_messages.len());
-                    }
-    
-                    self.state = FolderToAiState::ReadyForUse;
+            ScanEvent::ContentsLoaded { n_files_that_could_not_be_loaded } => {
+                self.n_files_that_could_not_be_loaded = n_files_that_could_not_be_loaded;
+                self.state = FolderToAiState::ProcessingContents;
+            },
+            ScanEvent::Finished { folder_info, messages, message_sizes, session_id, file_message_indices } => {
+                // sharing git changes leaves most of the folder out, so it does not count as sharing the folder
+                if self.settings.git_change_set.is_none()
+                    && let Ok(snapshots_directory_path) = setup_utils::snapshots_directory_path() {
+                    let _ = FolderSnapshot::of_folder(&folder_info, self.settings.redact_secrets).save(&snapshots_directory_path);
                 }
-                else {
-                    self.state = FolderToAiState::Error;
+                self.folder_info = Some(folder_info);
+                self.folder_representation_messages = messages;
+                self.folder_representation_message_sizes = message_sizes;
+                self.folder_representation_file_message_indices = file_message_indices;
+                self.scan_worker = None;
+
+                if !self.folder_representation_messages.is_empty() {
+                    self.start_or_continue_sharing_session(session_id);
+                    self.send_current_message_to_clipboard();
                 }
+
+                self.state = FolderToAiState::ReadyForUse;
             },
-            _ => {}
+            ScanEvent::Failed(error) => {
+                // the reason is also given where the messages would be, e.g. when they would exceed the context budget of the profile
+                self.clipboard_content_information_message = format!("No messages have been created: {}", error);
+                self.scan_error_message = error;
+                self.scan_worker = None;
+                self.state = FolderToAiState::Error;
+            }
+        }
+    }
+
+    fn update_scan_progress(&mut self)
+    {
+        let Some(scan_worker) = &self.scan_worker else {
+            return;
+        };
+        let scan_progress = scan_worker.progress();
+
+        self.n_files_already_determined_whether_binary_or_not = scan_progress.n_files_already_determined_whether_binary_or_not();
+        if self.total_n_files > 0 {
+            self.progress_of_determining_binary_files = self.n_files_already_determined_whether_binary_or_not as f64 / self.total_n_files as f64;
+        }
+
+        self.n_files_already_loaded_if_required_loading = scan_progress.n_files_already_loaded_if_required_loading();
+        self.n_files_loaded = scan_progress.n_files_loaded();
+        if self.total_n_files_to_load > 0 {
+            self.progress_of_loading_contents = self.n_files_loaded as f64 / self.total_n_files_to_load as f64;
+        }
+    }
+
+    fn handle_pending_scan_events(&mut self)
+    {
+        self.update_scan_progress();
+
+        while let Some(scan_event) = self.scan_worker.as_ref().and_then(|scan_worker| scan_worker.try_receive_event()) {
+            self.handle_scan_event(scan_event);
         }
     }
 }
@@ -419,7 +820,7 @@ impl FolderToAiApp {
 impl eframe::App for FolderToAiApp {
     fn on_exit(&mut self, _: Option<&eframe::glow::Context>)
     {
-        self.settings.save_to_file(&setup_utils::settings_file_path());
+        self.save_settings();
     }
 
     fn update(&mut self, ctx: &eframe::egui::Context, _: &mut eframe::Frame) {
@@ -434,27 +835,14 @@ impl eframe::App for FolderToAiApp {
         }
 
         if self.gui_has_ever_been_updated {
-            if self.state != FolderToAiState::ReadyForUse {
-                let update_start_instant = Instant::now();
-
-                loop {
-                    if self.state != FolderToAiState::ReadyForUse {
-                        let was_initializing = self.state == FolderToAiState::Initializing;
-
-                        self.update_state();
-
-                        if was_initializing {
-                            break;
-                        }
-                    }
-
-                    let duration_since_update_start = update_start_instant.elapsed();
-                    if duration_since_update_start.as_millis() >= GUI_UPDATE_DELAY_MS {
-                        break;
-                    }
-                }
+            if self.state == FolderToAiState::Initializing {
+                self.start_scan();
             }
-            else {
+            else if self.scan_is_in_progress() {
+                self.handle_pending_scan_events();
+            }
+            // pasting into a text field of the window itself (e.g. a reply) must not advance the messages
+            else if self.state == FolderToAiState::ReadyForUse && !(ctx.wants_keyboard_input() && ctx.input(|input_state| input_state.focused)) {
                 self.deal_with_user_input();
             }
         }
@@ -463,16 +851,20 @@ impl eframe::App for FolderToAiApp {
             self.gui_has_ever_been_updated = true;
         }
 
-        if self.state == FolderToAiState::ReadyForUse {
-            ctx.request_repaint_after(std::time::Duration::from_millis(GUI_UPDATE_DELAY_MS as u64));
-        }
+        // the scan progresses (and the keyboard is polled) independently of GUI events, so the GUI must keep updating by itself
+        ctx.request_repaint_after(Duration::from_millis(GUI_UPDATE_DELAY_MS));
     }
 }
 
 fn on_invoked_for_folder(folder_path: String)
 {
     let mut icon_data: Option<Arc<egui::viewport::IconData>> = None;
-    if let Ok(icon) = image::open(setup_utils::setup_icon_path()) {
+    // the icon file is only created by the setup, which is only available on Windows
+    #[cfg(windows)]
+    let icon = image::open(setup_utils::setup_icon_path());
+    #[cfg(not(windows))]
+    let icon: Result<image::DynamicImage, ()> = Err(());
+    if let Ok(icon) = icon {
         let icon_rgba8 = icon.to_rgba8();
         let (icon_width, icon_height) = icon_rgba8.dimensions();
         icon_data = Some(Arc::new(egui::viewport::IconData {
@@ -490,30 +882,14 @@ fn on_invoked_for_folder(folder_path: String)
         ..Default::default()
     };
 
-    // due to the synchronous nature of how the application is currently structured, it is possible that the user experences unresponsiveness in the program
-    // to partially mitigate this, we offer the possibility of forcibly closing the program with a hotkey
-    let may_terminate_thread_flag = Arc::new(AtomicBool::new(false));
-    let may_terminate_thread_flag_clone = Arc::clone(&may_terminate_thread_flag);
-    let allow_to_forcibly_terminate_thread_handle = thread::spawn(move || {
-        while !may_terminate_thread_flag_clone.load(Ordering::Relaxed) {
-            if InputManager::new().is_control_shift_d_pressed() {
-                std::process::exit(1);
-            }
-            thread::sleep(std::time::Duration::from_millis(50));
-        }
-    });
-
-    let folder_path_clone = folder_path.clone();
-    eframe::run_native(
+    let _ = eframe::run_native(
         APP_NAME,
         options,
-        Box::new(|_| Ok(Box::new(FolderToAiApp::new(folder_path_clone)))),
+        Box::new(|_| Ok(Box::new(FolderToAiApp::new(folder_path)))),
     );
-
-    may_terminate_thread_flag.store(true, Ordering::Relaxed);
-    allow_to_forcibly_terminate_thread_handle.join().unwrap();
 }
 
+#[cfg(windows)]
 fn on_manual_run()
 {
     if setup_utils::is_being_executed_from_installation_location() {
@@ -524,8 +900,8 @@ fn on_manual_run()
     if setup_utils::is_installed() {
         let user_wants_to_uninstall = win_utils::yesno_message_box("Do you wish to uninstall FolderToAI?", APP_NAME);
         if user_wants_to_uninstall {
-            setup_utils::uninstall();
-            if setup_utils::is_completely_uninstalled() {
+            let all_steps_succeeded = setup_utils::uninstall();
+            if all_steps_succeeded && setup_utils::is_completely_uninstalled() {
                 win_utils::message_box("FolderToAI has been successfully uninstalled.", APP_NAME);
             }
             else {
@@ -540,22 +916,58 @@ fn on_manual_run()
                 win_utils::message_box("Setup successful.\n\nRight click inside a folder to use FolderToAI.", APP_NAME);
             }
             else {
-                win_utils::message_box("Unfortunately something went wrong with the installation.", APP_NAME);
+                // what the installation left behind is removed on a best-effort basis
                 setup_utils::uninstall();
+                win_utils::message_box("Unfortunately something went wrong with the installation.", APP_NAME);
             }
         }
     }
 }
 
+#[cfg(not(windows))]
+fn on_manual_run()
+{
+    // the setup (Windows Explorer context menu integration) is only available on Windows
+    Cli::command().print_help().ok();
+}
 
-fn main() {
+fn main() -> ExitCode {
     let args: Vec<String> = std::env::args().collect();
-    if args.len() > 1 {
-        let folder_path: String = args[1].clone();
-
-        on_invoked_for_folder(folder_path);
-    }
-    else {
+    if args.len() <= 1 {
         on_manual_run();
+        return ExitCode::SUCCESS;
+    }
+
+    // the executable uses the windows subsystem, so it has no console of its own; output goes to the console it has been invoked from, if any
+    #[cfg(windows)]
+    win_utils::attach_to_parent_console();
+
+    let cli = Cli::parse();
+    match cli.command {
+        Some(CliCommand::Gui { folder }) => {
+            on_invoked_for_folder(folder);
+            ExitCode::SUCCESS
+        },
+        Some(CliCommand::Messages(messages_args)) => {
+            cli::run_messages_command(&messages_args)
+        },
+        Some(CliCommand::Verify { folder, transcript }) => {
+            cli::run_verify_command(&folder, transcript.as_deref())
+        },
+        Some(CliCommand::Reassemble { transcript, output_dir }) => {
+            cli::run_reassemble_command(transcript.as_deref(), &output_dir)
+        },
+        Some(CliCommand::Apply(apply_args)) => {
+            cli::run_apply_command(&apply_args)
+        },
+        None => {
+            if let Some(folder) = cli.folder {
+                on_invoked_for_folder(folder);
+            }
+            else {
+                Cli::command().print_help().ok();
+            }
+            ExitCode::SUCCESS
+        }
     }
-}
\ No newline at end of file
+}
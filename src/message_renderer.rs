@@ -0,0 +1,196 @@
+use crate::core_utils;
+use crate::folder_info::FolderInfo;
+use crate::git_changes::GitChanges;
+use crate::message_templates::MessageTemplates;
+use crate::output_format::OutputFormat;
+use crate::snapshot::{FolderChanges, FolderSnapshot};
+use crate::tokenizer::{CharacterCounter, Tokenizer};
+use crate::tree_overview::TreeOverviewPlacement;
+
+use std::sync::Arc;
+
+/// The messages rendered from a scanned folder.
+#[derive(Clone, Debug, Default)]
+pub struct RenderedMessages {
+    pub messages: Vec<String>,
+    /// Session ID stated in the messages' headers, if they have integrity markers.
+    pub session_id: Option<String>,
+    /// Indices (starting at 1, as stated in the messages) of the messages each file's content lands in, in the order of the folder's files;
+    /// empty for ignored files.
+    pub file_message_indices: Vec<Vec<usize>>
+}
+
+/// Turns a scanned folder into the sequence of messages to be sent to an AI chat.
+///
+/// Messages are limited to a maximum size, measured by a [`Tokenizer`] (by default, in characters).
+#[derive(Clone)]
+pub struct MessageRenderer {
+    pub(crate) maximum_message_size: usize,
+    pub(crate) context_budget: Option<usize>,
+    pub(crate) tokenizer: Arc<dyn Tokenizer>,
+    pub(crate) output_format: OutputFormat,
+    pub(crate) split_on_line_boundaries: bool,
+    pub(crate) single_message: bool,
+    pub(crate) task_prompt: Option<String>,
+    pub(crate) integrity_markers: bool,
+    pub(crate) reply_format_note: bool,
+    pub(crate) previous_snapshot: Option<Arc<FolderSnapshot>>,
+    pub(crate) modified_files_as_diffs: bool,
+    pub(crate) git_changes: Option<Arc<GitChanges>>,
+    pub(crate) tree_overview_placement: TreeOverviewPlacement,
+    pub(crate) templates: Option<MessageTemplates>
+}
+
+impl MessageRenderer {
+    pub fn new() -> Self {
+        MessageRenderer{ maximum_message_size: core_utils::DEFAULT_MAXIMUM_MESSAGE_SIZE, context_budget: None, tokenizer: Arc::new(CharacterCounter), output_format: OutputFormat::default(), split_on_line_boundaries: true, single_message: false, task_prompt: None, integrity_markers: true, reply_format_note: false, previous_snapshot: None, modified_files_as_diffs: true, git_changes: None, tree_overview_placement: TreeOverviewPlacement::default(), templates: None }
+    }
+
+    /// Sets the tokenizer that measures messages; the maximum message size is expressed in its unit.
+    pub fn tokenizer(mut self, tokenizer: Arc<dyn Tokenizer>) -> Self {
+        self.tokenizer = tokenizer;
+        self
+    }
+
+    /// Sets the maximum size of each message, in the unit of the tokenizer (e.g. 8000 tokens); it must be at least
+    /// [`core_utils::MINIMUM_MESSAGE_SIZE`].
+    pub fn maximum_message_size(mut self, maximum_message_size: usize) -> Self {
+        self.maximum_message_size = maximum_message_size;
+        self
+    }
+
+    /// Sets the maximum size of all the messages together, in the unit of the tokenizer (e.g. the context window of the model);
+    /// rendering fails if the messages exceed it.
+    pub fn context_budget(mut self, context_budget: Option<usize>) -> Self {
+        self.context_budget = context_budget;
+        self
+    }
+
+    /// Sets the format of the messages, which also determines the default templates.
+    pub fn output_format(mut self, output_format: OutputFormat) -> Self {
+        self.output_format = output_format;
+        self
+    }
+
+    /// Whether files are kept whole when they fit in a message and otherwise split at line boundaries, into parts with their own headers
+    /// (the default), instead of being cut wherever a message fills up, which packs them slightly more tightly.
+    pub fn split_on_line_boundaries(mut self, split_on_line_boundaries: bool) -> Self {
+        self.split_on_line_boundaries = split_on_line_boundaries;
+        self
+    }
+
+    /// Whether everything (a short preamble, the tree overview if any and all the files) is rendered as one self-contained message,
+    /// for long-context models; the maximum message size then acts as a safety limit, and rendering fails if the message exceeds it.
+    pub fn single_message(mut self, single_message: bool) -> Self {
+        self.single_message = single_message;
+        self
+    }
+
+    /// Sets the task to carry out with the files (e.g. "Review for bugs"), sent in a closing message (or at the end of the single message)
+    /// after the prologue has asked to wait for it.
+    pub fn task_prompt(mut self, task_prompt: Option<String>) -> Self {
+        self.task_prompt = task_prompt;
+        self
+    }
+
+    /// Whether message headers state a session ID and a checksum of the message's body (the default), so that duplicated, missing or
+    /// foreign messages can be detected; see [`crate::integrity`].
+    pub fn integrity_markers(mut self, integrity_markers: bool) -> Self {
+        self.integrity_markers = integrity_markers;
+        self
+    }
+
+    /// Whether the first message asks to give changes to the files in full, in the output format, or as unified diffs,
+    /// so that replies can be applied to the folder; see [`crate::reply_edits`].
+    pub fn reply_format_note(mut self, reply_format_note: bool) -> Self {
+        self.reply_format_note = reply_format_note;
+        self
+    }
+
+    /// Shares only the files added or modified since the snapshot was taken (e.g. when the folder was last shared), listing deleted ones,
+    /// in messages presented as an update to the files shared earlier; the tree overview is left out.
+    pub fn changes_since(mut self, folder_snapshot: FolderSnapshot) -> Self {
+        self.previous_snapshot = Some(Arc::new(folder_snapshot));
+        self
+    }
+
+    /// Whether modified files are given as unified diffs against their previous version (the default) rather than in full,
+    /// when sharing changes.
+    pub fn modified_files_as_diffs(mut self, modified_files_as_diffs: bool) -> Self {
+        self.modified_files_as_diffs = modified_files_as_diffs;
+        self
+    }
+
+    /// Shares only the files touched by the changes of a git repository (in the version the changes lead to), preceded by the diff of
+    /// the changes, with the branch and its recent commits stated in the first message; the tree overview is left out. This takes
+    /// precedence over [`MessageRenderer::changes_since`].
+    pub fn git_changes(mut self, git_changes: GitChanges) -> Self {
+        self.git_changes = Some(Arc::new(git_changes));
+        self
+    }
+
+    /// Whether (and where) a `tree`-style listing of the folder is included, so that the layout is known before any content arrives.
+    pub fn tree_overview_placement(mut self, tree_overview_placement: TreeOverviewPlacement) -> Self {
+        self.tree_overview_placement = tree_overview_placement;
+        self
+    }
+
+    /// Whether files are split at line boundaries, either by choice or because the output format requires it.
+    pub(crate) fn splits_on_line_boundaries(&self) -> bool {
+        self.split_on_line_boundaries || self.output_format.requires_line_boundaries()
+    }
+
+    /// Sets the wording of the messages, instead of the default templates of the output format; the templates are validated when rendering.
+    pub fn templates(mut self, templates: MessageTemplates) -> Self {
+        self.templates = Some(templates);
+        self
+    }
+
+    /// Limits messages to `maximum_amount_of_characters_per_message` characters, replacing the tokenizer.
+    pub fn maximum_amount_of_characters_per_message(self, maximum_amount_of_characters_per_message: usize) -> Self {
+        self.tokenizer(Arc::new(CharacterCounter)).maximum_message_size(maximum_amount_of_characters_per_message)
+    }
+
+    /// Size of `message` in the unit of the tokenizer.
+    pub fn message_size(&self, message: &str) -> usize {
+        self.tokenizer.count(message)
+    }
+
+    pub fn message_size_unit_name(&self) -> &str {
+        self.tokenizer.unit_name()
+    }
+
+    /// Renders the messages; the first one is a prologue explaining how the remaining ones are structured.
+    pub fn render(&self, folder_info: &FolderInfo) -> Result<Vec<String>, String> {
+        self.render_with_file_message_indices(folder_info).map(|rendered_messages| rendered_messages.messages)
+    }
+
+    /// Renders the messages, also keeping track of the messages each file lands in.
+    pub fn render_with_file_message_indices(&self, folder_info: &FolderInfo) -> Result<RenderedMessages, String> {
+        let templates = self.templates.clone().unwrap_or_else(|| MessageTemplates::for_output_format(self.output_format));
+        let rendered_messages = match (&self.git_changes, &self.previous_snapshot) {
+            (Some(git_changes), _) => core_utils::obtain_git_changes_messages(git_changes, folder_info, self, &templates),
+            (None, Some(previous_snapshot)) => {
+                let folder_changes = FolderChanges::since_snapshot(previous_snapshot, folder_info, self.modified_files_as_diffs);
+                core_utils::obtain_folder_update_messages(&folder_changes, self, &templates)
+            },
+            (None, None) => core_utils::obtain_folder_representation_messages(folder_info, self, &templates)
+        }?;
+
+        if let Some(context_budget) = self.context_budget {
+            let total_size: usize = rendered_messages.messages.iter().map(|message| self.message_size(message)).sum();
+            if total_size > context_budget {
+                let unit_name = self.message_size_unit_name();
+                return Err(format!("the messages would take {} {}, more than the context budget of {} {}", total_size, unit_name, context_budget, unit_name));
+            }
+        }
+
+        Ok(rendered_messages)
+    }
+}
+
+impl Default for MessageRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,265 @@
+use crate::output_format::OutputFormat;
+
+use serde::{Serialize, Deserialize};
+
+// {task_note} asks to wait for the closing message before answering, when there is a task prompt; {integrity_marker} states the session ID
+// (and, in other messages, the checksum of the message's body) and {integrity_note} explains it, when integrity markers are enabled;
+// {reply_note} asks to give changes to the files in a format they can be applied from, when requested; {update_note} explains,
+// when only the changes since the last share (or those of a git repository) are sent, what the messages update and what changed
+pub const PROLOGUE_PLACEHOLDERS: &[&str] = &["folder", "folder_size", "index", "total", "limit", "unit", "splitting_note", "update_note", "task_note", "integrity_marker", "integrity_note", "session", "reply_note"];
+pub const EMPTY_FOLDER_PLACEHOLDERS: &[&str] = &["folder", "index", "total", "task_note", "integrity_marker", "session"];
+pub const MESSAGE_HEADER_PLACEHOLDERS: &[&str] = &["index", "total", "integrity_marker", "session", "checksum"];
+pub const SINGLE_MESSAGE_PREAMBLE_PLACEHOLDERS: &[&str] = &["folder", "folder_size", "update_note", "reply_note"];
+pub const CLOSING_MESSAGE_PLACEHOLDERS: &[&str] = &["index", "total", "task", "integrity_marker", "session", "checksum"];
+pub const SINGLE_MESSAGE_TASK_PLACEHOLDERS: &[&str] = &["task"];
+// {delivered} states which messages were already delivered, and {index} is the one sharing resumes with
+pub const RESUME_MESSAGE_PLACEHOLDERS: &[&str] = &["session", "folder", "delivered", "index", "total"];
+// {fence} is a run of backticks longer than any within the content, and {newline_if_missing} a line break if the content does not end with one;
+// {path_code} is the path as Markdown inline code, delimited by more backticks than it contains
+pub const FILE_PLACEHOLDERS: &[&str] = &["path", "path_code", "size", "status", "lines", "language", "content", "fence", "newline_if_missing"];
+pub const FILE_PART_PLACEHOLDERS: &[&str] = &["path", "path_code", "size", "status", "lines", "language", "content", "fence", "newline_if_missing", "part", "parts", "first_line", "last_line"];
+
+/// Wording of the generated messages.
+///
+/// Templates contain placeholders between braces, such as `{path}`, which are replaced by their values; `{{` and `}}` stand for literal braces.
+/// The placeholders available in each template are listed in the constants of this module.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct MessageTemplates {
+    /// The first message, explaining how the remaining ones are structured.
+    pub prologue: String,
+    /// The only message, when no file is to be sent.
+    pub empty_folder: String,
+    /// Start of every message but the first one.
+    pub message_header: String,
+    /// Start of the only message, in single-message mode.
+    pub single_message_preamble: String,
+    /// A whole file.
+    pub file: String,
+    /// A part of a file that does not fit in a single message.
+    pub file_part: String,
+    /// The last message, carrying the task prompt, if any.
+    pub closing_message: String,
+    /// End of the only message, carrying the task prompt, if any, in single-message mode.
+    pub single_message_task: String,
+    /// Sent before resuming a sharing session that was interrupted (e.g. by a chat reset), to re-orient the model.
+    pub resume_message: String
+}
+
+impl MessageTemplates {
+    pub fn new() -> Self {
+        MessageTemplates {
+            prologue: String::from("[FolderToAI]\n\nMessage {index}/{total}{integrity_marker}:\n\nThis and the message(s) that follow will provide you relevant information about the files within the folder \"{folder}\", which occupies {folder_size}. There are {total} messages in total.{update_note}\n\nAt the beginning of each message, its index will be stated, along with the total number of messages. Each file's content will be be between lines that read \"--- BEGINNING OF CONTENT ---\" and \"--- END OF CONTENT ---\". {splitting_note}{integrity_note}\n\nThe messages will contain at most {limit} {unit}, including line breaks. Please acknowledge that you get all the messages correctly and in sequence, given the indices provided at the beginning of each message. Warn me about any gaps (missing messages) and make sure you receive all {total} of them in order.{task_note}{reply_note}"),
+            empty_folder: String::from("[FolderToAI]\n\nMessage {index}/{total}{integrity_marker}:\n\nThis message will provide you relevant information about the files within the folder {folder}.\n\nThe folder contains no relevant files.{task_note}"),
+            message_header: String::from("Message {index}/{total}{integrity_marker}:\n"),
+            single_message_preamble: String::from("[FolderToAI]\n\nThis message provides the files within the folder \"{folder}\", which occupies {folder_size}. Each file's content is between lines that read \"--- BEGINNING OF CONTENT ---\" and \"--- END OF CONTENT ---\".{update_note}{reply_note}"),
+            file: String::from("File: {path}\nSize: {size}\n--- BEGINNING OF CONTENT ---\n{content}\n--- END OF CONTENT ---\n"),
+            file_part: String::from("File: {path} (part {part}/{parts}), lines {first_line}–{last_line}\nSize: {size}\n--- BEGINNING OF CONTENT ---\n{content}\n--- END OF CONTENT ---\n"),
+            closing_message: String::from("Message {index}/{total}{integrity_marker}:\n\nAll the files have been sent. Now, based on them, please carry out the following task:\n\n{task}"),
+            single_message_task: String::from("Based on the files above, please carry out the following task:\n\n{task}"),
+            resume_message: String::from("[FolderToAI]\n\nThe conversation was interrupted while sharing the files within the folder \"{folder}\" (session {session}). {delivered} Sharing resumes with message {index}/{total}; please keep what you received so far and continue acknowledging the messages in order, as before.")
+        }
+    }
+
+    /// The default templates of an output format.
+    pub fn for_output_format(output_format: OutputFormat) -> Self {
+        match output_format {
+            OutputFormat::PlainText => Self::new(),
+            OutputFormat::Markdown => MessageTemplates {
+                prologue: String::from("[FolderToAI]\n\nMessage {index}/{total}{integrity_marker}:\n\nThis and the message(s) that follow will provide you relevant information about the files within the folder \"{folder}\", which occupies {folder_size}. There are {total} messages in total.{update_note}\n\nAt the beginning of each message, its index will be stated, along with the total number of messages. Each file is given as a heading with its path and size, followed by its content in a fenced code block. {splitting_note}{integrity_note}\n\nThe messages will contain at most {limit} {unit}, including line breaks. Please acknowledge that you get all the messages correctly and in sequence, given the indices provided at the beginning of each message. Warn me about any gaps (missing messages) and make sure you receive all {total} of them in order.{task_note}{reply_note}"),
+                single_message_preamble: String::from("[FolderToAI]\n\nThis message provides the files within the folder \"{folder}\", which occupies {folder_size}. Each file is given as a heading with its path and size, followed by its content in a fenced code block.{update_note}{reply_note}"),
+                file: String::from("### {path_code} ({size})\n\n{fence}{language}\n{content}{newline_if_missing}{fence}\n\n"),
+                file_part: String::from("### {path_code} ({size}), part {part}/{parts}, lines {first_line}–{last_line}\n\n{fence}{language}\n{content}{newline_if_missing}{fence}\n\n"),
+                ..Self::new()
+            },
+            OutputFormat::Xml => MessageTemplates {
+                prologue: String::from("[FolderToAI]\n\nMessage {index}/{total}{integrity_marker}:\n\nThis and the message(s) that follow will provide you relevant information about the files within the folder \"{folder}\", which occupies {folder_size}. There are {total} messages in total.{update_note}\n\nAt the beginning of each message, its index will be stated, along with the total number of messages. Each file is given as a <document> element whose path, size, status and lines attributes state its path, size, status (loaded, binary, too large or failed) and number of lines; its content is within a <content> element, wrapped in a CDATA section. Files that do not fit in a single message are split at line boundaries into several <document> elements with the same path, whose part, parts, first_line and last_line attributes state the part's index, the number of parts and the lines it contains; each message is well-formed on its own.{integrity_note}\n\nThe messages will contain at most {limit} {unit}, including line breaks. Please acknowledge that you get all the messages correctly and in sequence, given the indices provided at the beginning of each message. Warn me about any gaps (missing messages) and make sure you receive all {total} of them in order.{task_note}{reply_note}"),
+                single_message_preamble: String::from("[FolderToAI]\n\nThis message provides the files within the folder \"{folder}\", which occupies {folder_size}. Each file is given as a <document> element whose path, size, status and lines attributes state its path, size, status (loaded, binary, too large or failed) and number of lines; its content is within a <content> element, wrapped in a CDATA section.{update_note}{reply_note}"),
+                file: String::from("<document path=\"{path}\" size=\"{size}\" status=\"{status}\" lines=\"{lines}\">\n<content>{content}</content>\n</document>\n"),
+                file_part: String::from("<document path=\"{path}\" size=\"{size}\" status=\"{status}\" lines=\"{lines}\" part=\"{part}\" parts=\"{parts}\" first_line=\"{first_line}\" last_line=\"{last_line}\">\n<content>{content}</content>\n</document>\n"),
+                ..Self::new()
+            }
+        }
+    }
+
+    pub fn load_from_file(path: &str) -> Result<Self, String> {
+        let json = std::fs::read_to_string(path).map_err(|error| format!("could not read the templates file \"{}\": {}", path, error))?;
+        let message_templates: MessageTemplates = serde_json::from_str(&json).map_err(|error| format!("invalid templates file \"{}\": {}", path, error))?;
+        message_templates.validate()?;
+        Ok(message_templates)
+    }
+
+    pub fn save_to_file(&self, path: &str) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self).map_err(|error| error.to_string())?;
+        std::fs::write(path, json).map_err(|error| format!("could not write the templates file \"{}\": {}", path, error))
+    }
+
+    /// Checks that every template is well-formed and only uses the placeholders available to it, and that file templates include the content.
+    pub fn validate(&self) -> Result<(), String> {
+        validate_template("prologue", &self.prologue, PROLOGUE_PLACEHOLDERS)?;
+        validate_template("empty folder", &self.empty_folder, EMPTY_FOLDER_PLACEHOLDERS)?;
+        validate_template("message header", &self.message_header, MESSAGE_HEADER_PLACEHOLDERS)?;
+        validate_template("single message preamble", &self.single_message_preamble, SINGLE_MESSAGE_PREAMBLE_PLACEHOLDERS)?;
+        validate_template("file", &self.file, FILE_PLACEHOLDERS)?;
+        validate_template("file part", &self.file_part, FILE_PART_PLACEHOLDERS)?;
+        validate_template("closing message", &self.closing_message, CLOSING_MESSAGE_PLACEHOLDERS)?;
+        validate_template("single message task", &self.single_message_task, SINGLE_MESSAGE_TASK_PLACEHOLDERS)?;
+        validate_template("resume message", &self.resume_message, RESUME_MESSAGE_PLACEHOLDERS)?;
+
+        for (template_name, template) in [("file", &self.file), ("file part", &self.file_part)] {
+            if !placeholders_of_template(template)?.iter().any(|placeholder| placeholder == "content") {
+                return Err(format!("the {} template must contain the {{content}} placeholder", template_name));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for MessageTemplates {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Names of the placeholders found in `template`, in order of appearance.
+fn placeholders_of_template(template: &str) -> Result<Vec<String>, String> {
+    let mut placeholders = vec!();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => { chars.next(); },
+            '}' if chars.peek() == Some(&'}') => { chars.next(); },
+            '{' => {
+                let mut placeholder = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) if c.is_ascii_alphanumeric() || c == '_' => placeholder.push(c),
+                        _ => return Err(format!("unterminated placeholder \"{{{}\" (use \"{{{{\" for a literal brace)", placeholder))
+                    }
+                }
+                placeholders.push(placeholder);
+            },
+            '}' => return Err(String::from("unmatched \"}\" (use \"}}\" for a literal brace)")),
+            _ => {}
+        }
+    }
+    Ok(placeholders)
+}
+
+fn validate_template(template_name: &str, template: &str, available_placeholders: &[&str]) -> Result<(), String> {
+    let placeholders = placeholders_of_template(template).map_err(|error| format!("invalid {} template: {}", template_name, error))?;
+    for placeholder in placeholders {
+        if !available_placeholders.contains(&placeholder.as_str()) {
+            let available_placeholders_str: Vec<String> = available_placeholders.iter().map(|placeholder| format!("{{{}}}", placeholder)).collect();
+            return Err(format!("invalid {} template: unknown placeholder {{{}}} (available: {})", template_name, placeholder, available_placeholders_str.join(", ")));
+        }
+    }
+    Ok(())
+}
+
+fn longest_backtick_run_length(text: &str) -> usize {
+    let mut longest_backtick_run_length = 0;
+    let mut current_backtick_run_length = 0;
+    for c in text.chars() {
+        if c == '`' {
+            current_backtick_run_length += 1;
+            longest_backtick_run_length = std::cmp::max(longest_backtick_run_length, current_backtick_run_length);
+        }
+        else {
+            current_backtick_run_length = 0;
+        }
+    }
+    longest_backtick_run_length
+}
+
+/// Markdown code fence for `content`: a run of backticks longer than any found within it, and at least three long.
+pub fn code_fence(content: &str) -> String {
+    "`".repeat(std::cmp::max(3, longest_backtick_run_length(content) + 1))
+}
+
+/// Markdown inline code with `text`: between runs of backticks longer than any found within it, and padded with spaces if it starts
+/// or ends with a backtick, which would otherwise merge with the delimiters.
+pub fn code_span(text: &str) -> String {
+    let delimiter = "`".repeat(longest_backtick_run_length(text) + 1);
+    let padding = if text.starts_with('`') || text.ends_with('`') { " " } else { "" };
+    format!("{}{}{}{}{}", delimiter, padding, text, padding, delimiter)
+}
+
+/// Replaces the placeholders of a template (assumed valid) by their values; placeholders without a value are left empty.
+pub fn fill_template(template: &str, values: &[(&str, &str)]) -> String {
+    let mut filled_template = String::with_capacity(template.len());
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => { chars.next(); filled_template.push('{'); },
+            '}' if chars.peek() == Some(&'}') => { chars.next(); filled_template.push('}'); },
+            '{' => {
+                let placeholder: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                if let Some((_, value)) = values.iter().find(|(name, _)| *name == placeholder) {
+                    filled_template += value;
+                }
+            },
+            _ => filled_template.push(c)
+        }
+    }
+    filled_template
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_templates_are_valid() {
+        assert!(MessageTemplates::new().validate().is_ok());
+    }
+
+    #[test]
+    fn placeholders_unavailable_to_a_template_are_rejected() {
+        let message_templates = MessageTemplates{ message_header: String::from("Message {index}/{total} of {folder}\n"), ..MessageTemplates::new() };
+        let error = message_templates.validate().unwrap_err();
+        assert!(error.contains("message header") && error.contains("{folder}"));
+    }
+
+    #[test]
+    fn file_templates_without_content_are_rejected() {
+        let message_templates = MessageTemplates{ file_part: String::from("File: {path} (part {part}/{parts})\n"), ..MessageTemplates::new() };
+        assert!(message_templates.validate().is_err_and(|error| error.contains("file part") && error.contains("{content}")));
+    }
+
+    #[test]
+    fn unbalanced_braces_are_rejected() {
+        for file_template in ["File: {path\n{content}", "File: path}\n{content}", "File: {pa th}\n{content}"] {
+            let message_templates = MessageTemplates{ file: String::from(file_template), ..MessageTemplates::new() };
+            assert!(message_templates.validate().is_err(), "{}", file_template);
+        }
+    }
+
+    #[test]
+    fn placeholders_are_replaced_and_doubled_braces_are_kept_literally() {
+        let filled_template = fill_template("{{{path}}} is {size} {{large}}{missing}", &[("path", "src/main.rs"), ("size", "2 KiB")]);
+        assert_eq!(filled_template, "{src/main.rs} is 2 KiB {large}");
+    }
+
+    #[test]
+    fn code_fences_are_longer_than_any_backtick_run_of_the_content() {
+        assert_eq!(code_fence("no backticks"), "```");
+        assert_eq!(code_fence("an `inline` span and a ``double`` one"), "```");
+        assert_eq!(code_fence("```rust\nfn main() {}\n```"), "````");
+        assert_eq!(code_fence("`````"), "``````");
+    }
+
+    #[test]
+    fn code_spans_keep_backticks_of_the_text_within_them() {
+        assert_eq!(code_span("src/main.rs"), "`src/main.rs`");
+        assert_eq!(code_span("a `b` c.md"), "``a `b` c.md``");
+        assert_eq!(code_span("`quoted`.txt"), "`` `quoted`.txt ``");
+    }
+
+    #[test]
+    fn markdown_templates_are_valid() {
+        assert!(MessageTemplates::for_output_format(OutputFormat::Markdown).validate().is_ok());
+    }
+}
@@ -0,0 +1,68 @@
+use serde::{Serialize, Deserialize};
+use std::borrow::Cow;
+
+/// How the files are laid out within the generated messages.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum OutputFormat {
+    /// Each file's path and size, followed by its content between "--- BEGINNING OF CONTENT ---" and "--- END OF CONTENT ---" lines.
+    #[default]
+    PlainText,
+    /// Each file's path and size as a heading, followed by its content in a fenced code block tagged with its language.
+    Markdown,
+    /// Each file as a `<document>` element whose attributes carry its path, size, status and line count, with its content in a CDATA section.
+    /// Files are always split at line boundaries, so that every message is well-formed on its own.
+    Xml
+}
+
+impl OutputFormat {
+    pub const ALL: [OutputFormat; 3] = [OutputFormat::PlainText, OutputFormat::Markdown, OutputFormat::Xml];
+
+    pub fn label(&self) -> &str {
+        match self {
+            OutputFormat::PlainText => "plain text",
+            OutputFormat::Markdown => "markdown",
+            OutputFormat::Xml => "XML"
+        }
+    }
+
+    /// Whether files must be kept whole or split at line boundaries, because cutting them anywhere would break the format's structure.
+    pub fn requires_line_boundaries(&self) -> bool {
+        *self == OutputFormat::Xml
+    }
+
+    /// Value of a placeholder that stands for a file attribute (such as its path), escaped as the format requires.
+    pub fn escape_attribute<'a>(&self, value: &'a str) -> Cow<'a, str> {
+        match self {
+            OutputFormat::Xml if value.contains(['&', '<', '>', '"', '\'']) => {
+                Cow::Owned(value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;").replace('\'', "&apos;"))
+            },
+            _ => Cow::Borrowed(value)
+        }
+    }
+
+    /// Value of the `{content}` placeholder; in XML, content is wrapped in a CDATA section, whose terminator is split across two sections where it occurs.
+    pub fn encode_content<'a>(&self, content: &'a str) -> Cow<'a, str> {
+        match self {
+            OutputFormat::Xml => Cow::Owned(format!("<![CDATA[{}]]>", content.replace("]]>", "]]]]><![CDATA[>"))),
+            _ => Cow::Borrowed(content)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xml_attributes_are_escaped() {
+        assert_eq!(OutputFormat::Xml.escape_attribute("src/a<b>&\"c'.rs"), "src/a&lt;b&gt;&amp;&quot;c&apos;.rs");
+        assert_eq!(OutputFormat::Markdown.escape_attribute("a<b>.rs"), "a<b>.rs");
+    }
+
+    #[test]
+    fn cdata_terminators_are_split_across_two_sections() {
+        assert_eq!(OutputFormat::Xml.encode_content("if a[b[0]]>c {}"), "<![CDATA[if a[b[0]]]]><![CDATA[>c {}]]>");
+        assert_eq!(OutputFormat::Xml.encode_content(""), "<![CDATA[]]>");
+        assert_eq!(OutputFormat::PlainText.encode_content("a]]>b"), "a]]>b");
+    }
+}
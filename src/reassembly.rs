@@ -0,0 +1,330 @@
+use crate::core_utils::FILE_CONTENT_PLACEHOLDERS;
+use crate::file_utils;
+use crate::integrity;
+use crate::redaction;
+
+use fancy_regex::Regex;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+const BEGINNING_OF_CONTENT_DELIMITER: &str = "--- BEGINNING OF CONTENT ---";
+const END_OF_CONTENT_DELIMITER: &str = "--- END OF CONTENT ---";
+
+/// A file rebuilt from a transcript.
+pub struct ReassembledFile {
+    /// Path relative to the folder, as stated in the messages.
+    pub path: String,
+    pub content: String
+}
+
+/// Files rebuilt from a transcript of FolderToAI messages (e.g. copied back out of a chat log), and what kept others from being rebuilt.
+///
+/// Messages are recognized by their headers ("Message 3/12:", with or without integrity markers) and files by the framing of the
+/// default plain-text templates ("File:" and "Size:" lines, then the content between delimiters); a transcript without message
+/// headers is read as a single message.
+pub struct Reassembly {
+    pub files: Vec<ReassembledFile>,
+    /// Paths of the files whose content was not in the messages (binary, too large or not loaded).
+    pub files_without_content: Vec<String>,
+    /// Missing messages and parts, checksum mismatches, contents cut short, etc.
+    pub problems: Vec<String>
+}
+
+/// A message found in a transcript.
+struct TranscriptMessage<'a> {
+    index: usize,
+    n_messages: usize,
+    session_id: Option<&'a str>,
+    /// What follows the header; without a checksum to tell where it ends, the blank line separating it from the next message is left out.
+    body: &'a str,
+    /// Whether the body matches the checksum in the header; true if there is none.
+    checksum_matches: bool
+}
+
+/// The parts of a file found so far, by index (starting at 1).
+struct FoundFile {
+    path: String,
+    n_parts: usize,
+    parts: BTreeMap<usize, String>
+}
+
+/// Longest prefix of `text` that ends at a line boundary (or at the end of the text) and has the given checksum, if any:
+/// besides the message, the text up to the next header may include line breaks separating messages or text added by a chat log.
+fn prefix_with_checksum<'a>(text: &'a str, checksum: &str) -> Option<&'a str> {
+    let mut prefix_ends: Vec<usize> = vec![text.len()];
+    for (line_break_position, _) in text.match_indices('\n').collect::<Vec<_>>().into_iter().rev() {
+        prefix_ends.extend([line_break_position + 1, line_break_position]);
+    }
+    prefix_ends.dedup();
+    prefix_ends.into_iter().map(|prefix_end| &text[..prefix_end]).find(|prefix| integrity::checksum_of_message_body(prefix) == checksum)
+}
+
+/// Messages found in `transcript` (whose line breaks are normalized), in order of appearance.
+fn find_messages(transcript: &str) -> Vec<TranscriptMessage<'_>> {
+    let pattern = Regex::new(r"(?m)^Message (\d+)/(\d+)(?: \[session ([0-9a-f]{12})(?:, checksum ([0-9a-f]{8}))?\])?:\n").unwrap();
+    let headers: Vec<_> = pattern.captures_iter(transcript).flatten().collect();
+
+    let header_start = |header_index: usize| headers.get(header_index).map_or(transcript.len(), |captures| captures.get(0).unwrap().start());
+
+    let mut messages = vec!();
+    let mut header_index = 0;
+    while header_index < headers.len() {
+        let captures = &headers[header_index];
+        let body_start = captures.get(0).unwrap().end();
+        let mut next_header_index = header_index + 1;
+        let text_up_to_next_header = &transcript[body_start..header_start(next_header_index)];
+
+        let (body, checksum_matches) = match captures.get(4) {
+            Some(checksum) => {
+                // headers quoted within the files (e.g. in a saved chat log) can be told apart from those of the following messages, as the
+                // checksum only matches once they are part of the body; the search stops at the next header of the same session with a checksum
+                let session_id = captures.get(3).map(|session_id| session_id.as_str());
+                let last_candidate_header_index = (header_index + 1..headers.len()).find(|&candidate_header_index| {
+                    let candidate_captures = &headers[candidate_header_index];
+                    candidate_captures.get(4).is_some() && candidate_captures.get(3).map(|session_id| session_id.as_str()) == session_id
+                }).unwrap_or(headers.len());
+                let body = (header_index + 1..=last_candidate_header_index).find_map(|candidate_header_index| {
+                    prefix_with_checksum(&transcript[body_start..header_start(candidate_header_index)], checksum.as_str()).map(|body| (candidate_header_index, body))
+                });
+                match body {
+                    Some((candidate_header_index, body)) => {
+                        next_header_index = candidate_header_index;
+                        (body, true)
+                    },
+                    None => (text_up_to_next_header, false)
+                }
+            },
+            // as when FolderToAI prints them, messages are taken to be separated by a blank line
+            None => (text_up_to_next_header.strip_suffix("\n\n").unwrap_or(text_up_to_next_header), true)
+        };
+        if let (Ok(index), Ok(n_messages)) = (captures[1].parse(), captures[2].parse()) {
+            messages.push(TranscriptMessage{ index, n_messages, session_id: captures.get(3).map(|session_id| session_id.as_str()), body, checksum_matches });
+        }
+        header_index = next_header_index;
+    }
+    messages
+}
+
+impl Reassembly {
+    /// Rebuilds the files from a transcript of FolderToAI messages.
+    ///
+    /// When messages appear several times, the first copy that matches its checksum is used; messages of other sessions than that of
+    /// the first message with an integrity marker are ignored.
+    pub fn from_transcript(transcript: &str) -> Self {
+        let transcript = transcript.replace("\r\n", "\n");
+        let mut problems: Vec<String> = vec!();
+
+        let messages = find_messages(&transcript);
+        // contiguous runs of messages, whose bodies are concatenated, since files may be cut anywhere within them
+        let mut runs_of_message_bodies: Vec<String> = vec!();
+        if messages.is_empty() {
+            runs_of_message_bodies.push(transcript.clone());
+        }
+        else {
+            let session_id = messages.iter().find_map(|message| message.session_id);
+            let n_messages = messages.iter().find(|message| message.session_id == session_id).map_or(0, |message| message.n_messages);
+
+            let mut selected_messages: BTreeMap<usize, &TranscriptMessage> = BTreeMap::new();
+            for message in &messages {
+                if message.session_id != session_id {
+                    problems.push(format!("message {}/{} belongs to another session ({}) and was ignored", message.index, message.n_messages, message.session_id.unwrap_or("without integrity markers")));
+                    continue;
+                }
+                if message.n_messages != n_messages || message.index == 0 || message.index > n_messages {
+                    problems.push(format!("message {}/{} does not belong with the other {} messages and was ignored", message.index, message.n_messages, n_messages));
+                    continue;
+                }
+                let replaces_selected_message = selected_messages.get(&message.index).is_none_or(|selected_message| !selected_message.checksum_matches && message.checksum_matches);
+                if replaces_selected_message {
+                    selected_messages.insert(message.index, message);
+                }
+            }
+
+            for message in selected_messages.values().filter(|message| !message.checksum_matches) {
+                problems.push(format!("message {}/{} does not match its checksum (it may have been truncated or altered)", message.index, n_messages));
+            }
+            let missing_message_indices: Vec<String> = (1..=n_messages).filter(|message_index| !selected_messages.contains_key(message_index)).map(|message_index| message_index.to_string()).collect();
+            if !missing_message_indices.is_empty() {
+                problems.push(format!("missing message(s): {}", missing_message_indices.join(", ")));
+            }
+
+            // the first message is the prologue, which only describes the files
+            let mut previous_message_index = 0;
+            for (message_index, message) in selected_messages.range(2..) {
+                if *message_index != previous_message_index + 1 || runs_of_message_bodies.is_empty() {
+                    runs_of_message_bodies.push(String::new());
+                }
+                *runs_of_message_bodies.last_mut().unwrap() += message.body;
+                previous_message_index = *message_index;
+            }
+        }
+
+        let mut found_files: Vec<FoundFile> = vec!();
+        let mut found_file_indices: HashMap<String, usize> = HashMap::new();
+        for message_bodies in &runs_of_message_bodies {
+            for (path, part_index, n_parts, content) in find_file_blocks(message_bodies, &mut problems) {
+                let found_file_index = *found_file_indices.entry(path.clone()).or_insert_with(|| {
+                    found_files.push(FoundFile{ path: path.clone(), n_parts, parts: BTreeMap::new() });
+                    found_files.len() - 1
+                });
+                found_files[found_file_index].parts.entry(part_index).or_insert(content);
+            }
+        }
+
+        let mut files: Vec<ReassembledFile> = vec!();
+        let mut files_without_content: Vec<String> = vec!();
+        if messages.is_empty() && found_files.is_empty() {
+            problems.push(String::from("no FolderToAI message was found in the transcript"));
+        }
+        for found_file in found_files {
+            let missing_part_indices: Vec<String> = (1..=found_file.n_parts).filter(|part_index| !found_file.parts.contains_key(part_index)).map(|part_index| part_index.to_string()).collect();
+            if !missing_part_indices.is_empty() {
+                problems.push(format!("\"{}\" was not rebuilt, as part(s) {} of {} are missing", found_file.path, missing_part_indices.join(", "), found_file.n_parts));
+                continue;
+            }
+            if !file_utils::is_safe_relative_path(&found_file.path) {
+                problems.push(format!("\"{}\" was not rebuilt, as its path leads outside of the folder", found_file.path));
+                continue;
+            }
+
+            let content: String = found_file.parts.into_values().collect();
+            if found_file.n_parts == 1 && FILE_CONTENT_PLACEHOLDERS.contains(&content.as_str()) {
+                files_without_content.push(found_file.path);
+                continue;
+            }
+            // the secrets were redacted from the messages, so writing the file as is would put placeholders in place of them
+            if redaction::contains_redaction_placeholders(&content) {
+                problems.push(format!("\"{}\" holds redacted secrets ([REDACTED:<kind>] placeholders), which must be restored by hand", found_file.path));
+            }
+            files.push(ReassembledFile{ path: found_file.path, content });
+        }
+
+        Reassembly{ files, files_without_content, problems }
+    }
+
+    /// Writes the rebuilt files to `output_dir`, at their paths relative to the folder.
+    pub fn write_to_directory(&self, output_dir: &str) -> Result<(), String> {
+        for file in &self.files {
+            // paths written on Windows use backslashes, which other platforms do not take as separators
+            let file_path = Path::new(output_dir).join(file.path.replace('\\', "/"));
+            if let Some(parent_directory_path) = file_path.parent() {
+                std::fs::create_dir_all(parent_directory_path).map_err(|error| format!("could not create directory \"{}\": {}", parent_directory_path.display(), error))?;
+            }
+            std::fs::write(&file_path, &file.content).map_err(|error| format!("could not write \"{}\": {}", file_path.display(), error))?;
+        }
+        Ok(())
+    }
+}
+
+/// (path, part index, number of parts, content) of each file block (or part of a file) in `text`, in order.
+fn find_file_blocks(text: &str, problems: &mut Vec<String>) -> Vec<(String, usize, usize, String)> {
+    let block_header_pattern = Regex::new(&format!(r"(?m)^File: (.*)\nSize: .*\n{}\n", fancy_regex::escape(BEGINNING_OF_CONTENT_DELIMITER))).unwrap();
+    let part_path_pattern = Regex::new(r"^(.*) \(part (\d+)/(\d+)\), lines \d+–\d+$").unwrap();
+    let end_of_content = format!("\n{}", END_OF_CONTENT_DELIMITER);
+
+    let mut file_blocks = vec!();
+    let mut position = 0;
+    while let Ok(Some(captures)) = block_header_pattern.captures_from_pos(text, position) {
+        let stated_path = &captures[1];
+        let (path, part_index, n_parts) = match part_path_pattern.captures(stated_path) {
+            Ok(Some(part_captures)) => (part_captures[1].to_string(), part_captures[2].parse().unwrap_or(0), part_captures[3].parse().unwrap_or(0)),
+            _ => (stated_path.to_string(), 1, 1)
+        };
+
+        // the content is followed by exactly one line break, then by the end delimiter on a line of its own; a delimiter quoted within the
+        // content is told apart from the actual one by what follows, which is the next file or the end of the text
+        let content_start = captures.get(0).unwrap().end();
+        let content_ends: Vec<usize> = text[content_start..].match_indices(&end_of_content).map(|(offset, _)| content_start + offset).filter(|&content_end| {
+            let delimiter_end = content_end + end_of_content.len();
+            delimiter_end == text.len() || text[delimiter_end..].starts_with('\n')
+        }).collect();
+        let content_end = content_ends.iter().copied().find(|&content_end| {
+            let text_after_delimiter = text[content_end + end_of_content.len()..].trim_start();
+            text_after_delimiter.is_empty() || text_after_delimiter.starts_with("File: ")
+        }).or(content_ends.first().copied());
+        let Some(content_end) = content_end else {
+            problems.push(format!("the content of \"{}\" is cut short (a message may be missing or truncated)", path));
+            break;
+        };
+
+        file_blocks.push((path, part_index, n_parts, text[content_start..content_end].to_string()));
+        position = content_end + end_of_content.len();
+    }
+    file_blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_info::FileInfo;
+    use crate::folder_info::FolderInfo;
+    use crate::message_renderer::MessageRenderer;
+
+    fn folder_info_with_files(files: &[(&str, &str)]) -> FolderInfo {
+        let file_infos: Vec<FileInfo> = files.iter().map(|(path, content)| {
+            let mut file_info = FileInfo::new(path.to_string(), content.len(), false);
+            file_info.file_content = Some(content.to_string());
+            file_info
+        }).collect();
+        let size_in_bytes = files.iter().map(|(_, content)| content.len()).sum();
+        FolderInfo{ folder_path: String::from("/tmp/folder"), file_infos, skipped_subtrees: vec!(), size_in_bytes }
+    }
+
+    fn reassembled_contents(reassembly: &Reassembly) -> Vec<(&str, &str)> {
+        reassembly.files.iter().map(|file| (file.path.as_str(), file.content.as_str())).collect()
+    }
+
+    #[test]
+    fn rebuilds_the_files_of_split_messages() {
+        let long_content: String = (1..=600).map(|line_number| format!("line {}\n", line_number)).collect();
+        let files = [("src/long.txt", long_content.as_str()), ("no_final_line_break.txt", "first\nlast"), ("empty.txt", "")];
+        let messages = MessageRenderer::new().maximum_amount_of_characters_per_message(1500).render(&folder_info_with_files(&files)).unwrap();
+        assert!(messages.len() > 3);
+
+        let reassembly = Reassembly::from_transcript(&messages.join("\n\n"));
+        assert!(reassembly.problems.is_empty(), "{:?}", reassembly.problems);
+        assert_eq!(reassembled_contents(&reassembly), files.to_vec());
+    }
+
+    #[test]
+    fn rebuilds_the_files_of_messages_split_anywhere() {
+        let long_content: String = (1..=600).map(|line_number| format!("line {}\n", line_number)).collect();
+        let files = [("long.txt", long_content.as_str()), ("short.txt", "short\n")];
+        let messages = MessageRenderer::new().maximum_amount_of_characters_per_message(1500).split_on_line_boundaries(false).render(&folder_info_with_files(&files)).unwrap();
+
+        let reassembly = Reassembly::from_transcript(&messages.join("\n\n").replace('\n', "\r\n"));
+        assert!(reassembly.problems.is_empty(), "{:?}", reassembly.problems);
+        assert_eq!(reassembled_contents(&reassembly), files.to_vec());
+    }
+
+    #[test]
+    fn keeps_message_headers_and_delimiters_quoted_within_files() {
+        let content = "Message 2/9 [session 0123456789ab, checksum 89abcdef]:\nMessage 3/9:\nFile: quoted.txt\n--- END OF CONTENT ---\nsee \"Message 1/2:\"\n";
+        let files = [("quoted.txt", content), ("other.txt", "other\n")];
+        let messages = MessageRenderer::new().render(&folder_info_with_files(&files)).unwrap();
+
+        let reassembly = Reassembly::from_transcript(&messages.join("\n\n"));
+        assert_eq!(reassembled_contents(&reassembly), files.to_vec());
+    }
+
+    #[test]
+    fn reports_missing_messages_and_parts() {
+        let long_content: String = (1..=600).map(|line_number| format!("line {}\n", line_number)).collect();
+        let files = [("long.txt", long_content.as_str())];
+        let mut messages = MessageRenderer::new().maximum_amount_of_characters_per_message(1500).render(&folder_info_with_files(&files)).unwrap();
+        messages.remove(2);
+
+        let reassembly = Reassembly::from_transcript(&messages.join("\n\n"));
+        assert!(reassembly.files.is_empty());
+        assert!(reassembly.problems.iter().any(|problem| problem == "missing message(s): 3"), "{:?}", reassembly.problems);
+        assert!(reassembly.problems.iter().any(|problem| problem.starts_with("\"long.txt\" was not rebuilt, as part(s) ")), "{:?}", reassembly.problems);
+    }
+
+    #[test]
+    fn reports_redacted_secrets_and_unsafe_paths() {
+        let transcript = "File: .env\nSize: 1 B\n--- BEGINNING OF CONTENT ---\nAPI_KEY=[REDACTED:secret variable]\n--- END OF CONTENT ---\n\nFile: ../outside.txt\nSize: 1 B\n--- BEGINNING OF CONTENT ---\nx\n--- END OF CONTENT ---\n";
+        let reassembly = Reassembly::from_transcript(transcript);
+        assert_eq!(reassembled_contents(&reassembly), vec![(".env", "API_KEY=[REDACTED:secret variable]")]);
+        assert_eq!(reassembly.problems.len(), 2, "{:?}", reassembly.problems);
+    }
+}
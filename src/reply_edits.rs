@@ -0,0 +1,754 @@
+use crate::file_info::FileInfo;
+use crate::file_utils;
+use crate::folder_info::FolderInfo;
+use crate::unified_diff::{self, LineOperation};
+
+use fancy_regex::Regex;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A line of a unified diff's hunk, without its prefix.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum DiffLine {
+    Context(String),
+    Removed(String),
+    Added(String)
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct DiffHunk {
+    /// Line number (starting at 1) where the hunk starts in the original file, as stated in its "@@" line; for hunks that only add lines,
+    /// the number of the line they are added after (0 at the start of the file).
+    pub original_start_line: usize,
+    pub lines: Vec<DiffLine>,
+    /// Whether the file ends without a line break after the hunk ("\ No newline at end of file" after an added or context line).
+    pub ends_without_newline: bool
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum FileEditKind {
+    /// The whole new content of the file.
+    WholeFile(String),
+    Diff(Vec<DiffHunk>),
+    Deletion
+}
+
+/// An edit to a file found in an assistant's reply.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct FileEdit {
+    /// Path relative to the folder, with "/" as separator.
+    pub path: String,
+    pub kind: FileEditKind
+}
+
+/// Edits found in an assistant's reply, in order of appearance: whole files given as in the messages (in any of the output formats)
+/// and unified diffs, fenced or not.
+pub fn parse_reply(reply: &str) -> Vec<FileEdit> {
+    let reply = reply.replace("\r\n", "\n");
+    let mut positioned_file_edits: Vec<(usize, FileEdit)> = vec!();
+    positioned_file_edits.extend(find_plain_text_file_blocks(&reply));
+    positioned_file_edits.extend(find_markdown_file_blocks(&reply));
+    positioned_file_edits.extend(find_xml_documents(&reply));
+    positioned_file_edits.extend(find_unified_diffs(&reply));
+    positioned_file_edits.sort_by_key(|(position, _)| *position);
+    positioned_file_edits.into_iter().map(|(_, file_edit)| file_edit).collect()
+}
+
+fn normalized_path(path: &str) -> String {
+    let path = path.trim().trim_matches(['`', '"', '\'']).replace('\\', "/");
+    path.strip_prefix("./").map(str::to_string).unwrap_or(path)
+}
+
+/// Position of the end of the first line of `text` from `position` on that is exactly `line`, and the position of its start.
+fn find_line(text: &str, position: usize, line: &str) -> Option<(usize, usize)> {
+    let mut line_start = position;
+    while line_start <= text.len() {
+        let line_end = text[line_start..].find('\n').map_or(text.len(), |offset| line_start + offset);
+        if &text[line_start..line_end] == line {
+            return Some((line_start, line_end));
+        }
+        line_start = line_end + 1;
+    }
+    None
+}
+
+/// "File: <path>" blocks, with or without a "Size:" line, whose content is between the delimiter lines.
+fn find_plain_text_file_blocks(reply: &str) -> Vec<(usize, FileEdit)> {
+    let block_header_pattern = Regex::new(r"(?m)^File: (.+)\n(?:Size: .*\n)?--- BEGINNING OF CONTENT ---\n").unwrap();
+    let mut file_edits = vec!();
+    let mut position = 0;
+    while let Ok(Some(captures)) = block_header_pattern.captures_from_pos(reply, position) {
+        let block_start = captures.get(0).unwrap().start();
+        let content_start = captures.get(0).unwrap().end();
+        let Some((delimiter_start, delimiter_end)) = find_line(reply, content_start, "--- END OF CONTENT ---") else {
+            break;
+        };
+        // the content is followed by exactly one line break
+        let content = &reply[content_start..delimiter_start.saturating_sub(1).max(content_start)];
+        file_edits.push((block_start, FileEdit{ path: normalized_path(&captures[1]), kind: FileEditKind::WholeFile(content.to_string()) }));
+        position = delimiter_end;
+    }
+    file_edits
+}
+
+/// Headings with a path in backticks followed by a fenced code block; blocks of diffs are left to [`find_unified_diffs`].
+fn find_markdown_file_blocks(reply: &str) -> Vec<(usize, FileEdit)> {
+    let block_header_pattern = Regex::new(r"(?m)^#{1,6} `([^`\n]+)`[^\n]*\n(?:[ \t]*\n)*(`{3,})[ \t]*([^\s`]*)[^\n]*\n").unwrap();
+    let mut file_edits = vec!();
+    let mut position = 0;
+    while let Ok(Some(captures)) = block_header_pattern.captures_from_pos(reply, position) {
+        let block_start = captures.get(0).unwrap().start();
+        let content_start = captures.get(0).unwrap().end();
+        let fence = &captures[2];
+        let Some((fence_start, fence_end)) = find_line(reply, content_start, fence) else {
+            break;
+        };
+        position = fence_end;
+        if matches!(&captures[3], "diff" | "patch") {
+            continue;
+        }
+        // the content includes the line break before the closing fence
+        file_edits.push((block_start, FileEdit{ path: normalized_path(&captures[1]), kind: FileEditKind::WholeFile(reply[content_start..fence_start].to_string()) }));
+    }
+    file_edits
+}
+
+fn unescape_xml(text: &str) -> String {
+    text.replace("&lt;", "<").replace("&gt;", ">").replace("&quot;", "\"").replace("&apos;", "'").replace("&amp;", "&")
+}
+
+/// `<document path="...">` elements, whose content is within a `<content>` element, in CDATA sections or escaped.
+fn find_xml_documents(reply: &str) -> Vec<(usize, FileEdit)> {
+    let document_start_pattern = Regex::new(r#"<document\s+path="([^"]*)"[^>]*>\s*<content>"#).unwrap();
+    let mut file_edits = vec!();
+    let mut position = 0;
+    'documents: while let Ok(Some(captures)) = document_start_pattern.captures_from_pos(reply, position) {
+        let document_start = captures.get(0).unwrap().start();
+        let mut remaining_content = &reply[captures.get(0).unwrap().end()..];
+        let mut content = String::new();
+        loop {
+            let content_end = remaining_content.find("</content>");
+            match remaining_content.find("<![CDATA[") {
+                Some(cdata_start) if content_end.is_none_or(|content_end| cdata_start < content_end) => {
+                    content += &unescape_xml(&remaining_content[..cdata_start]);
+                    let cdata = &remaining_content[cdata_start + "<![CDATA[".len()..];
+                    let Some(cdata_end) = cdata.find("]]>") else {
+                        break 'documents;
+                    };
+                    content += &cdata[..cdata_end];
+                    remaining_content = &cdata[cdata_end + "]]>".len()..];
+                },
+                _ => {
+                    let Some(content_end) = content_end else {
+                        break 'documents;
+                    };
+                    content += &unescape_xml(&remaining_content[..content_end]);
+                    remaining_content = &remaining_content[content_end..];
+                    break;
+                }
+            }
+        }
+        file_edits.push((document_start, FileEdit{ path: normalized_path(&unescape_xml(&captures[1])), kind: FileEditKind::WholeFile(content) }));
+        position = reply.len() - remaining_content.len();
+    }
+    file_edits
+}
+
+/// Path in a "---" or "+++" line of a unified diff, without the "a/" or "b/" prefix nor a timestamp; None for "/dev/null".
+fn diff_path(path: &str) -> Option<String> {
+    let path = path.split('\t').next().unwrap_or(path).trim();
+    if path == "/dev/null" {
+        return None;
+    }
+    let path = path.strip_prefix("a/").or_else(|| path.strip_prefix("b/")).unwrap_or(path);
+    Some(normalized_path(path))
+}
+
+/// Unified diffs: "---" and "+++" lines followed by hunks; a diff whose "+++" path is "/dev/null" deletes the file, and one whose "---" path is
+/// "/dev/null" gives the whole content of a new file.
+fn find_unified_diffs(reply: &str) -> Vec<(usize, FileEdit)> {
+    let hunk_header_pattern = Regex::new(r"^@@ -(\d+)(?:,(\d+))? \+(\d+)(?:,(\d+))? @@").unwrap();
+    let mut line_starts: Vec<usize> = vec![0];
+    line_starts.extend(reply.match_indices('\n').map(|(line_break_position, _)| line_break_position + 1));
+    let lines: Vec<&str> = reply.split('\n').collect();
+    let is_file_header = |line_index: usize| lines[line_index].starts_with("--- ") && lines.get(line_index + 1).is_some_and(|next_line| next_line.starts_with("+++ "));
+
+    let mut file_edits = vec!();
+    let mut line_index = 0;
+    while line_index < lines.len() {
+        if !is_file_header(line_index) {
+            line_index += 1;
+            continue;
+        }
+        let diff_start = line_starts[line_index];
+        let original_path = diff_path(&lines[line_index][4..]);
+        let new_path = diff_path(&lines[line_index + 1][4..]);
+        line_index += 2;
+
+        let mut hunks: Vec<DiffHunk> = vec!();
+        while let Some(captures) = lines.get(line_index).and_then(|line| hunk_header_pattern.captures(line).ok().flatten()) {
+            line_index += 1;
+            let original_start_line: usize = captures[1].parse().unwrap_or(0);
+            let mut n_original_lines_left: usize = captures.get(2).map_or(Ok(1), |n_lines| n_lines.as_str().parse()).unwrap_or(0);
+            let mut n_new_lines_left: usize = captures.get(4).map_or(Ok(1), |n_lines| n_lines.as_str().parse()).unwrap_or(0);
+
+            let mut hunk = DiffHunk{ original_start_line, lines: vec!(), ends_without_newline: false };
+            // the counts tell where the hunk ends; if they are wrong (as they often are in written diffs), it ends with the first line that is not part of a hunk
+            let mut n_blank_lines_at_end = 0;
+            while let Some(line) = lines.get(line_index) {
+                if (n_original_lines_left == 0 && n_new_lines_left == 0 && !line.starts_with('\\')) || line.starts_with("@@") || is_file_header(line_index) {
+                    break;
+                }
+                match line.chars().next() {
+                    Some(' ') | None => {
+                        hunk.lines.push(DiffLine::Context(line.get(1..).unwrap_or_default().to_string()));
+                        n_blank_lines_at_end = if line.is_empty() { n_blank_lines_at_end + 1 } else { 0 };
+                        n_original_lines_left = n_original_lines_left.saturating_sub(1);
+                        n_new_lines_left = n_new_lines_left.saturating_sub(1);
+                    },
+                    Some('-') => {
+                        hunk.lines.push(DiffLine::Removed(line[1..].to_string()));
+                        n_blank_lines_at_end = 0;
+                        n_original_lines_left = n_original_lines_left.saturating_sub(1);
+                    },
+                    Some('+') => {
+                        hunk.lines.push(DiffLine::Added(line[1..].to_string()));
+                        n_blank_lines_at_end = 0;
+                        n_new_lines_left = n_new_lines_left.saturating_sub(1);
+                    },
+                    Some('\\') => {
+                        if matches!(hunk.lines.last(), Some(DiffLine::Added(_) | DiffLine::Context(_))) {
+                            hunk.ends_without_newline = true;
+                        }
+                    },
+                    _ => break
+                }
+                line_index += 1;
+            }
+            if n_original_lines_left > 0 || n_new_lines_left > 0 {
+                // blank lines that were taken as context are likely to separate the diff from what follows it
+                hunk.lines.truncate(hunk.lines.len() - n_blank_lines_at_end);
+            }
+            hunks.push(hunk);
+        }
+
+        let file_edit = match (original_path, new_path) {
+            (Some(original_path), None) => FileEdit{ path: original_path, kind: FileEditKind::Deletion },
+            // a diff from "/dev/null" gives the whole content of a new file
+            (None, Some(new_path)) => match apply_hunks("", &hunks) {
+                Ok(content) => FileEdit{ path: new_path, kind: FileEditKind::WholeFile(content) },
+                Err(_) => continue
+            },
+            (Some(_), Some(new_path)) if !hunks.is_empty() => FileEdit{ path: new_path, kind: FileEditKind::Diff(hunks) },
+            _ => continue
+        };
+        file_edits.push((diff_start, file_edit));
+    }
+    file_edits
+}
+
+/// Position (line index) at which the hunk's original lines are found in `lines`, from `minimum_position` on, the nearest to `expected_position`;
+/// lines are compared regardless of trailing whitespace, which is often lost when copying.
+fn find_hunk_position(lines: &[&str], hunk_original_lines: &[&str], minimum_position: usize, expected_position: usize) -> Option<usize> {
+    if hunk_original_lines.len() > lines.len() {
+        return None;
+    }
+    let last_position = lines.len() - hunk_original_lines.len();
+    let expected_position = expected_position.clamp(minimum_position, last_position.max(minimum_position));
+    let matches_at = |position: usize| position >= minimum_position && position <= last_position && hunk_original_lines.iter().zip(&lines[position..]).all(|(hunk_line, line)| hunk_line.trim_end() == line.trim_end());
+
+    for distance in 0..=lines.len() {
+        if matches_at(expected_position + distance) {
+            return Some(expected_position + distance);
+        }
+        if distance <= expected_position && matches_at(expected_position - distance) {
+            return Some(expected_position - distance);
+        }
+    }
+    None
+}
+
+/// `content` (with "\n" line breaks) with the hunks applied in order.
+fn apply_hunks(content: &str, hunks: &[DiffHunk]) -> Result<String, String> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut ends_with_newline = content.is_empty() || content.ends_with('\n');
+    let mut new_lines: Vec<&str> = vec!();
+    let mut position = 0;
+
+    for (hunk_index, hunk) in hunks.iter().enumerate() {
+        let hunk_original_lines: Vec<&str> = hunk.lines.iter().filter_map(|diff_line| match diff_line {
+            DiffLine::Context(line) | DiffLine::Removed(line) => Some(line.as_str()),
+            DiffLine::Added(_) => None
+        }).collect();
+        let expected_position = if hunk_original_lines.is_empty() { hunk.original_start_line } else { hunk.original_start_line.saturating_sub(1) };
+        let Some(hunk_position) = find_hunk_position(&lines, &hunk_original_lines, position, expected_position) else {
+            return Err(format!("hunk {} (at line {}) does not match the file", hunk_index + 1, hunk.original_start_line));
+        };
+
+        new_lines.extend(&lines[position..hunk_position]);
+        position = hunk_position;
+        for diff_line in &hunk.lines {
+            match diff_line {
+                DiffLine::Context(_) => {
+                    new_lines.push(lines[position]);
+                    position += 1;
+                },
+                DiffLine::Removed(_) => position += 1,
+                DiffLine::Added(line) => new_lines.push(line)
+            }
+        }
+
+        if position == lines.len() {
+            if hunk.ends_without_newline {
+                ends_with_newline = false;
+            }
+            else if matches!(hunk.lines.last(), Some(DiffLine::Added(_))) {
+                ends_with_newline = true;
+            }
+        }
+    }
+    new_lines.extend(&lines[position..]);
+
+    let mut new_content = new_lines.join("\n");
+    if ends_with_newline && !new_lines.is_empty() {
+        new_content.push('\n');
+    }
+    Ok(new_content)
+}
+
+/// `content` (with "\n" line breaks) with the line breaks of `original_content`, which may be "\r\n".
+fn with_line_breaks_of(original_content: Option<&str>, content: &str) -> String {
+    if original_content.is_some_and(|original_content| original_content.contains("\r\n")) {
+        content.replace('\n', "\r\n")
+    }
+    else {
+        content.to_string()
+    }
+}
+
+/// Number of lines (added, removed) between two contents; if they have too many lines to compare, all the lines in between
+/// their common beginning and end count as changed.
+fn changed_line_counts(original_content: &str, new_content: &str) -> (usize, usize) {
+    let original_lines: Vec<&str> = original_content.lines().collect();
+    let new_lines: Vec<&str> = new_content.lines().collect();
+    match unified_diff::line_operations(&original_lines, &new_lines) {
+        Some(line_operations) => (
+            line_operations.iter().filter(|line_operation| **line_operation == LineOperation::Added).count(),
+            line_operations.iter().filter(|line_operation| **line_operation == LineOperation::Removed).count()
+        ),
+        None => {
+            let n_common_first_lines = original_lines.iter().zip(&new_lines).take_while(|(original_line, new_line)| original_line == new_line).count();
+            let n_common_last_lines = original_lines[n_common_first_lines..].iter().rev().zip(new_lines[n_common_first_lines..].iter().rev()).take_while(|(original_line, new_line)| original_line == new_line).count();
+            (new_lines.len() - n_common_first_lines - n_common_last_lines, original_lines.len() - n_common_first_lines - n_common_last_lines)
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FileChangeKind {
+    Creation,
+    Modification,
+    Deletion,
+    Unchanged
+}
+
+/// The change that a reply's edits make to a file, worked out against the file's content at the time of the scan.
+#[derive(Clone, Debug)]
+pub struct PlannedFileChange {
+    /// Path relative to the folder.
+    pub path: String,
+    /// Content at the time of the scan; None if the file did not exist.
+    pub original_content: Option<String>,
+    /// None if the file is deleted.
+    pub new_content: Option<String>,
+    /// Why the change cannot be applied, if it cannot (e.g. the file changed since the scan, or a hunk does not match it).
+    pub conflict: Option<String>
+}
+
+impl PlannedFileChange {
+    pub fn kind(&self) -> FileChangeKind {
+        match (&self.original_content, &self.new_content) {
+            (None, Some(_)) => FileChangeKind::Creation,
+            (Some(_), None) => FileChangeKind::Deletion,
+            (Some(original_content), Some(new_content)) if original_content != new_content => FileChangeKind::Modification,
+            _ => FileChangeKind::Unchanged
+        }
+    }
+
+    /// Whether applying the change would write or delete the file.
+    pub fn is_applicable(&self) -> bool {
+        self.conflict.is_none() && self.kind() != FileChangeKind::Unchanged
+    }
+
+    /// What the change does, e.g. "modified (+3 -1 lines)", or why it cannot be applied.
+    pub fn summary(&self) -> String {
+        if let Some(conflict) = &self.conflict {
+            return format!("conflict: {}", conflict);
+        }
+        match (self.kind(), &self.original_content, &self.new_content) {
+            (FileChangeKind::Creation, _, Some(new_content)) => format!("created ({} lines)", new_content.lines().count()),
+            (FileChangeKind::Modification, Some(original_content), Some(new_content)) => {
+                match changed_line_counts(original_content, new_content) {
+                    (0, 0) => String::from("modified (line breaks only)"),
+                    (n_added_lines, n_removed_lines) => format!("modified (+{} -{} lines)", n_added_lines, n_removed_lines)
+                }
+            },
+            (FileChangeKind::Deletion, _, _) => String::from("deleted"),
+            _ => String::from("unchanged")
+        }
+    }
+}
+
+/// Content of a file as loaded by the scan, or None if it does not exist; contents that are not valid UTF-8 are read lossily, as when scanning.
+fn current_file_content(file_path: &Path) -> Option<String> {
+    std::fs::read(file_path).ok().map(|file_bytes| String::from_utf8_lossy(&file_bytes).into_owned())
+}
+
+/// Change to a file before any edit, with a conflict if it cannot be edited: it is outside of the folder, it was not loaded by the scan,
+/// or it changed since then.
+fn unedited_file_change(folder_info: &FolderInfo, scanned_file_info: Option<&FileInfo>, path: &str) -> PlannedFileChange {
+    let mut planned_file_change = PlannedFileChange{ path: path.to_string(), original_content: None, new_content: None, conflict: None };
+    if !file_utils::is_safe_relative_path(path) {
+        planned_file_change.conflict = Some(String::from("its path leads outside of the folder"));
+        return planned_file_change;
+    }
+    let current_content = current_file_content(&Path::new(&folder_info.folder_path).join(path));
+
+    let Some(scanned_file_info) = scanned_file_info else {
+        if current_content.is_some() {
+            let is_within_skipped_subtree = folder_info.skipped_subtrees.iter().any(|skipped_subtree| Path::new(path).starts_with(skipped_subtree.path.replace('\\', "/")));
+            planned_file_change.conflict = Some(String::from(if is_within_skipped_subtree {
+                "the file is within an ignored subfolder, which the scan skipped"
+            }
+            else {
+                "the file was created since the folder was scanned"
+            }));
+        }
+        return planned_file_change;
+    };
+    planned_file_change.path = scanned_file_info.filepath.clone();
+    let not_loaded_reason = if scanned_file_info.is_withheld_as_sensitive() {
+        "the file was withheld as sensitive when the folder was scanned"
+    }
+    else if scanned_file_info.should_be_ignored {
+        "the file is ignored, so it was not loaded when the folder was scanned"
+    }
+    else if scanned_file_info.is_binary {
+        "the file is binary, so it was not loaded when the folder was scanned"
+    }
+    else if scanned_file_info.file_too_large {
+        "the file is too large, so it was not loaded when the folder was scanned"
+    }
+    else {
+        "the file could not be read when the folder was scanned"
+    };
+    match (&scanned_file_info.file_content, scanned_file_info.should_be_ignored) {
+        (Some(scanned_content), false) => {
+            planned_file_change.original_content = Some(scanned_content.clone());
+            planned_file_change.new_content = Some(scanned_content.clone());
+            if current_content.is_none() {
+                planned_file_change.conflict = Some(String::from("the file was deleted since the folder was scanned"));
+            }
+            // the edits were made to a content with placeholders in place of the secrets, which must not end up in the file
+            else if !scanned_file_info.redactions.is_empty() {
+                planned_file_change.conflict = Some(String::from("the file was shared with secrets redacted, so it must be changed by hand"));
+            }
+            else if current_content.as_ref() != Some(scanned_content) {
+                planned_file_change.conflict = Some(String::from("the file was changed since the folder was scanned"));
+            }
+        },
+        _ => planned_file_change.conflict = Some(String::from(not_loaded_reason))
+    }
+    planned_file_change
+}
+
+/// Works out the changes that the edits make to the files of the scanned folder; the edits to a file are applied in order, and an edit
+/// that cannot be applied (or a file that changed since the scan) is reported as a conflict for the whole file.
+pub fn plan_file_changes(folder_info: &FolderInfo, file_edits: &[FileEdit]) -> Vec<PlannedFileChange> {
+    let scanned_file_infos: HashMap<String, &FileInfo> = folder_info.file_infos.iter().map(|file_info| (file_info.filepath.replace('\\', "/"), file_info)).collect();
+    let mut planned_file_changes: Vec<PlannedFileChange> = vec!();
+    let mut planned_file_change_indices: HashMap<&str, usize> = HashMap::new();
+
+    for file_edit in file_edits {
+        let planned_file_change_index = *planned_file_change_indices.entry(&file_edit.path).or_insert_with(|| {
+            planned_file_changes.push(unedited_file_change(folder_info, scanned_file_infos.get(&file_edit.path).copied(), &file_edit.path));
+            planned_file_changes.len() - 1
+        });
+        let planned_file_change = &mut planned_file_changes[planned_file_change_index];
+        if planned_file_change.conflict.is_some() {
+            continue;
+        }
+
+        let original_content = planned_file_change.original_content.as_deref();
+        let edited_content = match &file_edit.kind {
+            FileEditKind::WholeFile(content) => Ok(Some(with_line_breaks_of(original_content, content))),
+            FileEditKind::Deletion => match planned_file_change.new_content {
+                Some(_) => Ok(None),
+                None => Err(String::from("the file to delete does not exist"))
+            },
+            FileEditKind::Diff(hunks) => {
+                let content = planned_file_change.new_content.as_deref().unwrap_or_default().replace("\r\n", "\n");
+                apply_hunks(&content, hunks).map(|new_content| Some(with_line_breaks_of(original_content, &new_content)))
+            }
+        };
+        match edited_content {
+            Ok(new_content) => planned_file_change.new_content = new_content,
+            Err(conflict) => planned_file_change.conflict = Some(conflict)
+        }
+    }
+
+    planned_file_changes
+}
+
+/// Outcome of applying a planned change.
+pub struct AppliedFileChange {
+    pub path: String,
+    pub result: Result<(), String>
+}
+
+/// Outcome of applying a reply's planned changes.
+pub struct ReplyApplication {
+    /// Directory where the files were backed up before being overwritten or deleted, if any was.
+    pub backup_directory_path: Option<String>,
+    pub applied_file_changes: Vec<AppliedFileChange>
+}
+
+impl ReplyApplication {
+    pub fn n_failed_file_changes(&self) -> usize {
+        self.applied_file_changes.iter().filter(|applied_file_change| applied_file_change.result.is_err()).count()
+    }
+}
+
+fn apply_file_change(folder_path: &str, planned_file_change: &PlannedFileChange, backup_directory_path: Option<&Path>) -> Result<(), String> {
+    let relative_path = planned_file_change.path.replace('\\', "/");
+    let file_path = Path::new(folder_path).join(&relative_path);
+    if current_file_content(&file_path) != planned_file_change.original_content {
+        return Err(String::from("the file was changed since the changes were planned"));
+    }
+
+    if let (Some(backup_directory_path), Some(_)) = (backup_directory_path, &planned_file_change.original_content) {
+        let backup_file_path = backup_directory_path.join(&relative_path);
+        if let Some(parent_directory_path) = backup_file_path.parent() {
+            std::fs::create_dir_all(parent_directory_path).map_err(|error| format!("could not create the backup directory \"{}\": {}", parent_directory_path.display(), error))?;
+        }
+        std::fs::copy(&file_path, &backup_file_path).map_err(|error| format!("could not back the file up to \"{}\": {}", backup_file_path.display(), error))?;
+    }
+
+    match &planned_file_change.new_content {
+        Some(new_content) => {
+            if let Some(parent_directory_path) = file_path.parent() {
+                std::fs::create_dir_all(parent_directory_path).map_err(|error| format!("could not create directory \"{}\": {}", parent_directory_path.display(), error))?;
+            }
+            std::fs::write(&file_path, new_content).map_err(|error| format!("could not write the file: {}", error))
+        },
+        None => std::fs::remove_file(&file_path).map_err(|error| format!("could not delete the file: {}", error))
+    }
+}
+
+/// Path of a new subdirectory of the backups directory for the files of the folder, named after the folder and the time, and followed by
+/// a counter if there already is a backup from the same second.
+fn new_backup_directory_path(backups_directory_path: &str, folder_path: &str) -> PathBuf {
+    let folder_name = Path::new(folder_path).file_name().map_or_else(|| String::from("folder"), |folder_name| folder_name.to_string_lossy().to_string());
+    let seconds_since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_secs());
+    let backup_directory_name = format!("{}-{}", folder_name, seconds_since_epoch);
+    let mut backup_directory_path = Path::new(backups_directory_path).join(&backup_directory_name);
+    let mut n_backups_from_the_same_second = 1;
+    while backup_directory_path.exists() {
+        n_backups_from_the_same_second += 1;
+        backup_directory_path = Path::new(backups_directory_path).join(format!("{}-{}", backup_directory_name, n_backups_from_the_same_second));
+    }
+    backup_directory_path
+}
+
+/// Writes (or deletes) the files of the applicable changes, after checking that they did not change since the changes were planned;
+/// with a backups directory, the files are first copied into a new subdirectory of it, named after the folder and the time.
+pub fn apply_file_changes(folder_path: &str, planned_file_changes: &[PlannedFileChange], backups_directory_path: Option<&str>) -> ReplyApplication {
+    let backup_directory_path: Option<PathBuf> = backups_directory_path.map(|backups_directory_path| new_backup_directory_path(backups_directory_path, folder_path));
+
+    let applied_file_changes: Vec<AppliedFileChange> = planned_file_changes.iter().filter(|planned_file_change| planned_file_change.is_applicable()).map(|planned_file_change| AppliedFileChange{
+        path: planned_file_change.path.clone(),
+        result: apply_file_change(folder_path, planned_file_change, backup_directory_path.as_deref())
+    }).collect();
+
+    let files_were_backed_up = backup_directory_path.as_ref().is_some_and(|backup_directory_path| backup_directory_path.exists());
+    ReplyApplication{ backup_directory_path: backup_directory_path.filter(|_| files_were_backed_up).map(|backup_directory_path| backup_directory_path.to_string_lossy().to_string()), applied_file_changes }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::skipped_subtree::SkippedSubtree;
+    use crate::test_utils::TestDirectory;
+
+    fn whole_file(path: &str, content: &str) -> FileEdit {
+        FileEdit{ path: path.to_string(), kind: FileEditKind::WholeFile(content.to_string()) }
+    }
+
+    fn hunks_of(diff: &str) -> Vec<DiffHunk> {
+        match parse_reply(diff).into_iter().next().map(|file_edit| file_edit.kind) {
+            Some(FileEditKind::Diff(hunks)) => hunks,
+            kind => panic!("expected a diff, found {:?}", kind)
+        }
+    }
+
+    /// A test directory with the given files, and its scan, in which each file has the content it has on disk.
+    struct TestFolder {
+        test_directory: TestDirectory,
+        folder_info: FolderInfo
+    }
+
+    impl TestFolder {
+        fn new(name: &str, files: &[(&str, &str)]) -> Self {
+            let test_directory = TestDirectory::new(&format!("reply_edits_{}", name), files);
+            let file_infos = files.iter().map(|(path, content)| {
+                let mut file_info = FileInfo::new(path.to_string(), content.len(), false);
+                file_info.file_content = Some(content.to_string());
+                file_info
+            }).collect();
+            let folder_info = FolderInfo{ folder_path: test_directory.path().to_string_lossy().into_owned(), file_infos, skipped_subtrees: vec!(), size_in_bytes: 0 };
+            TestFolder{ test_directory, folder_info }
+        }
+
+        fn path(&self, relative_path: &str) -> PathBuf {
+            self.test_directory.join(relative_path)
+        }
+    }
+
+    #[test]
+    fn parses_whole_files_in_every_output_format() {
+        let reply = concat!(
+            "Here are the files.\n\n",
+            "File: src/a.txt\nSize: 4 bytes\n--- BEGINNING OF CONTENT ---\na\n--- END OF CONTENT ---\n\n",
+            "### `./src/b.rs` (updated)\n\n```rust\nfn b() {}\n```\n\n",
+            "<document path=\"src\\c.xml\"><content>&lt;c&gt; &amp; d\n</content></document>\n"
+        );
+        assert_eq!(parse_reply(&reply.replace('\n', "\r\n")), vec![
+            whole_file("src/a.txt", "a"),
+            whole_file("src/b.rs", "fn b() {}\n"),
+            whole_file("src/c.xml", "<c> & d\n")
+        ]);
+    }
+
+    #[test]
+    fn joins_cdata_sections_split_around_their_end_marker() {
+        let reply = "<document path=\"a.xml\"><content><![CDATA[x = y[z[0]]]]><![CDATA[> 1;\n]]></content></document>";
+        assert_eq!(parse_reply(reply), vec![whole_file("a.xml", "x = y[z[0]]> 1;\n")]);
+
+        let content = "]]>a]]>]]>\n";
+        let reply = format!("<document path=\"b.xml\">\n<content>{}</content>\n</document>", crate::output_format::OutputFormat::Xml.encode_content(content));
+        assert_eq!(parse_reply(&reply), vec![whole_file("b.xml", content)]);
+    }
+
+    #[test]
+    fn leaves_diff_blocks_to_the_diff_parser() {
+        let reply = "### `a.txt`\n```diff\n--- a/a.txt\n+++ b/a.txt\n@@ -1 +1 @@\n-a\n+b\n```\n";
+        assert_eq!(parse_reply(reply), vec![FileEdit{ path: String::from("a.txt"), kind: FileEditKind::Diff(vec![DiffHunk{
+            original_start_line: 1,
+            lines: vec![DiffLine::Removed(String::from("a")), DiffLine::Added(String::from("b"))],
+            ends_without_newline: false
+        }]) }]);
+    }
+
+    #[test]
+    fn parses_creations_and_deletions_as_diffs() {
+        let reply = "--- /dev/null\n+++ b/new.txt\n@@ -0,0 +1,2 @@\n+first\n+second\n--- a/old.txt\n+++ /dev/null\n@@ -1 +0,0 @@\n-old\n";
+        assert_eq!(parse_reply(reply), vec![
+            whole_file("new.txt", "first\nsecond\n"),
+            FileEdit{ path: String::from("old.txt"), kind: FileEditKind::Deletion }
+        ]);
+    }
+
+    #[test]
+    fn applies_hunks_with_wrong_counts_and_shifted_lines() {
+        let hunks = hunks_of("--- a/a.txt\n+++ b/a.txt\n@@ -10,9 +10,9 @@\n c\n-d\n+D\n e\n\nSome explanation.\n");
+        assert_eq!(apply_hunks("a\nb\nc\nd\ne\nf\n", &hunks), Ok(String::from("a\nb\nc\nD\ne\nf\n")));
+        assert!(apply_hunks("a\nb\n", &hunks).is_err());
+    }
+
+    #[test]
+    fn applies_hunks_at_the_end_of_files_without_newline() {
+        let removing_final_newline = hunks_of("--- a/a.txt\n+++ b/a.txt\n@@ -1,2 +1,2 @@\n a\n-b\n+c\n\\ No newline at end of file\n");
+        assert_eq!(apply_hunks("a\nb\n", &removing_final_newline), Ok(String::from("a\nc")));
+
+        let adding_final_newline = hunks_of("--- a/a.txt\n+++ b/a.txt\n@@ -1,2 +1,2 @@\n a\n-b\n\\ No newline at end of file\n+b\n");
+        assert_eq!(apply_hunks("a\nb", &adding_final_newline), Ok(String::from("a\nb\n")));
+
+        let keeping_missing_newline = hunks_of("--- a/a.txt\n+++ b/a.txt\n@@ -1,2 +1,2 @@\n-a\n+A\n b\n\\ No newline at end of file\n");
+        assert_eq!(apply_hunks("a\nb", &keeping_missing_newline), Ok(String::from("A\nb")));
+    }
+
+    #[test]
+    fn plans_changes_keeping_line_breaks() {
+        let test_folder = TestFolder::new("plans", &[("a.txt", "a\r\nb\r\n")]);
+        let file_edits = parse_reply("--- a/a.txt\n+++ b/a.txt\n@@ -1,2 +1,2 @@\n a\n-b\n+c\n\nFile: new.txt\n--- BEGINNING OF CONTENT ---\nnew\n--- END OF CONTENT ---\n");
+        let planned_file_changes = plan_file_changes(&test_folder.folder_info, &file_edits);
+        assert_eq!(planned_file_changes.len(), 2);
+        assert_eq!(planned_file_changes[0].new_content.as_deref(), Some("a\r\nc\r\n"));
+        assert_eq!(planned_file_changes[0].summary(), "modified (+1 -1 lines)");
+        assert_eq!(planned_file_changes[1].kind(), FileChangeKind::Creation);
+    }
+
+    #[test]
+    fn reports_why_files_cannot_be_edited() {
+        let mut test_folder = TestFolder::new("conflicts", &[("changed.txt", "a\n"), ("redacted.env", "KEY=[REDACTED:secret variable]\n"), ("secret.pem", "key\n"), ("ignored.log", "log\n"), ("node_modules/m.js", "m\n")]);
+        std::fs::write(test_folder.path("changed.txt"), "b\n").unwrap();
+        std::fs::write(test_folder.path("created.txt"), "c\n").unwrap();
+        std::fs::write(test_folder.path("redacted.env"), "KEY=value\n").unwrap();
+        let file_infos = &mut test_folder.folder_info.file_infos;
+        file_infos[1].redactions.push(crate::redaction::Redaction{ kind: "secret variable", line_number: 1 });
+        file_infos[2].is_sensitive = true;
+        file_infos[2].should_be_ignored = true;
+        file_infos[2].file_content = None;
+        file_infos[3].should_be_ignored = true;
+        file_infos[3].file_content = None;
+        file_infos.pop();
+        test_folder.folder_info.skipped_subtrees.push(SkippedSubtree::new(String::from("node_modules")));
+
+        let paths = ["changed.txt", "redacted.env", "secret.pem", "ignored.log", "node_modules/m.js", "created.txt", "../outside.txt"];
+        let file_edits: Vec<FileEdit> = paths.iter().map(|path| whole_file(path, "x\n")).collect();
+        let conflicts: Vec<String> = plan_file_changes(&test_folder.folder_info, &file_edits).into_iter().map(|planned_file_change| planned_file_change.conflict.unwrap_or_default()).collect();
+        assert_eq!(conflicts, vec![
+            "the file was changed since the folder was scanned",
+            "the file was shared with secrets redacted, so it must be changed by hand",
+            "the file was withheld as sensitive when the folder was scanned",
+            "the file is ignored, so it was not loaded when the folder was scanned",
+            "the file is within an ignored subfolder, which the scan skipped",
+            "the file was created since the folder was scanned",
+            "its path leads outside of the folder"
+        ]);
+    }
+
+    #[test]
+    fn applies_changes_after_backing_files_up() {
+        let test_folder = TestFolder::new("applies", &[("a.txt", "a\n"), ("old.txt", "old\n")]);
+        let backups_directory_path = test_folder.path("backups").to_string_lossy().into_owned();
+        let file_edits = vec![whole_file("a.txt", "b\n"), FileEdit{ path: String::from("old.txt"), kind: FileEditKind::Deletion }, whole_file("sub/new.txt", "new\n")];
+        let planned_file_changes = plan_file_changes(&test_folder.folder_info, &file_edits);
+
+        let reply_application = apply_file_changes(&test_folder.folder_info.folder_path, &planned_file_changes, Some(&backups_directory_path));
+        assert_eq!(reply_application.n_failed_file_changes(), 0);
+        assert_eq!(std::fs::read_to_string(test_folder.path("a.txt")).unwrap(), "b\n");
+        assert!(!test_folder.path("old.txt").exists());
+        assert_eq!(std::fs::read_to_string(test_folder.path("sub/new.txt")).unwrap(), "new\n");
+        let backup_directory_path = PathBuf::from(reply_application.backup_directory_path.unwrap());
+        assert_eq!(std::fs::read_to_string(backup_directory_path.join("a.txt")).unwrap(), "a\n");
+        assert_eq!(std::fs::read_to_string(backup_directory_path.join("old.txt")).unwrap(), "old\n");
+
+        // applying the same changes again finds the files changed since they were planned
+        let reply_application = apply_file_changes(&test_folder.folder_info.folder_path, &planned_file_changes, None);
+        assert_eq!(reply_application.n_failed_file_changes(), 3);
+    }
+
+    #[test]
+    fn backups_from_the_same_second_get_directories_of_their_own() {
+        let test_folder = TestFolder::new("backups_from_the_same_second", &[]);
+        let backups_directory_path = test_folder.path("backups").to_string_lossy().into_owned();
+
+        let first_backup_directory_path = new_backup_directory_path(&backups_directory_path, &test_folder.folder_info.folder_path);
+        std::fs::create_dir_all(&first_backup_directory_path).unwrap();
+        let second_backup_directory_path = new_backup_directory_path(&backups_directory_path, &test_folder.folder_info.folder_path);
+        assert_ne!(first_backup_directory_path, second_backup_directory_path);
+        assert!(!second_backup_directory_path.exists());
+    }
+}
@@ -0,0 +1,199 @@
+use crate::folder_info::FolderInfo;
+use crate::tree_overview;
+
+use serde::Serialize;
+
+/// Machine-readable layout of a [`ScanExport`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ExportFormat {
+    /// A single JSON document with the folder, its files and the messages.
+    Json,
+    /// One JSON record per line: the folder first, then each file, then each message, told apart by their "type" field.
+    Jsonl
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 2] = [ExportFormat::Json, ExportFormat::Jsonl];
+
+    pub fn label(&self) -> &str {
+        match self {
+            ExportFormat::Json => "JSON",
+            ExportFormat::Jsonl => "JSONL"
+        }
+    }
+
+    pub fn file_extension(&self) -> &str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Jsonl => "jsonl"
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct FolderRecord<'a> {
+    folder: &'a str,
+    size_in_bytes: usize,
+    n_files: usize,
+    n_messages: usize,
+    message_size_unit: &'a str
+}
+
+#[derive(Serialize)]
+struct FileRecord<'a> {
+    path: &'a str,
+    size_in_bytes: usize,
+    is_binary: bool,
+    file_too_large: bool,
+    should_be_ignored: bool,
+    is_sensitive: bool,
+    /// loaded, binary, too large, ignored, withheld: sensitive or failed
+    status: &'static str,
+    content: Option<&'a str>,
+    message_indices: &'a [usize]
+}
+
+#[derive(Serialize)]
+struct MessageRecord<'a> {
+    index: usize,
+    size: usize,
+    content: &'a str
+}
+
+#[derive(Serialize)]
+struct FolderDocument<'a> {
+    #[serde(flatten)]
+    folder: FolderRecord<'a>,
+    files: Vec<FileRecord<'a>>,
+    messages: Vec<MessageRecord<'a>>
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum JsonlRecord<'a> {
+    Folder(FolderRecord<'a>),
+    File(FileRecord<'a>),
+    Message(MessageRecord<'a>)
+}
+
+/// A scanned folder and the messages rendered from it, to be consumed by other tools.
+///
+/// Each file is exported with its flags, its status, its content (if loaded) and the indices (starting at 1) of the messages it lands in;
+/// each message with its index, its size (in `message_size_unit`) and its text.
+pub struct ScanExport<'a> {
+    pub folder_info: &'a FolderInfo,
+    pub messages: &'a [String],
+    pub message_sizes: &'a [usize],
+    pub message_size_unit: &'a str,
+    /// Aligned with the folder's files, as in [`crate::message_renderer::RenderedMessages`].
+    pub file_message_indices: &'a [Vec<usize>]
+}
+
+impl<'a> ScanExport<'a> {
+    fn folder_record(&self) -> FolderRecord<'a> {
+        FolderRecord{ folder: &self.folder_info.folder_path, size_in_bytes: self.folder_info.size_in_bytes, n_files: self.folder_info.file_infos.len(), n_messages: self.messages.len(), message_size_unit: self.message_size_unit }
+    }
+
+    fn file_records(&self) -> Vec<FileRecord<'a>> {
+        self.folder_info.file_infos.iter().enumerate().map(|(file_index, file_info)| FileRecord{
+            path: &file_info.filepath,
+            size_in_bytes: file_info.size_in_bytes,
+            is_binary: file_info.is_binary,
+            file_too_large: file_info.file_too_large,
+            should_be_ignored: file_info.should_be_ignored,
+            is_sensitive: file_info.is_sensitive,
+            status: tree_overview::file_status(file_info),
+            content: file_info.file_content.as_deref(),
+            message_indices: self.file_message_indices.get(file_index).map_or(&[], Vec::as_slice)
+        }).collect()
+    }
+
+    fn message_records(&self) -> Vec<MessageRecord<'a>> {
+        self.messages.iter().enumerate().map(|(message_index, message)| MessageRecord{
+            index: message_index + 1,
+            size: self.message_sizes.get(message_index).copied().unwrap_or_default(),
+            content: message
+        }).collect()
+    }
+
+    pub fn to_json(&self) -> Result<String, String> {
+        let folder_document = FolderDocument{ folder: self.folder_record(), files: self.file_records(), messages: self.message_records() };
+        serde_json::to_string_pretty(&folder_document).map_err(|error| error.to_string())
+    }
+
+    pub fn to_jsonl(&self) -> Result<String, String> {
+        let records = std::iter::once(JsonlRecord::Folder(self.folder_record()))
+            .chain(self.file_records().into_iter().map(JsonlRecord::File))
+            .chain(self.message_records().into_iter().map(JsonlRecord::Message));
+
+        let mut jsonl = String::new();
+        for record in records {
+            jsonl += &serde_json::to_string(&record).map_err(|error| error.to_string())?;
+            jsonl.push('\n');
+        }
+        Ok(jsonl)
+    }
+
+    pub fn to_format(&self, export_format: ExportFormat) -> Result<String, String> {
+        match export_format {
+            ExportFormat::Json => self.to_json(),
+            ExportFormat::Jsonl => self.to_jsonl()
+        }
+    }
+
+    pub fn save_to_file(&self, export_format: ExportFormat, path: &str) -> Result<(), String> {
+        let exported_scan = self.to_format(export_format)?;
+        std::fs::write(path, exported_scan).map_err(|error| format!("could not write the export file \"{}\": {}", path, error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_info::FileInfo;
+
+    fn folder_info() -> FolderInfo {
+        let mut loaded_file_info = FileInfo::new(String::from("src/main.rs"), 13, false);
+        loaded_file_info.file_content = Some(String::from("fn main() {}\n"));
+        let ignored_file_info = FileInfo::new(String::from("target/app"), 100, true);
+        FolderInfo{ folder_path: String::from("project"), file_infos: vec![loaded_file_info, ignored_file_info], skipped_subtrees: vec!(), size_in_bytes: 113 }
+    }
+
+    fn scan_export<'a>(folder_info: &'a FolderInfo, messages: &'a [String], file_message_indices: &'a [Vec<usize>]) -> ScanExport<'a> {
+        ScanExport{ folder_info, messages, message_sizes: &[10, 20], message_size_unit: "characters", file_message_indices }
+    }
+
+    #[test]
+    fn json_export_holds_the_folder_its_files_and_the_messages() {
+        let folder_info = folder_info();
+        let messages = [String::from("first"), String::from("second")];
+        let file_message_indices = [vec![2], vec!()];
+        let json: serde_json::Value = serde_json::from_str(&scan_export(&folder_info, &messages, &file_message_indices).to_json().unwrap()).unwrap();
+
+        assert_eq!(json["folder"], "project");
+        assert_eq!(json["n_files"], 2);
+        assert_eq!(json["n_messages"], 2);
+        assert_eq!(json["message_size_unit"], "characters");
+        assert_eq!(json["files"][0]["status"], "loaded");
+        assert_eq!(json["files"][0]["content"], "fn main() {}\n");
+        assert_eq!(json["files"][0]["message_indices"], serde_json::json!([2]));
+        assert_eq!(json["files"][1]["status"], "ignored");
+        assert!(json["files"][1]["content"].is_null());
+        assert_eq!(json["messages"][1], serde_json::json!({ "index": 2, "size": 20, "content": "second" }));
+    }
+
+    #[test]
+    fn jsonl_export_has_one_typed_record_per_line() {
+        let folder_info = folder_info();
+        let messages = [String::from("first"), String::from("second")];
+        let file_message_indices = [vec![2], vec!()];
+        let jsonl = scan_export(&folder_info, &messages, &file_message_indices).to_jsonl().unwrap();
+
+        let records: Vec<serde_json::Value> = jsonl.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        let record_types: Vec<&str> = records.iter().map(|record| record["type"].as_str().unwrap()).collect();
+        assert_eq!(record_types, ["folder", "file", "file", "message", "message"]);
+        assert_eq!(records[0]["folder"], "project");
+        assert_eq!(records[2]["path"], "target/app");
+        assert_eq!(records[3]["index"], 1);
+    }
+}
@@ -0,0 +1,97 @@
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use std::collections::BTreeSet;
+
+pub const DEFAULT_FILE_SIZE_LIMIT: usize = 100 * 1024; // 100 KiB
+
+/// Options controlling which files of a folder are scanned and which of them have their contents loaded.
+#[derive(Clone, Debug)]
+pub struct ScanOptions {
+    /// Names of subfolders to ignore, wherever they appear within the scanned folder; they are not walked into.
+    pub ignored_subfolders: BTreeSet<String>,
+    /// Globs a file must match (at least one of them, if any) to not be ignored; see [`FileFilter`](crate::file_filter::FileFilter).
+    pub included_file_globs: Vec<String>,
+    /// Globs a file must not match to not be ignored.
+    pub excluded_file_globs: Vec<String>,
+    /// Whether `.gitignore`, `.ignore` and `.foldertoaiignore` files (along with git's exclude files) are honored.
+    pub respect_ignore_files: bool,
+    /// Whether the approximate amount of files and size of the folders skipped for being ignored are determined.
+    pub summarize_skipped_subtrees: bool,
+    /// Files larger than this (in bytes) are reported but do not have their contents loaded.
+    pub file_size_limit: usize,
+    /// Amount of threads used to determine binary files and load contents; 0 means one per CPU.
+    pub n_threads: usize,
+    /// Whether secrets (keys, tokens, passwords, etc.) are redacted from the loaded contents; see [`crate::redaction`].
+    pub redact_secrets: bool,
+    /// Globs (as for [`excluded_file_globs`](Self::excluded_file_globs)) of the files withheld for usually holding secrets.
+    pub sensitive_file_globs: Vec<String>,
+    /// Paths (relative to the folder, with `/` as separator) of the sensitive files to share anyway.
+    pub allowed_sensitive_file_paths: BTreeSet<String>
+}
+
+impl ScanOptions {
+    pub fn new() -> Self {
+        ScanOptions{ ignored_subfolders: Self::default_ignored_subfolders(), included_file_globs: Vec::new(), excluded_file_globs: Vec::new(), respect_ignore_files: true, summarize_skipped_subtrees: false, file_size_limit: DEFAULT_FILE_SIZE_LIMIT, n_threads: 0, redact_secrets: true, sensitive_file_globs: Self::default_sensitive_file_globs(), allowed_sensitive_file_paths: BTreeSet::new() }
+    }
+
+    /// Subfolders that are ignored by default; mostly version control metadata, dependencies and build outputs.
+    pub fn default_ignored_subfolders() -> BTreeSet<String>
+    {
+        let default_ignored_subfolders = vec![
+            ".cache",
+            ".cargo",
+            ".git",
+            ".gradle",
+            ".idea",
+            ".mvn",
+            ".npm",
+            ".pytest_cache",
+            ".rustup",
+            ".svn",
+            ".venv",
+            ".vs",
+            ".vscode",
+            "bin",
+            "build",
+            "dist",
+            "node_modules",
+            "obj",
+            "target",
+            "tmp",
+            "venv",
+            "__pycache__"
+        ];
+
+        default_ignored_subfolders.into_iter().map(|subfolder| subfolder.to_string()).collect()
+    }
+
+    /// Globs of the files that are withheld by default for usually holding secrets; mostly keys, certificates, credentials, password databases and
+    /// environment files.
+    pub fn default_sensitive_file_globs() -> Vec<String>
+    {
+        let default_sensitive_file_globs = vec![
+            "**/*.pem",
+            "**/*.key",
+            "**/*.p12",
+            "**/*.pfx",
+            "**/id_*",
+            "**/.env*",
+            "**/.npmrc",
+            "**/.pypirc",
+            "**/.netrc",
+            "**/credentials*",
+            "**/*.kdbx"
+        ];
+
+        default_sensitive_file_globs.into_iter().map(|sensitive_file_glob| sensitive_file_glob.to_string()).collect()
+    }
+
+    pub fn build_thread_pool(&self) -> Result<ThreadPool, String> {
+        ThreadPoolBuilder::new().num_threads(self.n_threads).build().map_err(|error| error.to_string())
+    }
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
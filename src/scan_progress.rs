@@ -0,0 +1,27 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Progress counters of a scan, updated atomically so that they can be read from other threads while the scan runs.
+#[derive(Debug, Default)]
+pub struct ScanProgress {
+    pub n_files_already_determined_whether_binary_or_not: AtomicUsize,
+    pub n_files_already_loaded_if_required_loading: AtomicUsize,
+    pub n_files_loaded: AtomicUsize
+}
+
+impl ScanProgress {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn n_files_already_determined_whether_binary_or_not(&self) -> usize {
+        self.n_files_already_determined_whether_binary_or_not.load(Ordering::Relaxed)
+    }
+
+    pub fn n_files_already_loaded_if_required_loading(&self) -> usize {
+        self.n_files_already_loaded_if_required_loading.load(Ordering::Relaxed)
+    }
+
+    pub fn n_files_loaded(&self) -> usize {
+        self.n_files_loaded.load(Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,119 @@
+use folder_to_ai::{FolderInfo, GitChangeSet, GitChanges, MessageRenderer, RenderedMessages, ScanOptions, ScanProgress};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+
+pub enum ScanEvent {
+    /// The files have been listed; the folder info does not have any content loaded yet.
+    FilesListed(FolderInfo),
+    BinaryFilesDetermined { n_binary_files: usize, n_files_to_load: usize },
+    ContentsLoaded { n_files_that_could_not_be_loaded: usize },
+    /// The messages are ready; their sizes are measured in the unit of the renderer's tokenizer, and the indices of the messages
+    /// each file lands in are given in the order of the folder's files.
+    Finished { folder_info: FolderInfo, messages: Vec<String>, message_sizes: Vec<usize>, session_id: Option<String>, file_message_indices: Vec<Vec<usize>> },
+    Failed(String)
+}
+
+/// Runs the scanning and message generation pipeline on a separate thread (which distributes the per-file work on a thread pool),
+/// reporting the completion of each phase through [`ScanEvent`]s and the progress within phases through a shared [`ScanProgress`].
+/// With a git change set, the changes are obtained once the files are listed, so that only those of the files the scan keeps are shared.
+pub struct ScanWorker {
+    event_receiver: Receiver<ScanEvent>,
+    scan_progress: Arc<ScanProgress>,
+    cancellation_flag: Arc<AtomicBool>
+}
+
+impl ScanWorker {
+    pub fn start(folder_path: String, scan_options: ScanOptions, message_renderer: MessageRenderer, git_change_set: Option<GitChangeSet>) -> Self
+    {
+        let (event_sender, event_receiver) = mpsc::channel();
+        let scan_progress = Arc::new(ScanProgress::new());
+        let cancellation_flag = Arc::new(AtomicBool::new(false));
+
+        let scan_progress_clone = Arc::clone(&scan_progress);
+        let cancellation_flag_clone = Arc::clone(&cancellation_flag);
+        thread::spawn(move || {
+            // errors while sending mean that the receiving side is gone (e.g. the scan has been cancelled), so there is no one to report to
+            if let Err(error) = Self::run(&folder_path, &scan_options, message_renderer, git_change_set.as_ref(), &event_sender, &scan_progress_clone, &cancellation_flag_clone) {
+                let _ = event_sender.send(ScanEvent::Failed(error));
+            }
+        });
+
+        ScanWorker{ event_receiver, scan_progress, cancellation_flag }
+    }
+
+    fn run(folder_path: &str, scan_options: &ScanOptions, mut message_renderer: MessageRenderer, git_change_set: Option<&GitChangeSet>, event_sender: &Sender<ScanEvent>, scan_progress: &ScanProgress, cancellation_flag: &AtomicBool) -> Result<(), String>
+    {
+        let is_cancelled = || cancellation_flag.load(Ordering::Relaxed);
+
+        let thread_pool = scan_options.build_thread_pool()?;
+
+        let mut folder_info = FolderInfo::new_with_cancellation(folder_path, scan_options, cancellation_flag)?;
+        if is_cancelled() {
+            return Ok(());
+        }
+        let _ = event_sender.send(ScanEvent::FilesListed(folder_info.clone()));
+
+        if let Some(git_change_set) = git_change_set {
+            let git_changes = GitChanges::obtain(&folder_info, git_change_set)?;
+            message_renderer = message_renderer.git_changes(if scan_options.redact_secrets { git_changes.with_secrets_redacted() } else { git_changes });
+            if is_cancelled() {
+                return Ok(());
+            }
+        }
+
+        folder_info.determine_binarity_of_all_files(&thread_pool, scan_progress, cancellation_flag);
+        if is_cancelled() {
+            return Ok(());
+        }
+
+        folder_info.determine_files_too_large(scan_options.file_size_limit);
+        let _ = event_sender.send(ScanEvent::BinaryFilesDetermined{ n_binary_files: folder_info.number_of_binary_files(), n_files_to_load: folder_info.get_number_of_files_whose_contents_should_be_loaded() });
+
+        folder_info.load_all_file_contents_if_required(&thread_pool, scan_progress, cancellation_flag);
+        if is_cancelled() {
+            return Ok(());
+        }
+        if scan_options.redact_secrets {
+            folder_info.redact_secrets_in_all_files(&thread_pool);
+        }
+        let _ = event_sender.send(ScanEvent::ContentsLoaded{ n_files_that_could_not_be_loaded: folder_info.number_of_files_that_could_not_be_loaded() });
+
+        let RenderedMessages{ messages, session_id, file_message_indices } = message_renderer.render_with_file_message_indices(&folder_info)?;
+        let message_sizes = messages.iter().map(|message| message_renderer.message_size(message)).collect();
+        if is_cancelled() {
+            return Ok(());
+        }
+        let _ = event_sender.send(ScanEvent::Finished{ folder_info, messages, message_sizes, session_id, file_message_indices });
+
+        Ok(())
+    }
+
+    pub fn progress(&self) -> &ScanProgress
+    {
+        &self.scan_progress
+    }
+
+    /// Returns the next event, if there is one; never blocks.
+    pub fn try_receive_event(&self) -> Option<ScanEvent>
+    {
+        match self.event_receiver.try_recv() {
+            Ok(event) => Some(event),
+            Err(TryRecvError::Empty) | Err(TryRecvError::Disconnected) => None
+        }
+    }
+
+    /// Asks the worker thread to stop as soon as possible; no further events are sent afterwards, except possibly one already in flight.
+    pub fn cancel(&self)
+    {
+        self.cancellation_flag.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Drop for ScanWorker {
+    fn drop(&mut self) {
+        // the thread is not joined: it may be blocked on slow IO (e.g. a network drive), and it stops by itself once it checks the flag
+        self.cancel();
+    }
+}
@@ -1,7 +1,15 @@
+use folder_to_ai::core_utils::{DEFAULT_MAXIMUM_MESSAGE_SIZE, MINIMUM_MESSAGE_SIZE};
+use folder_to_ai::file_filter::FileFilter;
+use folder_to_ai::file_utils::normalized_folder_path;
+use folder_to_ai::git_changes;
+use folder_to_ai::message_templates::{self, fill_template};
+use folder_to_ai::scan_options::{DEFAULT_FILE_SIZE_LIMIT, ScanOptions};
+use folder_to_ai::{BpeTokenizer, GitChangeSet, ByteCounter, CharacterCounter, EstimatedTokenCounter, MessageRenderer, MessageTemplates, OutputFormat, Tokenizer, TreeOverviewPlacement};
 use serde::{Serialize, Deserialize};
 use size::Size;
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::io::Write;
+use std::sync::Arc;
 
 #[derive(Serialize, Deserialize)]
 struct Vec2Serializable {
@@ -9,27 +17,135 @@ struct Vec2Serializable {
     y: f32,
 }
 
+/// Unit in which the maximum message size is expressed.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, clap::ValueEnum)]
+pub enum MessageSizeUnit {
+    Characters,
+    Bytes,
+    /// Tokens of a BPE vocabulary file (tiktoken format)
+    Tokens
+}
+
+impl MessageSizeUnit {
+    fn label(&self) -> &str {
+        match self {
+            MessageSizeUnit::Characters => "characters",
+            MessageSizeUnit::Bytes => "bytes",
+            MessageSizeUnit::Tokens => "tokens"
+        }
+    }
+}
+
+/// Named set of message settings suited to a model or chat, e.g. "32k tokens".
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Profile {
+    pub name: String,
+    pub maximum_message_size: usize,
+    pub message_size_unit: MessageSizeUnit,
+    /// Maximum size of all the messages together (in the same unit), if the model's context window is limited; messages exceeding it
+    /// are not generated.
+    pub context_budget: Option<usize>,
+    pub output_format: OutputFormat,
+    /// Whether everything is sent in one message, whose maximum size then acts as a safety limit.
+    #[serde(default)]
+    pub single_message: bool
+}
+
+impl Profile {
+    pub fn default_profiles() -> Vec<Profile> {
+        vec![
+            Profile{ name: String::from("Small chat (4k characters)"), maximum_message_size: DEFAULT_MAXIMUM_MESSAGE_SIZE, message_size_unit: MessageSizeUnit::Characters, context_budget: None, output_format: OutputFormat::PlainText, single_message: false },
+            Profile{ name: String::from("32k tokens"), maximum_message_size: 32_000, message_size_unit: MessageSizeUnit::Tokens, context_budget: Some(128_000), output_format: OutputFormat::PlainText, single_message: false },
+            Profile{ name: String::from("200k tokens single message"), maximum_message_size: 200_000, message_size_unit: MessageSizeUnit::Tokens, context_budget: Some(200_000), output_format: OutputFormat::PlainText, single_message: true }
+        ]
+    }
+}
+
+/// Saved task prompt, e.g. "Review for bugs".
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct TaskPromptPreset {
+    pub name: String,
+    pub prompt: String
+}
+
+impl TaskPromptPreset {
+    pub fn default_presets() -> Vec<TaskPromptPreset> {
+        vec![
+            TaskPromptPreset{ name: String::from("Review for bugs"), prompt: String::from("Review the code for bugs, such as logic errors, unhandled edge cases and resource leaks. For each one, point to the file and lines involved, explain the problem and suggest a fix.") },
+            TaskPromptPreset{ name: String::from("Write unit tests"), prompt: String::from("Write unit tests for the code, covering both the usual cases and the edge cases, following the conventions of the project.") },
+            TaskPromptPreset{ name: String::from("Explain the architecture"), prompt: String::from("Explain the architecture of the project: its main components, how they interact and how data flows between them.") }
+        ]
+    }
+}
+
 #[derive(Serialize, Deserialize)]
+#[serde(default)] // settings files saved by older versions lack the newer fields
 pub struct Settings {
     window_size: Vec2Serializable,
     pub pasting_with_ctrlv_advances_to_next_message: bool,
     pub file_size_limit: usize,
     #[serde(skip)]
     ignored_subfolders_input: String,
-    pub ignored_subfolders: BTreeSet<String>
+    pub ignored_subfolders: BTreeSet<String>,
+    #[serde(skip)]
+    included_file_globs_input: String,
+    pub included_file_globs: Vec<String>,
+    #[serde(skip)]
+    excluded_file_globs_input: String,
+    pub excluded_file_globs: Vec<String>,
+    pub respect_ignore_files: bool,
+    pub summarize_skipped_subfolders: bool,
+    pub n_threads: usize,
+    pub profiles: Vec<Profile>,
+    pub selected_profile_name: String,
+    #[serde(skip)]
+    new_profile_name_input: String,
+    /// Whether a closing message carries the task prompt.
+    pub include_task_prompt: bool,
+    pub task_prompt: String,
+    pub task_prompt_presets: Vec<TaskPromptPreset>,
+    #[serde(skip)]
+    new_task_prompt_preset_name_input: String,
+    pub tokenizer_vocabulary_path: String,
+    pub split_messages_on_line_boundaries: bool,
+    /// Whether message headers state the session ID and the checksum of the message, to verify transcripts.
+    pub integrity_markers: bool,
+    /// Whether the first message asks to give changes to the files in a format that replies can be applied from.
+    pub request_reply_format: bool,
+    /// Whether files are backed up before replies are applied to them.
+    pub back_up_files_before_applying_replies: bool,
+    /// Whether only the files added, modified or deleted since the folder was last shared are sent, as an update to earlier context.
+    pub share_changes_only: bool,
+    /// Whether modified files are sent as unified diffs rather than in full, when sharing changes.
+    pub modified_files_as_diffs: bool,
+    /// Whether secrets (keys, tokens, passwords, etc.) are redacted from the contents before messages are generated.
+    pub redact_secrets: bool,
+    #[serde(skip)]
+    sensitive_file_globs_input: String,
+    /// Globs of the files withheld for usually holding secrets, unless allowed for a folder.
+    pub sensitive_file_globs: Vec<String>,
+    /// Sensitive files (relative paths with "/" as separator) the user chose to share, by normalized folder path.
+    pub allowed_sensitive_file_paths_by_folder: BTreeMap<String, BTreeSet<String>>,
+    /// Changes of the folder's git repository to share instead of the whole folder, if any.
+    pub git_change_set: Option<GitChangeSet>,
+    pub tree_overview_placement: TreeOverviewPlacement,
+    /// Replaces the default wording of the messages, if set.
+    pub custom_message_templates: Option<MessageTemplates>,
+    #[serde(skip)]
+    message_templates_file_path_input: String,
+    #[serde(skip)]
+    message_templates_file_status: String
 }
 
 impl Settings {
     pub fn new() -> Self {
         const DEFAULT_WINDOW_WIDTH: f32 = 510.0;
         const DEFAULT_WINDOW_HEIGHT: f32 = 400.0;
-        const DEFAULT_FILE_SIZE_LIMIT: usize = 100 * 1024; // 100 KiB
 
-        let mut settings = Settings{window_size: Vec2Serializable{x: DEFAULT_WINDOW_WIDTH, y: DEFAULT_WINDOW_HEIGHT}, pasting_with_ctrlv_advances_to_next_message: true, ignored_subfolders_input: String::from(""), ignored_subfolders: BTreeSet::new(), file_size_limit: DEFAULT_FILE_SIZE_LIMIT};
+        let profiles = Profile::default_profiles();
+        let selected_profile_name = profiles[0].name.clone();
 
-        settings.initialize_default_ignored_subfolders();
-
-        settings
+        Settings{window_size: Vec2Serializable{x: DEFAULT_WINDOW_WIDTH, y: DEFAULT_WINDOW_HEIGHT}, pasting_with_ctrlv_advances_to_next_message: true, ignored_subfolders_input: String::from(""), ignored_subfolders: ScanOptions::default_ignored_subfolders(), included_file_globs_input: String::from(""), included_file_globs: Vec::new(), excluded_file_globs_input: String::from(""), excluded_file_globs: Vec::new(), respect_ignore_files: true, summarize_skipped_subfolders: false, n_threads: 0, profiles, selected_profile_name, new_profile_name_input: String::new(), include_task_prompt: false, task_prompt: String::new(), task_prompt_presets: TaskPromptPreset::default_presets(), new_task_prompt_preset_name_input: String::new(), tokenizer_vocabulary_path: String::new(), split_messages_on_line_boundaries: true, integrity_markers: true, request_reply_format: false, back_up_files_before_applying_replies: true, share_changes_only: false, modified_files_as_diffs: true, redact_secrets: true, sensitive_file_globs_input: String::new(), sensitive_file_globs: ScanOptions::default_sensitive_file_globs(), allowed_sensitive_file_paths_by_folder: BTreeMap::new(), git_change_set: None, tree_overview_placement: TreeOverviewPlacement::None, custom_message_templates: None, message_templates_file_path_input: String::new(), message_templates_file_status: String::new(), file_size_limit: DEFAULT_FILE_SIZE_LIMIT}
     }
 
     pub fn window_size(&self) -> egui::Vec2
@@ -44,54 +160,139 @@ impl Settings {
     }
 
 
-    fn initialize_default_ignored_subfolders(&mut self)
+    /// Options to scan the folder at `folder_path` with, which include the sensitive files allowed for it.
+    pub fn scan_options(&self, folder_path: &str) -> ScanOptions
+    {
+        ScanOptions{ ignored_subfolders: self.ignored_subfolders.clone(), included_file_globs: self.included_file_globs.clone(), excluded_file_globs: self.excluded_file_globs.clone(), respect_ignore_files: self.respect_ignore_files, summarize_skipped_subtrees: self.summarize_skipped_subfolders, file_size_limit: self.file_size_limit, n_threads: self.n_threads, redact_secrets: self.redact_secrets, sensitive_file_globs: self.sensitive_file_globs.clone(), allowed_sensitive_file_paths: self.allowed_sensitive_file_paths(folder_path) }
+    }
+
+    pub fn allowed_sensitive_file_paths(&self, folder_path: &str) -> BTreeSet<String>
+    {
+        self.allowed_sensitive_file_paths_by_folder.get(&normalized_folder_path(folder_path)).cloned().unwrap_or_default()
+    }
+
+    /// Records that the sensitive file at `relative_path` (with "/" as separator) is shared from now on when scanning the folder.
+    pub fn allow_sensitive_file(&mut self, folder_path: &str, relative_path: &str)
     {
-        let default_ignored_subfolders = vec![
-            ".cache",
-            ".cargo",
-            ".git",
-            ".gradle",
-            ".idea",
-            ".mvn",
-            ".npm",
-            ".pytest_cache",
-            ".rustup",
-            ".svn",
-            ".venv",
-            ".vs",
-            ".vscode",
-            "bin",
-            "build",
-            "dist",
-            "node_modules",
-            "obj",
-            "target",
-            "tmp",
-            "venv",
-            "__pycache__"
-        ];
+        self.allowed_sensitive_file_paths_by_folder.entry(normalized_folder_path(folder_path)).or_default().insert(relative_path.to_string());
+    }
 
-        for subfolder in default_ignored_subfolders {
-            self.ignored_subfolders.insert(subfolder.to_string());
+    /// Withdraws the permission to share the sensitive file at `relative_path`, so that it is withheld again.
+    pub fn withhold_sensitive_file(&mut self, folder_path: &str, relative_path: &str)
+    {
+        let folder_path = normalized_folder_path(folder_path);
+        if let Some(allowed_sensitive_file_paths) = self.allowed_sensitive_file_paths_by_folder.get_mut(&folder_path) {
+            allowed_sensitive_file_paths.remove(relative_path);
+            if allowed_sensitive_file_paths.is_empty() {
+                self.allowed_sensitive_file_paths_by_folder.remove(&folder_path);
+            }
         }
     }
 
-    pub fn save_to_file(&self, path: &str) -> bool {
-        if let Ok(json) = serde_json::to_string_pretty(&self) {
-            if let Ok(mut file) = std::fs::File::create(path) {
-                file.write_all(json.as_bytes());
-                return true;
+    /// The profile whose name is `selected_profile_name` or, if there is none, the first one.
+    pub fn selected_profile(&self) -> &Profile
+    {
+        self.profiles.iter().find(|profile| profile.name == self.selected_profile_name).unwrap_or(&self.profiles[0])
+    }
+
+    pub fn selected_profile_mut(&mut self) -> &mut Profile
+    {
+        let selected_profile_index = self.profiles.iter().position(|profile| profile.name == self.selected_profile_name).unwrap_or(0);
+        &mut self.profiles[selected_profile_index]
+    }
+
+    pub fn select_profile(&mut self, profile_name: &str) -> Result<(), String>
+    {
+        if !self.profiles.iter().any(|profile| profile.name == profile_name) {
+            let profile_names: Vec<&str> = self.profiles.iter().map(|profile| profile.name.as_str()).collect();
+            return Err(format!("there is no profile named \"{}\" (available profiles: \"{}\")", profile_name, profile_names.join("\", \"")));
+        }
+        self.selected_profile_name = profile_name.to_string();
+        Ok(())
+    }
+
+    pub fn tokenizer(&self) -> Result<Arc<dyn Tokenizer>, String>
+    {
+        match self.selected_profile().message_size_unit {
+            MessageSizeUnit::Characters => Ok(Arc::new(CharacterCounter)),
+            MessageSizeUnit::Bytes => Ok(Arc::new(ByteCounter)),
+            MessageSizeUnit::Tokens => {
+                if self.tokenizer_vocabulary_path.is_empty() {
+                    return Ok(Arc::new(EstimatedTokenCounter));
+                }
+                Ok(Arc::new(BpeTokenizer::from_tiktoken_file(&self.tokenizer_vocabulary_path)?))
             }
         }
+    }
+
+    /// Warns that token counts are only estimated, when the selected profile counts tokens but no vocabulary file is set.
+    pub fn tokenizer_warning(&self) -> Option<String>
+    {
+        if self.selected_profile().message_size_unit == MessageSizeUnit::Tokens && self.tokenizer_vocabulary_path.is_empty() {
+            return Some(String::from("no vocabulary file (e.g. cl100k_base.tiktoken) is set, so tokens are estimated and messages may not fit the size limit exactly"));
+        }
+        None
+    }
+
+    /// The task prompt, if it is to be sent and is not empty.
+    pub fn task_prompt_to_send(&self) -> Option<String>
+    {
+        let task_prompt = self.task_prompt.trim();
+        if self.include_task_prompt && !task_prompt.is_empty() {
+            return Some(task_prompt.to_string());
+        }
+        None
+    }
+
+    /// Sets the task prompt to the one of the preset named `task_prompt_preset_name`, and has it sent.
+    pub fn apply_task_prompt_preset(&mut self, task_prompt_preset_name: &str) -> Result<(), String>
+    {
+        let Some(task_prompt_preset) = self.task_prompt_presets.iter().find(|task_prompt_preset| task_prompt_preset.name == task_prompt_preset_name) else {
+            let task_prompt_preset_names: Vec<&str> = self.task_prompt_presets.iter().map(|task_prompt_preset| task_prompt_preset.name.as_str()).collect();
+            return Err(format!("there is no task prompt preset named \"{}\" (available: {})", task_prompt_preset_name, task_prompt_preset_names.join(", ")));
+        };
+        self.task_prompt = task_prompt_preset.prompt.clone();
+        self.include_task_prompt = true;
+        Ok(())
+    }
+
+    /// The custom templates, if any, or else the default ones of the selected profile's output format.
+    pub fn message_templates(&self) -> MessageTemplates
+    {
+        self.custom_message_templates.clone().unwrap_or_else(|| MessageTemplates::for_output_format(self.selected_profile().output_format))
+    }
+
+    pub fn message_renderer(&self) -> Result<MessageRenderer, String>
+    {
+        let selected_profile = self.selected_profile();
+        let mut message_renderer = MessageRenderer::new();
+        if let Some(message_templates) = &self.custom_message_templates {
+            message_templates.validate()?;
+            message_renderer = message_renderer.templates(message_templates.clone());
+        }
+        Ok(message_renderer.tokenizer(self.tokenizer()?).maximum_message_size(selected_profile.maximum_message_size).context_budget(selected_profile.context_budget).output_format(selected_profile.output_format).single_message(selected_profile.single_message).task_prompt(self.task_prompt_to_send()).split_on_line_boundaries(self.split_messages_on_line_boundaries).integrity_markers(self.integrity_markers).reply_format_note(self.request_reply_format).modified_files_as_diffs(self.modified_files_as_diffs).tree_overview_placement(self.tree_overview_placement))
+    }
+
+    pub fn save_to_file(&self, path: &str) -> bool {
+        // outside of Windows, nothing creates the directory beforehand
+        if let Some(directory_path) = std::path::Path::new(path).parent() {
+            let _ = std::fs::create_dir_all(directory_path);
+        }
+        if let Ok(json) = serde_json::to_string_pretty(&self)
+            && let Ok(mut file) = std::fs::File::create(path) {
+            return file.write_all(json.as_bytes()).is_ok();
+        }
         false
     }
 
     pub fn load_from_file(&mut self, path: &str) -> bool {
-        if let Ok(json) = std::fs::read_to_string(path) {
-            if let Ok(loaded_settings) = serde_json::from_str(&json) {
-                *self = loaded_settings;
-                return true;
+        if let Ok(json) = std::fs::read_to_string(path)
+            && let Ok(loaded_settings) = serde_json::from_str::<Settings>(&json) {
+            *self = loaded_settings;
+            if self.profiles.is_empty() {
+                self.profiles = Profile::default_profiles();
             }
+            return true;
         }
         false
     }
@@ -116,13 +317,14 @@ impl Settings {
                 text_edit_response.request_focus();
             }
 
-            if ui.button("Add").clicked() {
-                if self.ignored_subfolders_input_contains_valid_folder_name() {
-                    self.add_subfolder_to_ignore();
-                }
+            if ui.button("Add").clicked()
+                && self.ignored_subfolders_input_contains_valid_folder_name() {
+                self.add_subfolder_to_ignore();
             }
         });
 
+        ui.checkbox(&mut self.summarize_skipped_subfolders, "Estimate the number of files and size of skipped subfolders");
+
         ui.separator();
         ui.label("Currently ignoring subfolders:");
         
@@ -149,12 +351,338 @@ impl Settings {
         }
     }
 
+    fn show_file_globs_list_gui(ui: &mut egui::Ui, label: &str, file_globs_input: &mut String, file_globs: &mut Vec<String>) {
+        ui.horizontal(|ui| {
+            ui.label(label);
+            let text_edit_response = ui.add(egui::TextEdit::singleline(file_globs_input).hint_text("e.g. src/**/*.rs"));
+
+            let input_is_valid = FileFilter::is_valid_glob(file_globs_input);
+            if text_edit_response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                if input_is_valid {
+                    Self::add_file_glob(file_globs_input, file_globs);
+                }
+                text_edit_response.request_focus();
+            }
+
+            if ui.add_enabled(input_is_valid, egui::Button::new("Add")).clicked() {
+                Self::add_file_glob(file_globs_input, file_globs);
+            }
+        });
+
+        let mut file_glob_index_to_remove = None;
+        for (file_glob_index, file_glob) in file_globs.iter().enumerate() {
+            ui.horizontal(|ui| {
+                ui.label(file_glob);
+                if ui.button("❌").clicked() {
+                    file_glob_index_to_remove = Some(file_glob_index);
+                }
+            });
+        }
+        if let Some(file_glob_index) = file_glob_index_to_remove {
+            file_globs.remove(file_glob_index);
+        }
+    }
+
+    fn show_file_globs_settings_gui(&mut self, ui: &mut egui::Ui) {
+        ui.label("File globs, matched against paths relative to the folder (once there are files to include, only those are loaded):");
+        Self::show_file_globs_list_gui(ui, "Files to include:", &mut self.included_file_globs_input, &mut self.included_file_globs);
+        Self::show_file_globs_list_gui(ui, "Files to exclude:", &mut self.excluded_file_globs_input, &mut self.excluded_file_globs);
+    }
+
+    fn show_profile_selection_gui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Profile:");
+            egui::ComboBox::from_id_salt("selected_profile").selected_text(self.selected_profile().name.clone()).show_ui(ui, |ui| {
+                for profile in &self.profiles {
+                    ui.selectable_value(&mut self.selected_profile_name, profile.name.clone(), &profile.name);
+                }
+            });
+
+            if ui.add_enabled(self.profiles.len() > 1, egui::Button::new("❌")).on_hover_text("Removes the selected profile").clicked() {
+                self.remove_selected_profile();
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("New profile:");
+            ui.add(egui::TextEdit::singleline(&mut self.new_profile_name_input).hint_text("name"));
+            let input_is_valid = self.new_profile_name_input_is_valid();
+            if ui.add_enabled(input_is_valid, egui::Button::new("Add")).on_hover_text("Adds a profile with the same settings as the selected one").clicked() {
+                self.add_profile_from_selected_one();
+            }
+        });
+    }
 
+    fn show_message_size_settings_gui(&mut self, ui: &mut egui::Ui) {
+        const RANGE_MAX: usize = 1_000_000;
+        let selected_profile = self.selected_profile_mut();
+
+        ui.horizontal(|ui| {
+            ui.add(egui::Slider::new(&mut selected_profile.maximum_message_size, MINIMUM_MESSAGE_SIZE..=RANGE_MAX).logarithmic(true).text("Maximum message size"));
+            egui::ComboBox::from_id_salt("message_size_unit").selected_text(selected_profile.message_size_unit.label()).show_ui(ui, |ui| {
+                for message_size_unit in [MessageSizeUnit::Characters, MessageSizeUnit::Bytes, MessageSizeUnit::Tokens] {
+                    ui.selectable_value(&mut selected_profile.message_size_unit, message_size_unit, message_size_unit.label());
+                }
+            });
+        });
+
+        ui.horizontal(|ui| {
+            let mut context_budget_is_limited = selected_profile.context_budget.is_some();
+            ui.checkbox(&mut context_budget_is_limited, "Limit the size of all messages together");
+            if context_budget_is_limited {
+                let mut context_budget = selected_profile.context_budget.unwrap_or(selected_profile.maximum_message_size);
+                ui.add(egui::Slider::new(&mut context_budget, MINIMUM_MESSAGE_SIZE..=10 * RANGE_MAX).logarithmic(true));
+                selected_profile.context_budget = Some(context_budget);
+            }
+            else {
+                selected_profile.context_budget = None;
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Output format:");
+            egui::ComboBox::from_id_salt("output_format").selected_text(selected_profile.output_format.label()).show_ui(ui, |ui| {
+                for output_format in OutputFormat::ALL {
+                    ui.selectable_value(&mut selected_profile.output_format, output_format, output_format.label());
+                }
+            });
+        });
+
+        ui.checkbox(&mut selected_profile.single_message, "Send everything in a single message (the maximum message size becomes a safety limit)");
+
+        let message_size_unit = selected_profile.message_size_unit;
+        ui.checkbox(&mut self.split_messages_on_line_boundaries, "Keep files whole when possible and split them only at line boundaries");
+        ui.checkbox(&mut self.integrity_markers, "State the session ID and a checksum in message headers (to verify transcripts)");
+        ui.checkbox(&mut self.request_reply_format, "Ask for changes to the files as whole files or unified diffs (so that replies can be applied)");
+        ui.checkbox(&mut self.share_changes_only, "Share only the changes since the folder was last shared (as an update to earlier context)");
+        ui.add_enabled(self.share_changes_only, egui::Checkbox::new(&mut self.modified_files_as_diffs, "Send modified files as unified diffs rather than in full"));
+
+        ui.horizontal(|ui| {
+            ui.label("Git repository:");
+            let selected_text = self.git_change_set.as_ref().map_or("share the whole folder", |git_change_set| git_change_set.label());
+            egui::ComboBox::from_id_salt("git_change_set").selected_text(selected_text).show_ui(ui, |ui| {
+                let base = match &self.git_change_set {
+                    Some(GitChangeSet::CommitRange { base }) => base.clone(),
+                    _ => String::from("HEAD~1")
+                };
+                for git_change_set in [None, Some(GitChangeSet::Unstaged), Some(GitChangeSet::Staged), Some(GitChangeSet::CommitRange { base })] {
+                    let label = git_change_set.as_ref().map_or("share the whole folder", |git_change_set| git_change_set.label()).to_string();
+                    // the base revision being edited does not matter to which choice is selected
+                    let is_selected = self.git_change_set.as_ref().map(std::mem::discriminant) == git_change_set.as_ref().map(std::mem::discriminant);
+                    if ui.selectable_label(is_selected, label).clicked() {
+                        self.git_change_set = git_change_set;
+                    }
+                }
+            });
+            if let Some(GitChangeSet::CommitRange { base }) = &mut self.git_change_set {
+                ui.add(egui::TextEdit::singleline(base).hint_text("base revision, e.g. main").desired_width(120.0));
+                if let Err(error) = git_changes::validate_base_revision(base) {
+                    ui.label(egui::RichText::new(error).color(egui::Color32::RED));
+                }
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Directory tree overview:");
+            egui::ComboBox::from_id_salt("tree_overview_placement").selected_text(self.tree_overview_placement.label()).show_ui(ui, |ui| {
+                for tree_overview_placement in TreeOverviewPlacement::ALL {
+                    ui.selectable_value(&mut self.tree_overview_placement, tree_overview_placement, tree_overview_placement.label());
+                }
+            });
+        });
+
+        if message_size_unit == MessageSizeUnit::Tokens {
+            ui.horizontal(|ui| {
+                ui.label("Vocabulary file:");
+                ui.add(egui::TextEdit::singleline(&mut self.tokenizer_vocabulary_path).hint_text("e.g. C:\\tokenizers\\cl100k_base.tiktoken"));
+            });
+            if self.tokenizer_vocabulary_path.is_empty() {
+                ui.label(egui::RichText::new("Without a vocabulary file, tokens are estimated (one per 3 bytes)").color(egui::Color32::GOLD));
+            }
+        }
+    }
+
+    fn message_templates_preview(message_templates: &MessageTemplates) -> String {
+        let message_header = fill_template(&message_templates.message_header, &[("index", "2"), ("total", "3")]);
+        let file = fill_template(&message_templates.file, &[("path", "src/main.rs"), ("size", "45 bytes"), ("status", "loaded"), ("lines", "3"), ("language", "rust"), ("content", "fn main() {\n    println!(\"Hello, world!\");\n}\n"), ("fence", "```"), ("newline_if_missing", "")]);
+        message_header + &file
+    }
+
+    fn show_message_template_gui(ui: &mut egui::Ui, label: &str, template: &mut String, available_placeholders: &[&str]) {
+        let available_placeholders_str: Vec<String> = available_placeholders.iter().map(|placeholder| format!("{{{}}}", placeholder)).collect();
+        ui.label(label).on_hover_text(format!("Available placeholders: {}", available_placeholders_str.join(", ")));
+        ui.add(egui::TextEdit::multiline(template).desired_rows(2).desired_width(f32::INFINITY).code_editor());
+    }
+
+    fn show_message_templates_settings_gui(&mut self, ui: &mut egui::Ui) {
+        let mut use_custom_message_templates = self.custom_message_templates.is_some();
+        ui.checkbox(&mut use_custom_message_templates, "Use custom message templates");
+        if !use_custom_message_templates {
+            self.custom_message_templates = None;
+            return;
+        }
+        // custom templates start from the defaults of the selected profile's output format
+        let output_format = self.selected_profile().output_format;
+        let message_templates = self.custom_message_templates.get_or_insert_with(|| MessageTemplates::for_output_format(output_format));
+
+        egui::CollapsingHeader::new("Message templates").id_salt("message_templates").show(ui, |ui| {
+            Self::show_message_template_gui(ui, "First message:", &mut message_templates.prologue, message_templates::PROLOGUE_PLACEHOLDERS);
+            Self::show_message_template_gui(ui, "Message header:", &mut message_templates.message_header, message_templates::MESSAGE_HEADER_PLACEHOLDERS);
+            Self::show_message_template_gui(ui, "Start of the single message:", &mut message_templates.single_message_preamble, message_templates::SINGLE_MESSAGE_PREAMBLE_PLACEHOLDERS);
+            Self::show_message_template_gui(ui, "File:", &mut message_templates.file, message_templates::FILE_PLACEHOLDERS);
+            Self::show_message_template_gui(ui, "Part of a file:", &mut message_templates.file_part, message_templates::FILE_PART_PLACEHOLDERS);
+            Self::show_message_template_gui(ui, "Closing message (with the task prompt):", &mut message_templates.closing_message, message_templates::CLOSING_MESSAGE_PLACEHOLDERS);
+            Self::show_message_template_gui(ui, "End of the single message (with the task prompt):", &mut message_templates.single_message_task, message_templates::SINGLE_MESSAGE_TASK_PLACEHOLDERS);
+            Self::show_message_template_gui(ui, "Resuming an interrupted session:", &mut message_templates.resume_message, message_templates::RESUME_MESSAGE_PLACEHOLDERS);
+            Self::show_message_template_gui(ui, "Folder without relevant files:", &mut message_templates.empty_folder, message_templates::EMPTY_FOLDER_PLACEHOLDERS);
+
+            match message_templates.validate() {
+                Ok(()) => {
+                    ui.label(egui::RichText::new("The templates are valid").color(egui::Color32::GREEN));
+                    egui::CollapsingHeader::new("Preview").id_salt("message_templates_preview").show(ui, |ui| {
+                        ui.label(egui::RichText::new(Self::message_templates_preview(message_templates)).monospace());
+                    });
+                },
+                Err(error) => {
+                    ui.label(egui::RichText::new(error).color(egui::Color32::RED));
+                }
+            }
+
+            if ui.button("Reset to defaults").clicked() {
+                *message_templates = MessageTemplates::for_output_format(output_format);
+            }
+
+            ui.horizontal(|ui| {
+                ui.label("Templates file:");
+                ui.add(egui::TextEdit::singleline(&mut self.message_templates_file_path_input).hint_text("e.g. C:\\templates\\assistant.json"));
+                if ui.button("Load").clicked() {
+                    match MessageTemplates::load_from_file(&self.message_templates_file_path_input) {
+                        Ok(loaded_message_templates) => {
+                            *message_templates = loaded_message_templates;
+                            self.message_templates_file_status = String::from("Templates loaded");
+                        },
+                        Err(error) => self.message_templates_file_status = error
+                    }
+                }
+                if ui.button("Save").clicked() {
+                    self.message_templates_file_status = match message_templates.save_to_file(&self.message_templates_file_path_input) {
+                        Ok(()) => String::from("Templates saved"),
+                        Err(error) => error
+                    };
+                }
+            });
+            if !self.message_templates_file_status.is_empty() {
+                ui.label(&self.message_templates_file_status);
+            }
+        });
+    }
+
+    fn show_threads_settings_gui(&mut self, ui: &mut egui::Ui) {
+        const RANGE_MAX: usize = 64;
+        ui.add(egui::Slider::new(&mut self.n_threads, 0..=RANGE_MAX).text("Worker threads (0 = one per CPU)"));
+    }
+
+    fn show_ignore_files_settings_gui(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.respect_ignore_files, "Honor .gitignore, .ignore and .foldertoaiignore files");
+    }
+
+    fn show_secrets_settings_gui(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.redact_secrets, "Redact secrets (private keys, API tokens, passwords, etc.) from the files");
+        ui.horizontal(|ui| {
+            ui.label("Sensitive files, withheld unless allowed for a folder (from the folder information):");
+            if ui.button("Restore defaults").clicked() {
+                self.sensitive_file_globs = ScanOptions::default_sensitive_file_globs();
+            }
+        });
+        Self::show_file_globs_list_gui(ui, "Sensitive files:", &mut self.sensitive_file_globs_input, &mut self.sensitive_file_globs);
+    }
+
+    fn show_task_prompt_settings_gui(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.include_task_prompt, "Finish with a task prompt (the first message asks to wait for it before answering)");
+        if !self.include_task_prompt {
+            return;
+        }
+
+        ui.add(egui::TextEdit::multiline(&mut self.task_prompt).desired_rows(3).desired_width(f32::INFINITY).hint_text("e.g. Review the code for bugs"));
+
+        ui.horizontal(|ui| {
+            ui.label("Presets:");
+            let mut task_prompt_preset_to_apply: Option<String> = None;
+            let mut task_prompt_preset_to_remove: Option<usize> = None;
+            for (task_prompt_preset_index, task_prompt_preset) in self.task_prompt_presets.iter().enumerate() {
+                if ui.button(&task_prompt_preset.name).on_hover_text(&task_prompt_preset.prompt).clicked() {
+                    task_prompt_preset_to_apply = Some(task_prompt_preset.name.clone());
+                }
+                if ui.small_button("❌").on_hover_text("Removes this preset").clicked() {
+                    task_prompt_preset_to_remove = Some(task_prompt_preset_index);
+                }
+            }
+            if let Some(task_prompt_preset_name) = task_prompt_preset_to_apply {
+                let _ = self.apply_task_prompt_preset(&task_prompt_preset_name);
+            }
+            if let Some(task_prompt_preset_index) = task_prompt_preset_to_remove {
+                self.task_prompt_presets.remove(task_prompt_preset_index);
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Save the task prompt as a preset:");
+            ui.add(egui::TextEdit::singleline(&mut self.new_task_prompt_preset_name_input).hint_text("name"));
+            let new_task_prompt_preset_name = self.new_task_prompt_preset_name_input.trim().to_string();
+            let input_is_valid = !new_task_prompt_preset_name.is_empty() && !self.task_prompt.trim().is_empty() && !self.task_prompt_presets.iter().any(|task_prompt_preset| task_prompt_preset.name == new_task_prompt_preset_name);
+            if ui.add_enabled(input_is_valid, egui::Button::new("Save")).clicked() {
+                self.task_prompt_presets.push(TaskPromptPreset{ name: new_task_prompt_preset_name, prompt: self.task_prompt.trim().to_string() });
+                self.new_task_prompt_preset_name_input.clear();
+            }
+        });
+    }
 
     pub fn show_gui(&mut self, ui: &mut egui::Ui) {
+        self.show_profile_selection_gui(ui);
+        self.show_message_size_settings_gui(ui);
+        self.show_message_templates_settings_gui(ui);
+        ui.separator();
+        self.show_task_prompt_settings_gui(ui);
+        ui.separator();
         self.show_file_size_limit_settings_gui(ui);
         ui.separator();
+        self.show_ignore_files_settings_gui(ui);
+        ui.separator();
+        self.show_secrets_settings_gui(ui);
+        ui.separator();
         self.show_folder_ignoring_settings_gui(ui);
+        ui.separator();
+        self.show_file_globs_settings_gui(ui);
+        ui.separator();
+        self.show_threads_settings_gui(ui);
+    }
+
+    fn new_profile_name_input_is_valid(&self) -> bool {
+        let new_profile_name = self.new_profile_name_input.trim();
+        !new_profile_name.is_empty() && !self.profiles.iter().any(|profile| profile.name == new_profile_name)
+    }
+
+    fn add_profile_from_selected_one(&mut self) {
+        let mut new_profile = self.selected_profile().clone();
+        new_profile.name = self.new_profile_name_input.trim().to_string();
+        self.selected_profile_name = new_profile.name.clone();
+        self.profiles.push(new_profile);
+        self.new_profile_name_input.clear();
+    }
+
+    fn remove_selected_profile(&mut self) {
+        if self.profiles.len() > 1 {
+            let selected_profile_name = self.selected_profile().name.clone();
+            self.profiles.retain(|profile| profile.name != selected_profile_name);
+            self.selected_profile_name = self.profiles[0].name.clone();
+        }
+    }
+
+    fn add_file_glob(file_globs_input: &mut String, file_globs: &mut Vec<String>) {
+        if !file_globs.contains(file_globs_input) {
+            file_globs.push(file_globs_input.clone());
+        }
+        file_globs_input.clear();
     }
 
     fn add_subfolder_to_ignore(&mut self) {
@@ -189,4 +717,10 @@ impl Settings {
 
         true
     }
-}
\ No newline at end of file
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
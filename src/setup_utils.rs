@@ -1,19 +1,28 @@
 
+#[cfg(windows)]
 use crate::win_utils;
 
+#[cfg(windows)]
 use std::fs::File;
+#[cfg(windows)]
 use std::io::Write;
+#[cfg(windows)]
 use std::path::Path;
+use std::path::PathBuf;
 
+#[cfg(windows)]
 const ICON_BINARY_DATA: &[u8] = include_bytes!("../mainicon.ico");
 
+#[cfg(windows)]
 const DIRECTORY_REGISTRY_PATH: &str = "Software\\Classes\\Directory";
 
+#[cfg(windows)]
 fn user_profile_path() -> String
 {
     std::env::var("userprofile").unwrap_or("".to_string())
 }
 
+#[cfg(windows)]
 fn setup_path() -> String
 {
     let user_profile_path = user_profile_path();
@@ -23,6 +32,7 @@ fn setup_path() -> String
     user_profile_path + "\\FolderToAI"
 }
 
+#[cfg(windows)]
 fn setup_executable_path() -> String
 {
     let setup_path = setup_path();
@@ -32,6 +42,7 @@ fn setup_executable_path() -> String
     setup_path + "\\FolderToAI.exe"
 }
 
+#[cfg(windows)]
 pub fn setup_icon_path() -> String
 {
     let setup_path = setup_path();
@@ -41,15 +52,46 @@ pub fn setup_icon_path() -> String
     setup_path + "\\FolderToAI.ico"
 }
 
-pub fn settings_file_path() -> String
+/// Directory where the settings and the data kept between runs are: the installation directory on Windows, and a "FolderToAI"
+/// directory within the platform's configuration directory elsewhere (e.g. "~/.config/FolderToAI" on Linux).
+fn data_directory_path() -> Result<PathBuf, String>
 {
-    let setup_path = setup_path();
-    if setup_path.is_empty() {
-        return "".to_string();
-    }
-    setup_path + "\\settings.json"
+    #[cfg(windows)]
+    let base_directory_path = std::env::var_os("userprofile").map(PathBuf::from);
+    #[cfg(not(windows))]
+    let base_directory_path = dirs::config_dir();
+    base_directory_path.map(|base_directory_path| base_directory_path.join("FolderToAI")).ok_or_else(|| String::from("could not find the directory to keep the settings and data of FolderToAI in"))
 }
 
+fn data_path(name: &str) -> Result<String, String>
+{
+    Ok(data_directory_path()?.join(name).to_string_lossy().into_owned())
+}
+
+pub fn settings_file_path() -> Result<String, String>
+{
+    data_path("settings.json")
+}
+
+/// Directory where the sharing session of each folder is kept.
+pub fn sessions_directory_path() -> Result<String, String>
+{
+    data_path("sessions")
+}
+
+/// Directory where the snapshot of each folder, as it was last shared, is kept.
+pub fn snapshots_directory_path() -> Result<String, String>
+{
+    data_path("snapshots")
+}
+
+/// Directory where files are backed up before replies are applied to them; it is created when the first backup is made.
+pub fn backups_directory_path() -> Result<String, String>
+{
+    data_path("backups")
+}
+
+#[cfg(windows)]
 fn current_process_executable_path() -> String
 {
     let mut path: String = String::from("");
@@ -60,11 +102,13 @@ fn current_process_executable_path() -> String
     path
 }
 
+#[cfg(windows)]
 pub fn is_being_executed_from_installation_location() -> bool
 {
     Path::new(&current_process_executable_path()).starts_with(Path::new(&setup_path()))
 }
 
+#[cfg(windows)]
 fn create_icon_file_within_setup_path() -> bool
 {
     if let Ok(mut file) = File::create(setup_icon_path()) {
@@ -75,6 +119,7 @@ fn create_icon_file_within_setup_path() -> bool
     false
 }
 
+#[cfg(windows)]
 fn add_context_menu_on_windows_explorer_when_right_clicking_folder_background()
 {
     win_utils::create_registry_key(&(String::from(DIRECTORY_REGISTRY_PATH) + "\\Background\\shell\\FolderToAI"), "", "FolderToAI");
@@ -84,21 +129,25 @@ fn add_context_menu_on_windows_explorer_when_right_clicking_folder_background()
     win_utils::create_registry_key(&(String::from(DIRECTORY_REGISTRY_PATH) + "\\Background\\shell\\FolderToAI\\command"), "", &folder_to_ai_command_value);
 }
 
+#[cfg(windows)]
 fn add_context_menus_on_windows_explorer()
 {
     add_context_menu_on_windows_explorer_when_right_clicking_folder_background();
 }
 
+#[cfg(windows)]
 fn remove_context_menu_from_windows_explorer_when_right_clicking_folder_background()
 {
     win_utils::remove_registry_key(&(String::from(DIRECTORY_REGISTRY_PATH) + "\\Background\\shell\\FolderToAI"));
 }
 
+#[cfg(windows)]
 fn context_menu_registry_entry_exists() -> bool
 {
     win_utils::registry_key_exists(&(String::from(DIRECTORY_REGISTRY_PATH) + "\\Background\\shell\\FolderToAI"))
 }
 
+#[cfg(windows)]
 fn assure_setup_directory_is_created() -> bool
 {
     let setup_path = setup_path();
@@ -111,28 +160,61 @@ fn assure_setup_directory_is_created() -> bool
     true
 }
 
-fn remove_executable()
+/// Whether a removal succeeded; there being nothing to remove counts as success.
+#[cfg(windows)]
+fn removal_succeeded(removal_result: std::io::Result<()>) -> bool
+{
+    match removal_result {
+        Ok(()) => true,
+        Err(error) => error.kind() == std::io::ErrorKind::NotFound
+    }
+}
+
+#[cfg(windows)]
+fn remove_executable() -> bool
+{
+    removal_succeeded(std::fs::remove_file(setup_executable_path()))
+}
+
+#[cfg(windows)]
+fn remove_icon() -> bool
+{
+    removal_succeeded(std::fs::remove_file(setup_icon_path()))
+}
+
+#[cfg(windows)]
+fn remove_settings_file() -> bool
 {
-    std::fs::remove_file(setup_executable_path());
+    settings_file_path().is_ok_and(|settings_file_path| removal_succeeded(std::fs::remove_file(settings_file_path)))
 }
 
-fn remove_icon()
+#[cfg(windows)]
+fn remove_sessions_directory() -> bool
 {
-    std::fs::remove_file(setup_icon_path());
+    sessions_directory_path().is_ok_and(|sessions_directory_path| removal_succeeded(std::fs::remove_dir_all(sessions_directory_path)))
 }
 
-fn remove_settings_file()
+#[cfg(windows)]
+fn remove_backups_directory() -> bool
 {
-    std::fs::remove_file(settings_file_path());
+    backups_directory_path().is_ok_and(|backups_directory_path| removal_succeeded(std::fs::remove_dir_all(backups_directory_path)))
 }
 
-fn remove_setup_directory()
+#[cfg(windows)]
+fn remove_snapshots_directory() -> bool
+{
+    snapshots_directory_path().is_ok_and(|snapshots_directory_path| removal_succeeded(std::fs::remove_dir_all(snapshots_directory_path)))
+}
+
+#[cfg(windows)]
+fn remove_setup_directory() -> bool
 {
     // note: remove_dir is used instead of remove_dir_all because supposedly the directory is empty
     // if it's not empty, we simply do not remove it, because, although unlikely, it's possible that the user has put relevant data inside it
-    std::fs::remove_dir(setup_path());
+    removal_succeeded(std::fs::remove_dir(setup_path()))
 }
 
+#[cfg(windows)]
 fn assure_executable_exists_on_setup_path() -> bool
 {
     let setup_executable_path = setup_executable_path();
@@ -145,6 +227,7 @@ fn assure_executable_exists_on_setup_path() -> bool
     true
 }
 
+#[cfg(windows)]
 pub fn setup() -> bool
 {
     if !assure_setup_directory_is_created() {
@@ -160,6 +243,7 @@ pub fn setup() -> bool
     true
 }
 
+#[cfg(windows)]
 pub fn is_installed() -> bool {
     if let Ok(executable_file_exists) = std::fs::exists(setup_executable_path()) {
         if !executable_file_exists {
@@ -177,6 +261,7 @@ pub fn is_installed() -> bool {
     true
 }
 
+#[cfg(windows)]
 pub fn is_completely_uninstalled() -> bool {
     if context_menu_registry_entry_exists() {
         return false;
@@ -193,12 +278,17 @@ pub fn is_completely_uninstalled() -> bool {
     true
 }
 
+/// Performs every uninstallation step, even after one fails; returns whether all of them succeeded.
+#[cfg(windows)]
 pub fn uninstall() -> bool
 {
-    remove_executable();
-    remove_icon();
-    remove_settings_file();
-    remove_setup_directory();
+    let mut succeeded = remove_executable();
+    succeeded &= remove_icon();
+    succeeded &= remove_settings_file();
+    succeeded &= remove_sessions_directory();
+    succeeded &= remove_backups_directory();
+    succeeded &= remove_snapshots_directory();
+    succeeded &= remove_setup_directory();
     remove_context_menu_from_windows_explorer_when_right_clicking_folder_background();
-    true
+    succeeded
 }
\ No newline at end of file
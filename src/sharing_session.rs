@@ -0,0 +1,186 @@
+use folder_to_ai::{integrity, MessageTemplates};
+use folder_to_ai::file_utils::normalized_folder_path;
+use folder_to_ai::message_templates::fill_template;
+
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// The messages being shared about a folder and how far the sharing went, kept across runs so that it can be resumed
+/// (e.g. after the chat errors out or is reset).
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SharingSession {
+    pub session_id: String,
+    pub folder_path: String,
+    /// The whole messages, only kept when they cannot hold secrets (see [`SharingSession::new`]); empty otherwise.
+    pub messages: Vec<String>,
+    /// Header of each message (see [`message_header`]), which holds no content of the files.
+    pub message_headers: Vec<String>,
+    /// Hash of each message, to recognize the messages of the session even when they are not kept.
+    message_hashes: Vec<String>,
+    /// Index (starting at 0) of the message currently in the clipboard.
+    pub current_message_index: usize
+}
+
+/// Part of a message up to the line with its integrity marker, or its first line if it has none.
+fn message_header(message: &str) -> &str {
+    let header_end = match message.find(" [session ") {
+        Some(integrity_marker_position) => message[integrity_marker_position..].find('\n').map_or(message.len(), |line_end| integrity_marker_position + line_end),
+        None => message.find('\n').unwrap_or(message.len())
+    };
+    &message[..header_end]
+}
+
+/// Part of a message following the line with its integrity marker (the header), if it has one.
+fn message_body_after_integrity_marker(message: &str) -> Option<&str> {
+    let integrity_marker_position = message.find(" [session ")?;
+    let body_start = message[integrity_marker_position..].find('\n').map_or(message.len(), |line_end| integrity_marker_position + line_end + 1);
+    Some(&message[body_start..])
+}
+
+fn message_hash(message: &str) -> String {
+    format!("{:016x}", integrity::stable_hash(message.as_bytes()))
+}
+
+impl SharingSession {
+    /// Session of the given messages, identified by the session ID stated in them or, if they have no integrity markers, by one derived likewise.
+    /// Like the contents of snapshots, the messages are only kept (to check transcripts for alterations and to compare replies with what was
+    /// shared) when `messages_are_redacted`, i.e. secrets were redacted from them and no sensitive file was allowed, so that no secret is written
+    /// to disk; otherwise, only their headers and hashes are.
+    pub fn new(folder_path: &str, messages: Vec<String>, session_id: Option<String>, messages_are_redacted: bool) -> Self {
+        let session_id = session_id.unwrap_or_else(|| integrity::session_id_of(folder_path, &messages));
+        let message_headers = messages.iter().map(|message| message_header(message).to_string()).collect();
+        let message_hashes = messages.iter().map(|message| message_hash(message)).collect();
+        let messages = if messages_are_redacted { messages } else { vec!() };
+        SharingSession{ session_id, folder_path: normalized_folder_path(folder_path), messages, message_headers, message_hashes, current_message_index: 0 }
+    }
+
+    /// Whether the session is the one of these messages.
+    pub fn is_of_messages(&self, messages: &[String]) -> bool {
+        self.message_hashes.len() == messages.len() && self.message_hashes.iter().zip(messages).all(|(message_hash_of_session, message)| *message_hash_of_session == message_hash(message))
+    }
+
+    /// Whether the whole messages are kept, rather than only their headers and hashes.
+    pub fn keeps_messages(&self) -> bool {
+        !self.messages.is_empty()
+    }
+
+    fn file_path(sessions_directory_path: &str, folder_path: &str) -> PathBuf {
+        // the hash (unlike the standard library's) stays the same across versions, so that session files keep being found
+        Path::new(sessions_directory_path).join(format!("{:016x}.json", integrity::stable_hash(folder_path.as_bytes())))
+    }
+
+    pub fn load_for_folder(sessions_directory_path: &str, folder_path: &str) -> Option<Self> {
+        let folder_path = normalized_folder_path(folder_path);
+        let folder_path = folder_path.as_str();
+        let json = std::fs::read_to_string(Self::file_path(sessions_directory_path, folder_path)).ok()?;
+        let sharing_session: SharingSession = serde_json::from_str(&json).ok()?;
+        if sharing_session.folder_path != folder_path {
+            return None;
+        }
+        Some(sharing_session)
+    }
+
+    pub fn save(&self, sessions_directory_path: &str) -> Result<(), String> {
+        if sessions_directory_path.is_empty() {
+            return Err(String::from("there is no directory to keep sessions in"));
+        }
+        std::fs::create_dir_all(sessions_directory_path).map_err(|error| format!("could not create directory \"{}\": {}", sessions_directory_path, error))?;
+        let json = serde_json::to_string(self).map_err(|error| error.to_string())?;
+        let file_path = Self::file_path(sessions_directory_path, &self.folder_path);
+        std::fs::write(&file_path, json).map_err(|error| format!("could not write \"{}\": {}", file_path.display(), error))
+    }
+
+    /// Message re-orienting the model before sharing resumes with message `resume_message_index` (starting at 0),
+    /// stating which messages were already delivered.
+    pub fn resume_message(&self, message_templates: &MessageTemplates, resume_message_index: usize) -> String {
+        let n_messages = self.message_headers.len();
+        let delivered = match resume_message_index {
+            0 => String::from("No message was delivered yet."),
+            1 => format!("Message 1 of {} was already delivered.", n_messages),
+            _ => format!("Messages 1 to {} of {} were already delivered.", resume_message_index, n_messages)
+        };
+        fill_template(&message_templates.resume_message, &[
+            ("session", &self.session_id),
+            ("folder", &self.folder_path),
+            ("delivered", &delivered),
+            ("index", &(resume_message_index + 1).to_string()),
+            ("total", &n_messages.to_string())
+        ])
+    }
+
+    /// Problems found when checking a transcript of the chat (in which the messages were pasted) against the session:
+    /// messages from other sessions or other versions of this one, duplicated, missing, out of order or altered messages
+    /// (the latter only when the messages are kept).
+    pub fn verify_transcript(&self, transcript: &str) -> Vec<String> {
+        let transcript = transcript.replace("\r\n", "\n");
+        let n_messages = self.message_headers.len();
+        let found_integrity_markers = integrity::find_integrity_markers(&transcript);
+        if found_integrity_markers.is_empty() {
+            return vec![String::from("no message header with an integrity marker was found in the transcript")];
+        }
+
+        let mut problems: Vec<String> = vec!();
+        let mut expected_checksums: BTreeMap<usize, Option<String>> = BTreeMap::new();
+        for message_header in &self.message_headers {
+            if let Some(integrity_marker) = integrity::find_integrity_markers(message_header).into_iter().next() {
+                expected_checksums.insert(integrity_marker.message_index, integrity_marker.checksum);
+            }
+        }
+
+        // occurrences of each message of the session, in the order they were pasted
+        let mut n_occurrences_of_messages: BTreeMap<usize, usize> = BTreeMap::new();
+        let mut previous_message_index = 0;
+        for found_integrity_marker in &found_integrity_markers {
+            let message_str = format!("message {}/{}", found_integrity_marker.message_index, found_integrity_marker.n_messages);
+            if found_integrity_marker.session_id != self.session_id {
+                problems.push(format!("{} belongs to another session ({})", message_str, found_integrity_marker.session_id));
+                continue;
+            }
+            if found_integrity_marker.n_messages != n_messages || !expected_checksums.contains_key(&found_integrity_marker.message_index) {
+                problems.push(format!("{} does not match the stored session, which has {} messages", message_str, n_messages));
+                continue;
+            }
+            if expected_checksums.get(&found_integrity_marker.message_index) != Some(&found_integrity_marker.checksum) {
+                problems.push(format!("{} has a checksum that differs from the stored one", message_str));
+                continue;
+            }
+
+            if found_integrity_marker.message_index < previous_message_index {
+                problems.push(format!("{} was pasted after message {}", message_str, previous_message_index));
+            }
+            previous_message_index = found_integrity_marker.message_index;
+            *n_occurrences_of_messages.entry(found_integrity_marker.message_index).or_default() += 1;
+        }
+
+        for (message_index, n_occurrences) in &n_occurrences_of_messages {
+            if *n_occurrences > 1 {
+                problems.push(format!("message {}/{} was pasted {} times", message_index, n_messages, n_occurrences));
+            }
+        }
+
+        let last_pasted_message_index = n_occurrences_of_messages.keys().next_back().copied().unwrap_or(0);
+        let missing_message_indices: Vec<String> = (1..last_pasted_message_index).filter(|message_index| !n_occurrences_of_messages.contains_key(message_index)).map(|message_index| message_index.to_string()).collect();
+        if !missing_message_indices.is_empty() {
+            problems.push(format!("missing message(s): {}", missing_message_indices.join(", ")));
+        }
+        if last_pasted_message_index + 1 == n_messages {
+            problems.push(format!("message {} has not been pasted yet", n_messages));
+        }
+        else if last_pasted_message_index < n_messages {
+            problems.push(format!("messages {} to {} have not been pasted yet", last_pasted_message_index + 1, n_messages));
+        }
+
+        for (message_index, message) in self.messages.iter().enumerate() {
+            if !n_occurrences_of_messages.contains_key(&(message_index + 1)) {
+                continue;
+            }
+            if let Some(message_body) = message_body_after_integrity_marker(message)
+                && !transcript.contains(message_body.trim()) {
+                problems.push(format!("message {}/{} differs from the stored one (it may have been truncated or altered)", message_index + 1, n_messages));
+            }
+        }
+
+        problems
+    }
+}
@@ -0,0 +1,60 @@
+use std::path::Path;
+
+// summarizing a skipped subtree must not cost as much as scanning it; past this amount of entries, the summary is left partial
+const MAXIMUM_AMOUNT_OF_ENTRIES_TO_SUMMARIZE: usize = 10_000;
+
+/// Approximate amount of files and size of a skipped subtree.
+#[derive(Debug, Clone)]
+pub struct SkippedSubtreeSummary {
+    pub n_files: usize,
+    pub size_in_bytes: usize,
+    /// Whether the summary stopped before going through the whole subtree, in which case the values are lower bounds.
+    pub is_partial: bool
+}
+
+/// A folder that has not been walked into because it is ignored.
+#[derive(Debug, Clone)]
+pub struct SkippedSubtree
+{
+    /// Path relative to the scanned folder.
+    pub path: String,
+    pub summary: Option<SkippedSubtreeSummary>
+}
+
+impl SkippedSubtree {
+    pub fn new(path: String) -> Self {
+        SkippedSubtree{ path, summary: None }
+    }
+
+    pub fn summarize(path: &Path) -> SkippedSubtreeSummary {
+        let mut summary = SkippedSubtreeSummary{ n_files: 0, size_in_bytes: 0, is_partial: false };
+
+        let mut n_entries_visited: usize = 0;
+        let mut directories_to_visit = vec![path.to_path_buf()];
+        while let Some(directory_path) = directories_to_visit.pop() {
+            if let Ok(entries) = std::fs::read_dir(&directory_path) {
+                for dir_entry in entries.flatten() {
+                    n_entries_visited += 1;
+                    if n_entries_visited > MAXIMUM_AMOUNT_OF_ENTRIES_TO_SUMMARIZE {
+                        summary.is_partial = true;
+                        return summary;
+                    }
+
+                    if let Ok(file_type) = dir_entry.file_type() {
+                        if file_type.is_file() {
+                            summary.n_files += 1;
+                            if let Ok(metadata) = dir_entry.metadata() {
+                                summary.size_in_bytes += metadata.len() as usize;
+                            }
+                        }
+                        else if file_type.is_dir() {
+                            directories_to_visit.push(dir_entry.path());
+                        }
+                    }
+                }
+            }
+        }
+
+        summary
+    }
+}
@@ -0,0 +1,222 @@
+use crate::file_utils;
+use crate::folder_info::FolderInfo;
+use crate::integrity;
+use crate::unified_diff;
+
+use serde::{Serialize, Deserialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// snapshots are kept for every folder shared, so larger contents are only hashed (their modified versions are then shared in full)
+const MAXIMUM_KEPT_CONTENT_SIZE_IN_BYTES: usize = 256 * 1024;
+
+/// A file as it was when a snapshot was taken.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SnapshotEntry {
+    /// Path relative to the folder.
+    pub path: String,
+    pub size_in_bytes: usize,
+    /// Time of the last modification, in seconds since the Unix epoch.
+    pub modified_time: Option<u64>,
+    /// Hash of the content, if it was loaded.
+    pub content_hash: Option<String>,
+    /// Kept so that modified files can be shared as diffs against it, if it was redacted and is not too large.
+    pub content: Option<String>
+}
+
+/// Manifest of the files of a folder (those that are not ignored) as they were when it was last shared, so that only what changed
+/// since then can be shared next.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FolderSnapshot {
+    pub folder_path: String,
+    /// Time the snapshot was taken, in seconds since the Unix epoch.
+    pub taken_at: u64,
+    pub entries: Vec<SnapshotEntry>
+}
+
+fn content_hash(content: &str) -> String {
+    format!("{:016x}", integrity::stable_hash(content.as_bytes()))
+}
+
+impl FolderSnapshot {
+    /// Snapshot of the scanned folder; the contents are only kept when `contents_are_redacted`, so that no secret is written to disk.
+    pub fn of_folder(folder_info: &FolderInfo, contents_are_redacted: bool) -> Self {
+        let entries = folder_info.file_infos.iter().filter(|file_info| !file_info.should_be_ignored).map(|file_info| SnapshotEntry{
+            path: file_info.filepath.clone(),
+            size_in_bytes: file_info.size_in_bytes,
+            modified_time: file_info.modified_time,
+            content_hash: file_info.file_content.as_deref().map(content_hash),
+            content: file_info.file_content.clone().filter(|file_content| contents_are_redacted && file_content.len() <= MAXIMUM_KEPT_CONTENT_SIZE_IN_BYTES)
+        }).collect();
+        let taken_at = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_secs());
+        FolderSnapshot{ folder_path: file_utils::normalized_folder_path(&folder_info.folder_path), taken_at, entries }
+    }
+
+    fn file_path(snapshots_directory_path: &str, folder_path: &str) -> PathBuf {
+        Path::new(snapshots_directory_path).join(format!("{:016x}.json", integrity::stable_hash(folder_path.as_bytes())))
+    }
+
+    pub fn load_for_folder(snapshots_directory_path: &str, folder_path: &str) -> Option<Self> {
+        let folder_path = file_utils::normalized_folder_path(folder_path);
+        let json = std::fs::read_to_string(Self::file_path(snapshots_directory_path, &folder_path)).ok()?;
+        let folder_snapshot: FolderSnapshot = serde_json::from_str(&json).ok()?;
+        if folder_snapshot.folder_path != folder_path {
+            return None;
+        }
+        Some(folder_snapshot)
+    }
+
+    pub fn save(&self, snapshots_directory_path: &str) -> Result<(), String> {
+        if snapshots_directory_path.is_empty() {
+            return Err(String::from("there is no directory to keep snapshots in"));
+        }
+        std::fs::create_dir_all(snapshots_directory_path).map_err(|error| format!("could not create directory \"{}\": {}", snapshots_directory_path, error))?;
+        let json = serde_json::to_string(self).map_err(|error| error.to_string())?;
+        let file_path = Self::file_path(snapshots_directory_path, &self.folder_path);
+        std::fs::write(&file_path, json).map_err(|error| format!("could not write \"{}\": {}", file_path.display(), error))
+    }
+}
+
+/// What changed in a folder since a snapshot of it was taken.
+#[derive(Clone, Debug)]
+pub struct FolderChanges {
+    /// The folder with only its added and modified files not ignored; the content of modified files is a unified diff against
+    /// their previous version when they are shared as diffs and the diff could be computed.
+    pub changed_folder_info: FolderInfo,
+    pub added_file_paths: Vec<String>,
+    pub modified_file_paths: Vec<String>,
+    pub deleted_file_paths: Vec<String>,
+    /// Time the snapshot was taken, in seconds since the Unix epoch.
+    pub snapshot_taken_at: u64
+}
+
+impl FolderChanges {
+    /// Compares the folder with the snapshot; files whose content was not loaded (e.g. binary ones) are compared by size and modification time.
+    pub fn since_snapshot(folder_snapshot: &FolderSnapshot, folder_info: &FolderInfo, modified_files_as_diffs: bool) -> Self {
+        let snapshot_entries: HashMap<&str, &SnapshotEntry> = folder_snapshot.entries.iter().map(|snapshot_entry| (snapshot_entry.path.as_str(), snapshot_entry)).collect();
+        let mut changed_folder_info = folder_info.clone();
+        let mut added_file_paths = vec!();
+        let mut modified_file_paths = vec!();
+        let mut current_file_paths: HashSet<&str> = HashSet::new();
+
+        for file_info in &mut changed_folder_info.file_infos {
+            if file_info.should_be_ignored {
+                continue;
+            }
+            current_file_paths.insert(file_info.filepath.as_str());
+            let Some(snapshot_entry) = snapshot_entries.get(file_info.filepath.as_str()) else {
+                added_file_paths.push(file_info.filepath.clone());
+                continue;
+            };
+
+            let is_modified = match (&snapshot_entry.content_hash, &file_info.file_content) {
+                (Some(previous_content_hash), Some(file_content)) => *previous_content_hash != content_hash(file_content),
+                (None, None) => snapshot_entry.size_in_bytes != file_info.size_in_bytes || snapshot_entry.modified_time != file_info.modified_time,
+                _ => true
+            };
+            if !is_modified {
+                file_info.should_be_ignored = true;
+                continue;
+            }
+            modified_file_paths.push(file_info.filepath.clone());
+
+            if modified_files_as_diffs
+                && let (Some(previous_content), Some(file_content)) = (&snapshot_entry.content, &file_info.file_content)
+                && let Some(diff) = unified_diff::unified_diff(&file_info.filepath, previous_content, file_content) {
+                file_info.file_content = Some(diff);
+                file_info.content_is_diff = true;
+            }
+        }
+
+        let deleted_file_paths = folder_snapshot.entries.iter().filter(|snapshot_entry| !current_file_paths.contains(snapshot_entry.path.as_str())).map(|snapshot_entry| snapshot_entry.path.clone()).collect();
+        FolderChanges{ changed_folder_info, added_file_paths, modified_file_paths, deleted_file_paths, snapshot_taken_at: folder_snapshot.taken_at }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added_file_paths.is_empty() && self.modified_file_paths.is_empty() && self.deleted_file_paths.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_info::FileInfo;
+
+    fn loaded_file_info(path: &str, content: &str) -> FileInfo {
+        let mut file_info = FileInfo::new(path.to_string(), content.len(), false);
+        file_info.modified_time = Some(1);
+        file_info.file_content = Some(content.to_string());
+        file_info
+    }
+
+    fn binary_file_info(path: &str, size_in_bytes: usize, modified_time: u64) -> FileInfo {
+        let mut file_info = FileInfo::new(path.to_string(), size_in_bytes, false);
+        file_info.is_binary = true;
+        file_info.modified_time = Some(modified_time);
+        file_info
+    }
+
+    fn folder_info_with(file_infos: Vec<FileInfo>) -> FolderInfo {
+        FolderInfo{ folder_path: String::from("/tmp/folder"), file_infos, skipped_subtrees: vec!(), size_in_bytes: 0 }
+    }
+
+    #[test]
+    fn keeps_only_redacted_contents_of_moderate_size() {
+        let large_content = "a".repeat(MAXIMUM_KEPT_CONTENT_SIZE_IN_BYTES + 1);
+        let mut ignored_file_info = loaded_file_info("ignored.txt", "ignored");
+        ignored_file_info.should_be_ignored = true;
+        let folder_info = folder_info_with(vec![loaded_file_info("small.txt", "small"), loaded_file_info("large.txt", &large_content), ignored_file_info, binary_file_info("image.png", 10, 1)]);
+
+        let folder_snapshot = FolderSnapshot::of_folder(&folder_info, true);
+        let kept_contents: Vec<(&str, Option<&str>, bool)> = folder_snapshot.entries.iter().map(|snapshot_entry| (snapshot_entry.path.as_str(), snapshot_entry.content.as_deref(), snapshot_entry.content_hash.is_some())).collect();
+        assert_eq!(kept_contents, vec![("small.txt", Some("small"), true), ("large.txt", None, true), ("image.png", None, false)]);
+
+        let folder_snapshot = FolderSnapshot::of_folder(&folder_info, false);
+        assert!(folder_snapshot.entries.iter().all(|snapshot_entry| snapshot_entry.content.is_none()));
+        assert_eq!(folder_snapshot.entries[0].content_hash, Some(content_hash("small")));
+    }
+
+    #[test]
+    fn finds_the_changes_since_a_snapshot() {
+        let previous_folder_info = folder_info_with(vec![
+            loaded_file_info("deleted.txt", "deleted\n"),
+            loaded_file_info("modified.txt", "a\nb\nc\n"),
+            loaded_file_info("unchanged.txt", "same\n"),
+            binary_file_info("image.png", 10, 1),
+            binary_file_info("touched.png", 10, 1)
+        ]);
+        let folder_snapshot = FolderSnapshot::of_folder(&previous_folder_info, true);
+        let folder_info = folder_info_with(vec![
+            loaded_file_info("added.txt", "added\n"),
+            loaded_file_info("modified.txt", "a\nB\nc\n"),
+            loaded_file_info("unchanged.txt", "same\n"),
+            binary_file_info("image.png", 10, 1),
+            binary_file_info("touched.png", 10, 2)
+        ]);
+
+        let folder_changes = FolderChanges::since_snapshot(&folder_snapshot, &folder_info, true);
+        assert_eq!(folder_changes.added_file_paths, vec!["added.txt"]);
+        assert_eq!(folder_changes.modified_file_paths, vec!["modified.txt", "touched.png"]);
+        assert_eq!(folder_changes.deleted_file_paths, vec!["deleted.txt"]);
+        let shared_file_infos: Vec<&FileInfo> = folder_changes.changed_folder_info.file_infos.iter().filter(|file_info| !file_info.should_be_ignored).collect();
+        assert_eq!(shared_file_infos.len(), 3);
+        assert!(shared_file_infos[1].content_is_diff);
+        assert_eq!(shared_file_infos[1].file_content.as_deref(), Some("--- a/modified.txt\n+++ b/modified.txt\n@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n"));
+        assert!(FolderChanges::since_snapshot(&folder_snapshot, &previous_folder_info, true).is_empty());
+        assert!(FolderChanges::since_snapshot(&FolderSnapshot::of_folder(&folder_info, true), &folder_info, true).is_empty());
+    }
+
+    #[test]
+    fn shares_modified_files_in_full_without_their_previous_content() {
+        let folder_snapshot = FolderSnapshot::of_folder(&folder_info_with(vec![loaded_file_info("a.txt", "a\n")]), false);
+        let folder_info = folder_info_with(vec![loaded_file_info("a.txt", "b\n")]);
+
+        for modified_files_as_diffs in [true, false] {
+            let folder_changes = FolderChanges::since_snapshot(&folder_snapshot, &folder_info, modified_files_as_diffs);
+            assert_eq!(folder_changes.modified_file_paths, vec!["a.txt"]);
+            assert!(!folder_changes.changed_folder_info.file_infos[0].content_is_diff);
+            assert_eq!(folder_changes.changed_folder_info.file_infos[0].file_content.as_deref(), Some("b\n"));
+        }
+    }
+}
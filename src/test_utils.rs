@@ -0,0 +1,43 @@
+//! Helpers shared by the unit tests of the crate.
+
+use std::path::{Path, PathBuf};
+
+/// A directory within the temporary directory (removed when dropped), with the given files.
+pub struct TestDirectory {
+    path: PathBuf
+}
+
+impl TestDirectory {
+    /// The name has to be unique among the tests, so it's best prefixed with the name of the module of the test.
+    pub fn new(name: &str, files: &[(&str, &str)]) -> Self {
+        let path = std::env::temp_dir().join(format!("folder_to_ai_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        let test_directory = TestDirectory{ path };
+        test_directory.write_files(files);
+        test_directory
+    }
+
+    /// Writes the files (paths relative to the directory), creating their parent directories as needed.
+    pub fn write_files(&self, files: &[(&str, &str)]) {
+        for (path, content) in files {
+            let file_path = self.path.join(path);
+            std::fs::create_dir_all(file_path.parent().unwrap()).unwrap();
+            std::fs::write(&file_path, content).unwrap();
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn join(&self, relative_path: &str) -> PathBuf {
+        self.path.join(relative_path)
+    }
+}
+
+impl Drop for TestDirectory {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
@@ -0,0 +1,277 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64_STANDARD;
+use fancy_regex::Regex;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::path::Path;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Measures the size of texts, so that messages can be limited to a budget expressed in its unit (e.g. "4096 characters" or "8000 tokens").
+pub trait Tokenizer: Send + Sync {
+    /// Name of the counted unit, in plural and lowercase (e.g. "characters").
+    fn unit_name(&self) -> &str;
+
+    fn count(&self, text: &str) -> usize;
+}
+
+/// Counts user-perceived characters (grapheme clusters), so a line break or an emoji counts as one.
+pub struct CharacterCounter;
+
+impl Tokenizer for CharacterCounter {
+    fn unit_name(&self) -> &str {
+        "characters"
+    }
+
+    fn count(&self, text: &str) -> usize {
+        text.graphemes(true).count()
+    }
+}
+
+/// Counts bytes of the UTF-8 encoding.
+pub struct ByteCounter;
+
+impl Tokenizer for ByteCounter {
+    fn unit_name(&self) -> &str {
+        "bytes"
+    }
+
+    fn count(&self, text: &str) -> usize {
+        text.len()
+    }
+}
+
+/// Estimates tokens as one per 3 bytes (rounded up), for when no vocabulary file is available.
+///
+/// Vocabularies such as `cl100k_base` average about 4 bytes per token on English text and code, so the estimate
+/// usually errs on the large side, but it may fall short on unusual texts.
+pub struct EstimatedTokenCounter;
+
+pub const ESTIMATED_BYTES_PER_TOKEN: usize = 3;
+
+impl Tokenizer for EstimatedTokenCounter {
+    fn unit_name(&self) -> &str {
+        "tokens"
+    }
+
+    fn count(&self, text: &str) -> usize {
+        text.len().div_ceil(ESTIMATED_BYTES_PER_TOKEN)
+    }
+}
+
+/// Pre-tokenization pattern of the `cl100k_base` encoding (used by GPT-4 and GPT-3.5 models).
+pub const CL100K_PATTERN: &str = r"(?i:'s|'t|'re|'ve|'m|'ll|'d)|[^\r\n\p{L}\p{N}]?\p{L}+|\p{N}{1,3}| ?[^\s\p{L}\p{N}]+[\r\n]*|\s*[\r\n]+|\s+(?!\S)|\s+";
+
+/// Pre-tokenization pattern of the `o200k_base` encoding (used by GPT-4o and later models).
+pub const O200K_PATTERN: &str = r"[^\r\n\p{L}\p{N}]?[\p{Lu}\p{Lt}\p{Lm}\p{Lo}\p{M}]*[\p{Ll}\p{Lm}\p{Lo}\p{M}]+(?i:'s|'t|'re|'ve|'m|'ll|'d)?|[^\r\n\p{L}\p{N}]?[\p{Lu}\p{Lt}\p{Lm}\p{Lo}\p{M}]+[\p{Ll}\p{Lm}\p{Lo}\p{M}]*(?i:'s|'t|'re|'ve|'m|'ll|'d)?|\p{N}{1,3}| ?[^\s\p{L}\p{N}]+[\r\n/]*|\s*[\r\n]+|\s+(?!\S)|\s+";
+
+/// Byte pair encoding tokenizer, counting tokens the way the models sharing its vocabulary do.
+///
+/// The vocabulary is loaded from a local file in the tiktoken format (e.g. `cl100k_base.tiktoken`), where each line holds
+/// a base64-encoded token followed by its rank; nothing is downloaded.
+pub struct BpeTokenizer {
+    ranks: HashMap<Vec<u8>, u32>,
+    pattern: Regex
+}
+
+impl BpeTokenizer {
+    /// Loads a vocabulary file, splitting texts with the pattern of the encoding its file name refers to
+    /// ([`O200K_PATTERN`] if it contains "o200k", [`CL100K_PATTERN`] otherwise).
+    pub fn from_tiktoken_file(vocabulary_file_path: &str) -> Result<Self, String> {
+        let vocabulary_file_name = Path::new(vocabulary_file_path).file_name().map(|file_name| file_name.to_string_lossy().to_string()).unwrap_or_default();
+        let pattern = if vocabulary_file_name.contains("o200k") { O200K_PATTERN } else { CL100K_PATTERN };
+        Self::from_tiktoken_file_with_pattern(vocabulary_file_path, pattern)
+    }
+
+    pub fn from_tiktoken_file_with_pattern(vocabulary_file_path: &str, pattern: &str) -> Result<Self, String> {
+        let vocabulary = std::fs::read_to_string(vocabulary_file_path).map_err(|error| format!("could not read the vocabulary file \"{}\": {}", vocabulary_file_path, error))?;
+
+        let mut ranks = HashMap::new();
+        for (line_index, line) in vocabulary.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let invalid_line_error = || format!("invalid line {} in the vocabulary file \"{}\"", line_index + 1, vocabulary_file_path);
+
+            let mut line_parts = line.split_whitespace();
+            let (Some(encoded_token), Some(rank), None) = (line_parts.next(), line_parts.next(), line_parts.next()) else {
+                return Err(invalid_line_error());
+            };
+            let token = BASE64_STANDARD.decode(encoded_token).map_err(|_| invalid_line_error())?;
+            let rank: u32 = rank.parse().map_err(|_| invalid_line_error())?;
+            ranks.insert(token, rank);
+        }
+        if ranks.is_empty() {
+            return Err(format!("the vocabulary file \"{}\" contains no tokens", vocabulary_file_path));
+        }
+
+        Self::from_ranks(ranks, pattern)
+    }
+
+    fn from_ranks(ranks: HashMap<Vec<u8>, u32>, pattern: &str) -> Result<Self, String> {
+        let pattern = Regex::new(pattern).map_err(|error| format!("invalid tokenization pattern: {}", error))?;
+        Ok(BpeTokenizer{ ranks, pattern })
+    }
+
+    fn count_tokens_of_piece(&self, piece: &[u8]) -> usize {
+        if self.ranks.contains_key(piece) {
+            return 1;
+        }
+
+        // the adjacent pair of tokens with the lowest rank (the leftmost one among equals) is merged first, as when the vocabulary was built;
+        // the tokens, starting from single bytes, form a linked list and the possible merges are kept in a heap, so that long pieces stay fast
+        let n_bytes = piece.len();
+        let mut is_token_start: Vec<bool> = vec![true; n_bytes];
+        let mut token_ends: Vec<usize> = (1..=n_bytes).collect();
+        let mut previous_token_starts: Vec<Option<usize>> = (0..n_bytes).map(|byte_index| byte_index.checked_sub(1)).collect();
+
+        let mut possible_merges: BinaryHeap<Reverse<(u32, usize, usize, usize)>> = BinaryHeap::new();
+        let add_possible_merge = |possible_merges: &mut BinaryHeap<_>, left_token_start: usize, right_token_start: usize, right_token_end: usize| {
+            if let Some(&rank) = self.ranks.get(&piece[left_token_start..right_token_end]) {
+                possible_merges.push(Reverse((rank, left_token_start, right_token_start, right_token_end)));
+            }
+        };
+        for token_start in 0..n_bytes.saturating_sub(1) {
+            add_possible_merge(&mut possible_merges, token_start, token_start + 1, token_start + 2);
+        }
+
+        let mut n_tokens = n_bytes;
+        while let Some(Reverse((_, left_token_start, right_token_start, right_token_end))) = possible_merges.pop() {
+            // merges involving tokens that have changed since they were added are stale
+            let merge_is_stale = !is_token_start[left_token_start] || token_ends[left_token_start] != right_token_start || token_ends[right_token_start] != right_token_end;
+            if merge_is_stale {
+                continue;
+            }
+
+            is_token_start[right_token_start] = false;
+            token_ends[left_token_start] = right_token_end;
+            n_tokens -= 1;
+
+            if let Some(previous_token_start) = previous_token_starts[left_token_start] {
+                add_possible_merge(&mut possible_merges, previous_token_start, left_token_start, right_token_end);
+            }
+            if right_token_end < n_bytes {
+                previous_token_starts[right_token_end] = Some(left_token_start);
+                add_possible_merge(&mut possible_merges, left_token_start, right_token_end, token_ends[right_token_end]);
+            }
+        }
+
+        n_tokens
+    }
+}
+
+impl Tokenizer for BpeTokenizer {
+    fn unit_name(&self) -> &str {
+        "tokens"
+    }
+
+    fn count(&self, text: &str) -> usize {
+        let mut n_tokens = 0;
+        let mut end_of_previous_piece = 0;
+        for piece in self.pattern.find_iter(text) {
+            let Ok(piece) = piece else {
+                break;
+            };
+            // text not covered by the pattern (there is none with the standard ones) is counted byte by byte, as the worst case
+            n_tokens += piece.start() - end_of_previous_piece;
+            n_tokens += self.count_tokens_of_piece(piece.as_str().as_bytes());
+            end_of_previous_piece = piece.end();
+        }
+        n_tokens + text.len() - end_of_previous_piece
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A vocabulary with every single byte (ranked by value) followed by the given merged tokens, ranked in order.
+    fn tokenizer_with_merges(merged_tokens: &[&str], pattern: &str) -> BpeTokenizer {
+        let mut ranks: HashMap<Vec<u8>, u32> = (0..=255u8).map(|byte| (vec![byte], byte as u32)).collect();
+        for (merged_token_index, merged_token) in merged_tokens.iter().enumerate() {
+            ranks.insert(merged_token.as_bytes().to_vec(), 256 + merged_token_index as u32);
+        }
+        BpeTokenizer::from_ranks(ranks, pattern).unwrap()
+    }
+
+    /// Straightforward version of the merging, which rescans all pairs after each merge.
+    fn count_tokens_of_piece_naively(tokenizer: &BpeTokenizer, piece: &[u8]) -> usize {
+        let mut token_boundaries: Vec<usize> = (0..=piece.len()).collect();
+        loop {
+            let mut lowest_rank_merge: Option<(u32, usize)> = None;
+            for boundary_index in 0..token_boundaries.len().saturating_sub(2) {
+                if let Some(&rank) = tokenizer.ranks.get(&piece[token_boundaries[boundary_index]..token_boundaries[boundary_index + 2]])
+                    && lowest_rank_merge.is_none_or(|(lowest_rank, _)| rank < lowest_rank) {
+                    lowest_rank_merge = Some((rank, boundary_index));
+                }
+            }
+            match lowest_rank_merge {
+                Some((_, boundary_index)) => { token_boundaries.remove(boundary_index + 1); },
+                None => break
+            }
+        }
+        token_boundaries.len() - 1
+    }
+
+    #[test]
+    fn character_and_byte_counters_count_grapheme_clusters_and_bytes() {
+        assert_eq!(CharacterCounter.count("e\u{301}👍\r\n"), 3);
+        assert_eq!(ByteCounter.count("é👍\n"), 7);
+        assert_eq!(CharacterCounter.count(""), 0);
+    }
+
+    #[test]
+    fn pairs_with_the_lowest_rank_are_merged_first() {
+        let tokenizer = tokenizer_with_merges(&["ab", "bc", "abc"], "[a-z]+");
+        assert_eq!(tokenizer.count("abc"), 1);
+
+        // "bc" would be the better split, but "ab" has the lower rank
+        let tokenizer = tokenizer_with_merges(&["ab", "bc", "bcd"], "[a-z]+");
+        assert_eq!(tokenizer.count("abcd"), 3);
+
+        let tokenizer = tokenizer_with_merges(&["bc", "ab", "bcd"], "[a-z]+");
+        assert_eq!(tokenizer.count("abcd"), 2);
+    }
+
+    #[test]
+    fn equally_ranked_pairs_are_merged_from_the_left() {
+        let tokenizer = tokenizer_with_merges(&["aa", "aaaa"], "[a-z]+");
+        assert_eq!(tokenizer.count("aaa"), 2);
+        assert_eq!(tokenizer.count("aaaa"), 1);
+        assert_eq!(tokenizer.count("aaaaaaa"), 3);
+    }
+
+    #[test]
+    fn merging_matches_the_straightforward_version() {
+        let tokenizer = tokenizer_with_merges(&["ab", "ba", "aa", "abab", "bb", "aab", "abba", "baab", "ababab"], "[a-z]+");
+        let pieces = ["", "a", "ab", "aabbaabb", "abababababab", "bbbbabbbaaab", "abbaabbaabbaab", &"ab".repeat(500), &"aab".repeat(300)];
+        for piece in pieces {
+            assert_eq!(tokenizer.count_tokens_of_piece(piece.as_bytes()), count_tokens_of_piece_naively(&tokenizer, piece.as_bytes()), "piece \"{}\"", piece);
+        }
+    }
+
+    #[test]
+    fn text_is_split_with_the_pattern_before_merging() {
+        let tokenizer = tokenizer_with_merges(&["ab", " ab"], CL100K_PATTERN);
+        assert_eq!(tokenizer.count("ab ab"), 2);
+        assert_eq!(tokenizer.count("abab"), 2);
+
+        // text not covered by the pattern counts one token per byte
+        let tokenizer = tokenizer_with_merges(&["ab"], "ab");
+        assert_eq!(tokenizer.count("xaby!"), 4);
+    }
+
+    #[test]
+    fn vocabulary_files_are_read_in_the_tiktoken_format() {
+        let vocabulary_file_path = std::env::temp_dir().join(format!("folder_to_ai_tokenizer_{}.tiktoken", std::process::id()));
+        let vocabulary = ["a", "b", "ab"].iter().enumerate().map(|(rank, token)| format!("{} {}\n", BASE64_STANDARD.encode(token), rank)).collect::<String>();
+        std::fs::write(&vocabulary_file_path, vocabulary).unwrap();
+        let tokenizer = BpeTokenizer::from_tiktoken_file(vocabulary_file_path.to_str().unwrap());
+
+        std::fs::write(&vocabulary_file_path, "YQ== 0 extra\n").unwrap();
+        let invalid_tokenizer = BpeTokenizer::from_tiktoken_file(vocabulary_file_path.to_str().unwrap());
+        let _ = std::fs::remove_file(&vocabulary_file_path);
+
+        assert_eq!(tokenizer.unwrap().count("abab"), 2);
+        assert!(invalid_tokenizer.is_err_and(|error| error.contains("invalid line 1")));
+    }
+}
@@ -0,0 +1,202 @@
+use crate::file_info::FileInfo;
+use crate::folder_info::FolderInfo;
+use crate::skipped_subtree::SkippedSubtree;
+
+use serde::{Serialize, Deserialize};
+use size::Size;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Where the directory tree overview is placed among the messages, if anywhere.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum TreeOverviewPlacement {
+    #[default]
+    None,
+    /// At the end of the prologue, if it fits there; otherwise in dedicated messages.
+    Prologue,
+    /// In dedicated messages right after the prologue.
+    SeparateMessage
+}
+
+impl TreeOverviewPlacement {
+    pub const ALL: [TreeOverviewPlacement; 3] = [TreeOverviewPlacement::None, TreeOverviewPlacement::Prologue, TreeOverviewPlacement::SeparateMessage];
+
+    pub fn label(&self) -> &str {
+        match self {
+            TreeOverviewPlacement::None => "none",
+            TreeOverviewPlacement::Prologue => "in the first message",
+            TreeOverviewPlacement::SeparateMessage => "in a separate message"
+        }
+    }
+}
+
+#[derive(Default)]
+struct TreeNode<'a> {
+    children: BTreeMap<String, TreeNode<'a>>,
+    file_info: Option<&'a FileInfo>,
+    skipped_subtree: Option<&'a SkippedSubtree>
+}
+
+impl<'a> TreeNode<'a> {
+    fn node_at(&mut self, relative_path: &str) -> &mut TreeNode<'a> {
+        let mut node = self;
+        for component in Path::new(relative_path).components() {
+            node = node.children.entry(component.as_os_str().to_string_lossy().to_string()).or_default();
+        }
+        node
+    }
+
+    fn is_directory(&self) -> bool {
+        self.file_info.is_none()
+    }
+
+    /// Amount of files within the directory, and whether all of them are ignored.
+    fn count_files(&self) -> (usize, bool) {
+        if let Some(file_info) = self.file_info {
+            return (1, file_info.should_be_ignored);
+        }
+        if self.skipped_subtree.is_some() {
+            return (0, true);
+        }
+
+        let mut n_files = 0;
+        let mut all_files_are_ignored = true;
+        for child in self.children.values() {
+            let (n_child_files, all_child_files_are_ignored) = child.count_files();
+            n_files += n_child_files;
+            all_files_are_ignored &= all_child_files_are_ignored;
+        }
+        (n_files, all_files_are_ignored)
+    }
+}
+
+/// Status of a file: loaded, binary, too large, ignored, withheld: sensitive or failed.
+pub(crate) fn file_status(file_info: &FileInfo) -> &'static str {
+    if file_info.is_withheld_as_sensitive() {
+        "withheld: sensitive"
+    }
+    else if file_info.should_be_ignored {
+        "ignored"
+    }
+    else if file_info.has_content_loaded() {
+        "loaded"
+    }
+    else if file_info.is_binary {
+        "binary"
+    }
+    else if file_info.file_too_large {
+        "too large"
+    }
+    else {
+        "failed"
+    }
+}
+
+fn skipped_subtree_description(skipped_subtree: &SkippedSubtree) -> String {
+    match &skipped_subtree.summary {
+        Some(summary) => {
+            let approximation_str = if summary.is_partial { "over " } else { "~" };
+            format!("ignored, {}{} files, {}{}", approximation_str, summary.n_files, approximation_str, Size::from_bytes(summary.size_in_bytes))
+        },
+        None => String::from("ignored")
+    }
+}
+
+fn append_children_lines(node: &TreeNode, prefix: &str, lines: &mut String) {
+    let n_children = node.children.len();
+    for (child_index, (name, child)) in node.children.iter().enumerate() {
+        let is_last_child = child_index + 1 == n_children;
+        let branch = if is_last_child { "└── " } else { "├── " };
+
+        if let Some(file_info) = child.file_info {
+            *lines += &format!("{}{}{} ({}, {})\n", prefix, branch, name, Size::from_bytes(file_info.size_in_bytes), file_status(file_info));
+            continue;
+        }
+        if let Some(skipped_subtree) = child.skipped_subtree {
+            *lines += &format!("{}{}{}/ ({})\n", prefix, branch, name, skipped_subtree_description(skipped_subtree));
+            continue;
+        }
+
+        // directories without any file that is not ignored collapse to a single line
+        let (n_files, all_files_are_ignored) = child.count_files();
+        if all_files_are_ignored && n_files > 0 {
+            *lines += &format!("{}{}{}/ (ignored, {} files)\n", prefix, branch, name, n_files);
+            continue;
+        }
+
+        *lines += &format!("{}{}{}/\n", prefix, branch, name);
+        let child_prefix = format!("{}{}", prefix, if is_last_child { "    " } else { "│   " });
+        append_children_lines(child, &child_prefix, lines);
+    }
+}
+
+/// `tree`-style listing of the scanned folder, marking each file with its size and status (loaded, binary, too large, ignored, withheld: sensitive or failed);
+/// skipped subfolders and folders whose files are all ignored are shown as a single line.
+pub fn obtain_tree_overview(folder_info: &FolderInfo) -> String {
+    let mut root = TreeNode::default();
+    for file_info in &folder_info.file_infos {
+        root.node_at(&file_info.filepath).file_info = Some(file_info);
+    }
+    for skipped_subtree in &folder_info.skipped_subtrees {
+        let node = root.node_at(&skipped_subtree.path);
+        if node.is_directory() {
+            node.skipped_subtree = Some(skipped_subtree);
+        }
+    }
+
+    let folder_name = Path::new(&folder_info.folder_path).file_name().map(|folder_name| folder_name.to_string_lossy().to_string()).unwrap_or_else(|| folder_info.folder_path.clone());
+    let mut lines = format!("{}/\n", folder_name);
+    append_children_lines(&root, "", &mut lines);
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::skipped_subtree::SkippedSubtreeSummary;
+
+    fn file_info(path: &str, size_in_bytes: usize, is_ignored: bool, content: Option<&str>) -> FileInfo {
+        let mut file_info = FileInfo::new(path.to_string(), size_in_bytes, is_ignored);
+        file_info.file_content = content.map(str::to_string);
+        file_info
+    }
+
+    #[test]
+    fn files_are_listed_with_their_size_and_status() {
+        let mut logo = file_info("logo.png", 2048, false, None);
+        logo.is_binary = true;
+        let file_infos = vec![logo, file_info("src/main.rs", 120, false, Some("fn main() {}")), file_info("src/lib.rs", 80, false, None)];
+        let folder_info = FolderInfo{ folder_path: String::from("/home/user/project"), file_infos, skipped_subtrees: vec!(), size_in_bytes: 2248 };
+
+        let expected_tree_overview = format!("project/\n├── logo.png ({}, binary)\n└── src/\n    ├── lib.rs ({}, failed)\n    └── main.rs ({}, loaded)\n", Size::from_bytes(2048), Size::from_bytes(80), Size::from_bytes(120));
+        assert_eq!(obtain_tree_overview(&folder_info), expected_tree_overview);
+    }
+
+    #[test]
+    fn skipped_folders_and_folders_with_only_ignored_files_collapse_to_a_single_line() {
+        let file_infos = vec![
+            file_info("src/generated/a.rs", 10, true, None),
+            file_info("src/generated/nested/b.rs", 10, true, None),
+            file_info("src/main.rs", 10, false, Some("fn main() {}")),
+            file_info("notes.txt", 10, true, None)
+        ];
+        let mut target = SkippedSubtree::new(String::from("target"));
+        target.summary = Some(SkippedSubtreeSummary{ n_files: 10_000, size_in_bytes: 4096, is_partial: true });
+        let mut venv = SkippedSubtree::new(String::from("src/venv"));
+        venv.summary = Some(SkippedSubtreeSummary{ n_files: 3, size_in_bytes: 30, is_partial: false });
+        let skipped_subtrees = vec![SkippedSubtree::new(String::from("node_modules")), target, venv];
+        let folder_info = FolderInfo{ folder_path: String::from("project"), file_infos, skipped_subtrees, size_in_bytes: 10 };
+
+        let expected_tree_overview = [
+            String::from("project/"),
+            String::from("├── node_modules/ (ignored)"),
+            format!("├── notes.txt ({}, ignored)", Size::from_bytes(10)),
+            String::from("├── src/"),
+            String::from("│   ├── generated/ (ignored, 2 files)"),
+            format!("│   ├── main.rs ({}, loaded)", Size::from_bytes(10)),
+            format!("│   └── venv/ (ignored, ~3 files, ~{})", Size::from_bytes(30)),
+            format!("└── target/ (ignored, over 10000 files, over {})", Size::from_bytes(4096))
+        ].map(|line| line + "\n").concat();
+        assert_eq!(obtain_tree_overview(&folder_info), expected_tree_overview);
+    }
+}
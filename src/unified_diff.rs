@@ -0,0 +1,221 @@
+// the longest common subsequence of the lines in between the common beginning and end of two contents is only computed
+// up to this many pairs of lines; beyond that, contents are not compared line by line
+pub const MAXIMUM_N_LINE_PAIRS_COMPARED: usize = 4_000_000;
+
+// number of unchanged lines shown around the changes in each hunk
+const N_CONTEXT_LINES: usize = 3;
+
+/// What happens to a line when turning one content into another.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LineOperation {
+    Unchanged,
+    Removed,
+    Added
+}
+
+/// Operations turning `original_lines` into `new_lines` with the fewest removed and added lines, in order: each original line is either
+/// unchanged or removed, and each new line either unchanged or added; None if there are too many lines to compare.
+pub fn line_operations(original_lines: &[&str], new_lines: &[&str]) -> Option<Vec<LineOperation>> {
+    let n_common_first_lines = original_lines.iter().zip(new_lines).take_while(|(original_line, new_line)| original_line == new_line).count();
+    let n_common_last_lines = original_lines[n_common_first_lines..].iter().rev().zip(new_lines[n_common_first_lines..].iter().rev()).take_while(|(original_line, new_line)| original_line == new_line).count();
+    let changed_original_lines = &original_lines[n_common_first_lines..original_lines.len() - n_common_last_lines];
+    let changed_new_lines = &new_lines[n_common_first_lines..new_lines.len() - n_common_last_lines];
+    if changed_original_lines.len() * changed_new_lines.len() > MAXIMUM_N_LINE_PAIRS_COMPARED {
+        return None;
+    }
+
+    // lengths of the longest common subsequences of each suffix of the original lines and each suffix of the new ones
+    let row_length = changed_new_lines.len() + 1;
+    let mut common_lengths = vec![0u32; (changed_original_lines.len() + 1) * row_length];
+    for original_line_index in (0..changed_original_lines.len()).rev() {
+        for new_line_index in (0..changed_new_lines.len()).rev() {
+            common_lengths[original_line_index * row_length + new_line_index] = if changed_original_lines[original_line_index] == changed_new_lines[new_line_index] {
+                common_lengths[(original_line_index + 1) * row_length + new_line_index + 1] + 1
+            }
+            else {
+                common_lengths[(original_line_index + 1) * row_length + new_line_index].max(common_lengths[original_line_index * row_length + new_line_index + 1])
+            };
+        }
+    }
+
+    let mut line_operations = vec![LineOperation::Unchanged; n_common_first_lines];
+    let (mut original_line_index, mut new_line_index) = (0, 0);
+    while original_line_index < changed_original_lines.len() || new_line_index < changed_new_lines.len() {
+        if original_line_index == changed_original_lines.len() {
+            line_operations.push(LineOperation::Added);
+            new_line_index += 1;
+        }
+        else if new_line_index == changed_new_lines.len() {
+            line_operations.push(LineOperation::Removed);
+            original_line_index += 1;
+        }
+        else if changed_original_lines[original_line_index] == changed_new_lines[new_line_index] {
+            line_operations.push(LineOperation::Unchanged);
+            original_line_index += 1;
+            new_line_index += 1;
+        }
+        else if common_lengths[(original_line_index + 1) * row_length + new_line_index] >= common_lengths[original_line_index * row_length + new_line_index + 1] {
+            line_operations.push(LineOperation::Removed);
+            original_line_index += 1;
+        }
+        else {
+            line_operations.push(LineOperation::Added);
+            new_line_index += 1;
+        }
+    }
+    line_operations.extend(std::iter::repeat_n(LineOperation::Unchanged, n_common_last_lines));
+    Some(line_operations)
+}
+
+/// Unified diff turning `original_content` into `new_content`, with "--- a/<path>" and "+++ b/<path>" lines and hunks with three lines
+/// of context; empty if the contents are the same, and None if they have too many lines to compare.
+pub fn unified_diff(path: &str, original_content: &str, new_content: &str) -> Option<String> {
+    // lines keep their line break, so that a change to the line break at the end of the content is a change to the last line
+    let original_lines: Vec<&str> = original_content.split_inclusive('\n').collect();
+    let new_lines: Vec<&str> = new_content.split_inclusive('\n').collect();
+    let line_operations = line_operations(&original_lines, &new_lines)?;
+
+    let changed_operation_indices: Vec<usize> = line_operations.iter().enumerate().filter(|(_, line_operation)| **line_operation != LineOperation::Unchanged).map(|(operation_index, _)| operation_index).collect();
+    let Some(&first_changed_operation_index) = changed_operation_indices.first() else {
+        return Some(String::new());
+    };
+
+    // ranges of operations shown in each hunk; changes closer than twice the context are in the same hunk
+    let mut hunk_ranges: Vec<(usize, usize)> = vec![(first_changed_operation_index.saturating_sub(N_CONTEXT_LINES), first_changed_operation_index + 1)];
+    for &changed_operation_index in &changed_operation_indices[1..] {
+        let hunk_range = hunk_ranges.last_mut().unwrap();
+        if changed_operation_index <= hunk_range.1 + 2 * N_CONTEXT_LINES {
+            hunk_range.1 = changed_operation_index + 1;
+        }
+        else {
+            hunk_ranges.push((changed_operation_index - N_CONTEXT_LINES, changed_operation_index + 1));
+        }
+    }
+
+    let path = path.replace('\\', "/");
+    let mut diff = format!("--- a/{}\n+++ b/{}\n", path, path);
+    let (mut original_line_index, mut new_line_index, mut operation_index) = (0, 0, 0);
+    for (hunk_start, hunk_end) in hunk_ranges {
+        let hunk_end = (hunk_end + N_CONTEXT_LINES).min(line_operations.len());
+        for line_operation in &line_operations[operation_index..hunk_start] {
+            if *line_operation != LineOperation::Added { original_line_index += 1; }
+            if *line_operation != LineOperation::Removed { new_line_index += 1; }
+        }
+
+        let hunk_operations = &line_operations[hunk_start..hunk_end];
+        let n_original_lines = hunk_operations.iter().filter(|line_operation| **line_operation != LineOperation::Added).count();
+        let n_new_lines = hunk_operations.iter().filter(|line_operation| **line_operation != LineOperation::Removed).count();
+        // a range without lines starts at the line before it
+        diff += &format!("@@ -{},{} +{},{} @@\n", original_line_index + usize::from(n_original_lines > 0), n_original_lines, new_line_index + usize::from(n_new_lines > 0), n_new_lines);
+
+        for line_operation in hunk_operations {
+            let (prefix, line) = match line_operation {
+                LineOperation::Unchanged => (' ', original_lines[original_line_index]),
+                LineOperation::Removed => ('-', original_lines[original_line_index]),
+                LineOperation::Added => ('+', new_lines[new_line_index])
+            };
+            if *line_operation != LineOperation::Added { original_line_index += 1; }
+            if *line_operation != LineOperation::Removed { new_line_index += 1; }
+
+            diff.push(prefix);
+            diff += line;
+            if !line.ends_with('\n') {
+                diff += "\n\\ No newline at end of file\n";
+            }
+        }
+        operation_index = hunk_end;
+    }
+    Some(diff)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `original_content` with the diff applied, checking that its hunks' line numbers and removed and unchanged lines match it.
+    fn patched(original_content: &str, diff: &str) -> String {
+        // (prefix, line with its line break); a "\ No newline at end of file" line takes the line break off the line before it
+        let mut diff_lines: Vec<(char, String)> = vec!();
+        for diff_line in diff.split_inclusive('\n').skip(2) {
+            if diff_line.starts_with('\\') {
+                diff_lines.last_mut().unwrap().1.pop();
+            }
+            else {
+                diff_lines.push((diff_line.chars().next().unwrap(), diff_line[1..].to_string()));
+            }
+        }
+
+        let original_lines: Vec<&str> = original_content.split_inclusive('\n').collect();
+        let mut new_content = String::new();
+        let mut position = 0;
+        for (prefix, line) in diff_lines {
+            match prefix {
+                '@' => {
+                    let original_range = line.split(' ').nth(1).unwrap().trim_start_matches('-');
+                    let (start_line, n_lines) = original_range.split_once(',').unwrap();
+                    let start_line: usize = start_line.parse().unwrap();
+                    let hunk_position = if n_lines == "0" { start_line } else { start_line - 1 };
+                    new_content.extend(original_lines[position..hunk_position].iter().copied());
+                    position = hunk_position;
+                },
+                ' ' | '-' => {
+                    assert_eq!(original_lines[position], line);
+                    if prefix == ' ' {
+                        new_content += &line;
+                    }
+                    position += 1;
+                },
+                _ => new_content += &line
+            }
+        }
+        new_content.extend(original_lines[position..].iter().copied());
+        new_content
+    }
+
+    #[test]
+    fn writes_hunks_with_context() {
+        let original_content: String = (1..=20).map(|line_number| format!("{}\n", line_number)).collect();
+        let new_content = original_content.replace("\n2\n", "\ntwo\n").replace("\n17\n", "\n");
+        assert_eq!(unified_diff("src\\numbers.txt", &original_content, &new_content).unwrap(), concat!(
+            "--- a/src/numbers.txt\n+++ b/src/numbers.txt\n",
+            "@@ -1,5 +1,5 @@\n 1\n-2\n+two\n 3\n 4\n 5\n",
+            "@@ -14,7 +14,6 @@\n 14\n 15\n 16\n-17\n 18\n 19\n 20\n"
+        ));
+    }
+
+    #[test]
+    fn marks_lines_without_newline_at_end_of_file() {
+        assert_eq!(unified_diff("a.txt", "a\nb\n", "a\nb").unwrap(), "--- a/a.txt\n+++ b/a.txt\n@@ -1,2 +1,2 @@\n a\n-b\n+b\n\\ No newline at end of file\n");
+        assert_eq!(unified_diff("a.txt", "a", "b").unwrap(), "--- a/a.txt\n+++ b/a.txt\n@@ -1,1 +1,1 @@\n-a\n\\ No newline at end of file\n+b\n\\ No newline at end of file\n");
+        assert_eq!(unified_diff("a.txt", "", "a\n").unwrap(), "--- a/a.txt\n+++ b/a.txt\n@@ -0,0 +1,1 @@\n+a\n");
+    }
+
+    #[test]
+    fn diffs_apply_back_to_the_new_contents() {
+        let long_content: String = (1..=100).map(|line_number| format!("line {}\n", line_number)).collect();
+        let content_pairs = [
+            ("", "a\nb\n"),
+            ("a\nb\n", ""),
+            ("a\nb", "a\nb\n"),
+            ("a\nb\n", "a\nb"),
+            ("a\nb\nc\n", "c\nb\na\n"),
+            ("x\n\n\ny\n", "x\n\ny\n\n"),
+            (long_content.as_str(), &long_content.replace("line 3\n", "").replace("line 50\n", "line 50\nnew line\n").replace("line 100\n", "line 100"))
+        ];
+        for (original_content, new_content) in content_pairs {
+            let diff = unified_diff("a.txt", original_content, new_content).unwrap();
+            assert_eq!(patched(original_content, &diff), new_content, "diff:\n{}", diff);
+        }
+        assert_eq!(unified_diff("a.txt", &long_content, &long_content), Some(String::new()));
+    }
+
+    #[test]
+    fn gives_up_on_too_many_lines() {
+        let original_lines: Vec<String> = (0..2001).map(|line_number| format!("a{}", line_number)).collect();
+        let new_lines: Vec<String> = (0..2001).map(|line_number| format!("b{}", line_number)).collect();
+        let original_lines: Vec<&str> = original_lines.iter().map(String::as_str).collect();
+        let new_lines: Vec<&str> = new_lines.iter().map(String::as_str).collect();
+        assert_eq!(line_operations(&original_lines, &new_lines), None);
+        assert_eq!(line_operations(&original_lines, &original_lines).map(|line_operations| line_operations.len()), Some(2001));
+    }
+}
@@ -1,5 +1,6 @@
 use std::ffi::CString;
 use std::io;
+use winapi::um::wincon::{ATTACH_PARENT_PROCESS, AttachConsole};
 use winapi::um::winuser::{IDYES, MB_OK, MB_YESNO, MessageBoxA};
 use winreg::enums::*;
 use winreg::RegKey;
@@ -10,6 +11,13 @@ pub fn pause_cmd()
     io::stdin().read_line(&mut input).expect("Failed to read line");
 }
 
+pub fn attach_to_parent_console() -> bool
+{
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS) != 0
+    }
+}
+
 pub fn message_box(message: &str, title: &str)
 {
     let lp_text = CString::new(message).unwrap();